@@ -0,0 +1,176 @@
+use serde::{Deserialize, Serialize};
+
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+
+// password   cleartext
+// ntlm       NT hash, 32 hex digits
+// hash       any other hash, e.g. NetNTLMv2 or sha512crypt, in hashcat format
+// key        private key, e.g. an ssh key
+// token      API token, session cookie, JWT, ...
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CredentialKind {
+    Password,
+    Ntlm,
+    Hash,
+    Key,
+    Token,
+}
+
+impl CredentialKind {
+    pub fn parse(value: &str) -> Result<Self> {
+	match value {
+	    "password" => Ok(CredentialKind::Password),
+	    "ntlm" => Ok(CredentialKind::Ntlm),
+	    "hash" => Ok(CredentialKind::Hash),
+	    "key" => Ok(CredentialKind::Key),
+	    "token" => Ok(CredentialKind::Token),
+	    _ => Err(format!("unknown credential kind {}, use password, ntlm, hash, key or token", value).into()),
+	}
+    }
+
+    pub fn as_str(&self) -> &'static str {
+	match self {
+	    CredentialKind::Password => "password",
+	    CredentialKind::Ntlm => "ntlm",
+	    CredentialKind::Hash => "hash",
+	    CredentialKind::Key => "key",
+	    CredentialKind::Token => "token",
+	}
+    }
+}
+
+// Whether a credential works, for a credential as a whole it is valid once
+// any service accepted it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CredentialStatus {
+    Valid,
+    Invalid,
+    Unknown,
+}
+
+impl CredentialStatus {
+    pub fn parse(value: &str) -> Result<Self> {
+	match value {
+	    "valid" => Ok(CredentialStatus::Valid),
+	    "invalid" => Ok(CredentialStatus::Invalid),
+	    "unknown" => Ok(CredentialStatus::Unknown),
+	    _ => Err(format!("unknown credential status {}, use valid, invalid or unknown", value).into()),
+	}
+    }
+
+    pub fn as_str(&self) -> &'static str {
+	match self {
+	    CredentialStatus::Valid => "valid",
+	    CredentialStatus::Invalid => "invalid",
+	    CredentialStatus::Unknown => "unknown",
+	}
+    }
+
+    /// Parses the status column, anything unexpected is unknown.
+    pub fn from_column(value: &str) -> CredentialStatus {
+	Self::parse(value).unwrap_or(CredentialStatus::Unknown)
+    }
+}
+
+// A service a credential was tried against, identified like the service
+// table by (ip, port).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CredentialService {
+    pub ip: String,
+    pub port: u16,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub service_name: Option<String>,
+    pub status: CredentialStatus,
+    #[serde(default)]
+    pub verified: String,
+}
+
+impl CredentialService {
+    /// "10.0.0.1:445" or "[2001:db8::1]:22"
+    pub fn parse_target(target: &str) -> Result<(String, u16)> {
+	let (ip, port) = target.rsplit_once(':').ok_or_else(|| format!("{} is not ip:port", target))?;
+	let ip = ip.trim_start_matches('[').trim_end_matches(']');
+	if ip.parse::<std::net::IpAddr>().is_err() {
+	    return Err(format!("{} is not an address", ip).into());
+	}
+	Ok((ip.to_string(), port.parse()?))
+    }
+}
+
+// One entry of the creds db. username and realm (AD domain, Kerberos realm)
+// are empty if unknown, the same secret for the same account is stored once.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Credential {
+    #[serde(default)]
+    pub id: i64,
+    #[serde(default)]
+    pub username: String,
+    #[serde(default)]
+    pub realm: String,
+    pub kind: CredentialKind,
+    pub secret: String,
+    // where it was found: a file, a host, "secretsdump dc01", ...
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    #[serde(default = "unknown")]
+    pub status: CredentialStatus,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub services: Vec<CredentialService>,
+}
+
+fn unknown() -> CredentialStatus {
+    CredentialStatus::Unknown
+}
+
+impl Credential {
+    pub fn new(username: &str, realm: &str, kind: CredentialKind, secret: &str) -> Self {
+	Self {
+	    id: 0,
+	    username: username.to_string(),
+	    realm: realm.to_string(),
+	    kind,
+	    secret: secret.to_string(),
+	    source: None,
+	    status: CredentialStatus::Unknown,
+	    services: vec![],
+	}
+    }
+
+    /// DOMAIN\user, or just user without a realm.
+    pub fn account(&self) -> String {
+	if self.realm.is_empty() {
+	    self.username.clone()
+	} else {
+	    format!("{}\\{}", self.realm, self.username)
+	}
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{from_str, to_string};
+
+
+    #[test]
+    fn parse_credential() {
+	let json = r#"{"username":"administrator","realm":"CORP","kind":"ntlm","secret":"31d6cfe0d16ae931b73c59d7e0c089c0","services":[{"ip":"10.0.0.5","port":445,"status":"valid"}]}"#;
+	let credential: Credential = from_str(json).unwrap();
+	assert!(credential.kind == CredentialKind::Ntlm);
+	assert!(credential.status == CredentialStatus::Unknown);
+	assert!(credential.account() == "CORP\\administrator");
+	assert!(credential.services[0].status == CredentialStatus::Valid);
+	let again: Credential = from_str(&to_string(&credential).unwrap()).unwrap();
+	assert!(again == credential);
+
+	assert!(CredentialService::parse_target("10.0.0.5:445").unwrap() == ("10.0.0.5".to_string(), 445));
+	assert!(CredentialService::parse_target("[2001:db8::1]:22").unwrap() == ("2001:db8::1".to_string(), 22));
+	assert!(CredentialService::parse_target("dc01:445").is_err());
+	assert!(CredentialKind::parse("cookie").is_err());
+    }
+}
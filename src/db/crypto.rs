@@ -0,0 +1,120 @@
+use aes_gcm_siv::{Aes256GcmSiv, Key, KeyInit, Nonce};
+use aes_gcm_siv::aead::{Aead, Payload};
+use argon2::Argon2;
+
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+// Marks an encrypted column value, followed by the hex of the ciphertext.
+// Bumped if the format ever changes.
+pub const PREFIX: &str = "pendb3:";
+
+pub const SALT_LEN: usize = 16;
+
+// Associated data a value is encrypted under, it only decrypts in a column
+// of the same kind. Credential secrets and cracked hashes share one so that
+// credential.secret = cracked.hash still joins.
+pub const SECRET: &str = "secret";
+pub const NOTE: &str = "note";
+pub const CHECK: &str = "check";
+
+// Length of the authentication tag at the end of every ciphertext
+const TAG_LEN: usize = 16;
+
+
+// AES-256-GCM-SIV with a key derived from the passphrase by Argon2id.
+//
+// Every value is encrypted under the same fixed nonce. GCM-SIV is built to
+// survive nonce reuse: it derives the IV from the plaintext itself, so the
+// only thing given away is which rows hold equal secrets. That is exactly
+// what keeps the unique indexes and the joins of the creds db working (a
+// cracked hash finds its credential). The associated data ties a value to
+// the kind of column it was written to.
+pub struct Cipher {
+    aead: Aes256GcmSiv,
+}
+
+impl Cipher {
+    pub fn new(passphrase: &str, salt: &[u8]) -> Result<Self> {
+	let mut key = [0u8; 32];
+	Argon2::default()
+	    .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+	    .map_err(|e| format!("key derivation failed: {}", e))?;
+	Ok(Self { aead: Aes256GcmSiv::new(Key::<Aes256GcmSiv>::from_slice(&key)) })
+    }
+
+    pub fn salt() -> Result<Vec<u8>> {
+	let mut salt = vec![0u8; SALT_LEN];
+	getrandom::getrandom(&mut salt).map_err(|e| format!("no randomness for the salt: {}", e))?;
+	Ok(salt)
+    }
+
+    pub fn encrypt(&self, plaintext: &str, aad: &str) -> Result<String> {
+	let payload = Payload { msg: plaintext.as_bytes(), aad: aad.as_bytes() };
+	let ciphertext = self.aead.encrypt(&Nonce::default(), payload)
+	    .map_err(|_| "encryption failed")?;
+	Ok(format!("{}{}", PREFIX, to_hex(&ciphertext)))
+    }
+
+    /// Fails on a wrong passphrase, a tampered value or one moved over from
+    /// a column of another kind.
+    pub fn decrypt(&self, value: &str, aad: &str) -> Result<String> {
+	let ciphertext = value.strip_prefix(PREFIX)
+	    .and_then(from_hex)
+	    .filter(|bytes| bytes.len() >= TAG_LEN)
+	    .ok_or("not an encrypted value")?;
+	let payload = Payload { msg: ciphertext.as_slice(), aad: aad.as_bytes() };
+	let plaintext = self.aead.decrypt(&Nonce::default(), payload)
+	    .map_err(|_| "decryption failed, wrong passphrase or a modified database")?;
+	Ok(String::from_utf8(plaintext)?)
+    }
+}
+
+pub fn is_encrypted(value: &str) -> bool {
+    value.starts_with(PREFIX)
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+	return None;
+    }
+    (0..hex.len())
+	.step_by(2)
+	.map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+	.collect()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+
+    #[test]
+    fn encrypt_decrypt() {
+	let salt = Cipher::salt().unwrap();
+	let cipher = Cipher::new("correct horse", &salt).unwrap();
+	let encrypted = cipher.encrypt("Summer2022!", SECRET).unwrap();
+	assert!(is_encrypted(&encrypted));
+	assert!(!encrypted.contains("Summer"));
+	assert!(cipher.decrypt(&encrypted, SECRET).unwrap() == "Summer2022!");
+	// deterministic, for the unique indexes
+	assert!(cipher.encrypt("Summer2022!", SECRET).unwrap() == encrypted);
+	assert!(cipher.encrypt("Summer2023!", SECRET).unwrap() != encrypted);
+
+	let wrong = Cipher::new("battery staple", &salt).unwrap();
+	assert!(wrong.decrypt(&encrypted, SECRET).is_err());
+	let last = if encrypted.ends_with('0') { "1" } else { "0" };
+	let tampered = format!("{}{}", &encrypted[..encrypted.len() - 1], last);
+	assert!(cipher.decrypt(&tampered, SECRET).is_err());
+	assert!(cipher.decrypt("Summer2022!", SECRET).is_err());
+
+	// bound to the kind of column it was encrypted for
+	assert!(cipher.encrypt("Summer2022!", NOTE).unwrap() != encrypted);
+	assert!(cipher.decrypt(&encrypted, NOTE).is_err());
+    }
+}
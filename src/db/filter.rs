@@ -0,0 +1,616 @@
+use std::fmt;
+
+use sqlite::Value;
+
+use super::sqlite::canonical_ip;
+
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+
+// Filter expressions of the list and targets commands:
+//
+//   port in (80,443) and service ~ "http" and product contains "Apache" and tag:prod and seen>2026-10-01
+//
+// Comparisons are field op value, with the operators
+//   = !=            any field
+//   < <= > >=       numbers and times
+//   ~               text, case-insensitive substring
+//   contains        text, case-sensitive substring
+//   in (a, b, ...)  text and numbers
+// combined with and, or, not and parentheses. tag:<tag> matches rows
+// carrying the tag. Values are single words or "quoted". Times compare
+// with the precision given, seen=2026-10-01 is that whole day.
+//
+// A filter compiles to the WHERE clause of one table, values are bound as
+// parameters.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Filter {
+    And(Box<Filter>, Box<Filter>),
+    Or(Box<Filter>, Box<Filter>),
+    Not(Box<Filter>),
+    Compare(Comparison),
+    In(Comparison, Vec<String>),
+    Tag(String),
+}
+
+// field op value, position is where the field starts, for errors
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Comparison {
+    pub field: String,
+    pub op: Op,
+    pub value: String,
+    pub position: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Like,
+    Contains,
+    In,
+}
+
+impl Op {
+    fn parse(value: &str) -> Option<Self> {
+	match value {
+	    "=" | "==" => Some(Op::Eq),
+	    "!=" => Some(Op::Ne),
+	    "<" => Some(Op::Lt),
+	    "<=" => Some(Op::Le),
+	    ">" => Some(Op::Gt),
+	    ">=" => Some(Op::Ge),
+	    "~" => Some(Op::Like),
+	    _ => None,
+	}
+    }
+
+    fn sql(&self) -> &'static str {
+	match self {
+	    Op::Eq => "=",
+	    Op::Ne => "!=",
+	    Op::Lt => "<",
+	    Op::Le => "<=",
+	    Op::Gt => ">",
+	    Op::Ge => ">=",
+	    Op::Like => "LIKE",
+	    Op::Contains => "contains",
+	    Op::In => "IN",
+	}
+    }
+}
+
+impl fmt::Display for Op {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+	match self {
+	    Op::Like => write!(f, "~"),
+	    Op::In => write!(f, "in"),
+	    op => write!(f, "{}", op.sql()),
+	}
+    }
+}
+
+// What the values of a field are, decides the operators it takes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Text,
+    // text, values are canonicalized like the ip columns
+    Ip,
+    Number,
+    // UTC "YYYY-MM-DD HH:MM:SS"
+    Time,
+    // 0 or 1, written as true/false, yes/no or 1/0
+    Bool,
+}
+
+// The tables a filter can select rows of.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Table {
+    Service,
+    Domain,
+    Script,
+}
+
+impl Table {
+    pub fn parse(value: &str) -> Result<Self> {
+	match value {
+	    "services" | "service" => Ok(Table::Service),
+	    "domains" | "domain" => Ok(Table::Domain),
+	    "scripts" | "script" => Ok(Table::Script),
+	    _ => Err(format!("unknown table {}, use services, domains or scripts", value).into()),
+	}
+    }
+
+    pub fn as_str(&self) -> &'static str {
+	match self {
+	    Table::Service => "services",
+	    Table::Domain => "domains",
+	    Table::Script => "scripts",
+	}
+    }
+
+    /// FROM clause, the columns below refer to its aliases.
+    pub fn from(&self) -> &'static str {
+	match self {
+	    Table::Service => "service AS s",
+	    Table::Domain => "domain AS d",
+	    // scripts are filtered by the service they ran against too
+	    Table::Script => "script AS c LEFT JOIN service AS s ON s.ip = c.ip AND s.port = c.port",
+	}
+    }
+
+    /// Id of the selected rows.
+    pub fn id(&self) -> &'static str {
+	match self {
+	    Table::Service => "s.id",
+	    Table::Domain => "d.id",
+	    Table::Script => "c.id",
+	}
+    }
+
+    // entity_tag entity and id the tags of a row hang on
+    fn tagged(&self) -> (&'static str, &'static str) {
+	match self {
+	    Table::Service | Table::Script => ("service", "s.id"),
+	    Table::Domain => ("domain", "d.id"),
+	}
+    }
+
+    // field name, column and kind
+    fn fields(&self) -> &'static [(&'static str, &'static str, Kind)] {
+	match self {
+	    Table::Service => &[
+		("ip", "s.ip", Kind::Ip),
+		("port", "s.port", Kind::Number),
+		("protocol", "s.protocol", Kind::Text),
+		("service", "s.service_name", Kind::Text),
+		("product", "s.service_product", Kind::Text),
+		("state", "s.state", Kind::Text),
+		("scope", "s.scope", Kind::Text),
+		("ssl", "coalesce(s.tunnel = 'ssl', 0)", Kind::Bool),
+		("first_seen", "s.first_seen", Kind::Time),
+		("last_seen", "s.last_seen", Kind::Time),
+		("seen", "s.last_seen", Kind::Time),
+	    ],
+	    Table::Domain => &[
+		("domain", "d.domain", Kind::Text),
+		("name", "d.domain", Kind::Text),
+		("ip", "d.ip", Kind::Ip),
+		("cidr", "d.cidr", Kind::Text),
+		("asn", "d.asn", Kind::Number),
+		("source", "d.source", Kind::Text),
+		("tool", "d.tool", Kind::Text),
+		("wildcard", "d.wildcard", Kind::Bool),
+		("scope", "d.scope", Kind::Text),
+		("first_seen", "d.first_seen", Kind::Time),
+		("last_seen", "d.last_seen", Kind::Time),
+		("seen", "d.last_seen", Kind::Time),
+	    ],
+	    Table::Script => &[
+		("ip", "c.ip", Kind::Ip),
+		("port", "c.port", Kind::Number),
+		("vhost", "c.vhost", Kind::Text),
+		("script", "c.script_id", Kind::Text),
+		("output", "c.script_output", Kind::Text),
+		("service", "s.service_name", Kind::Text),
+		("product", "s.service_product", Kind::Text),
+	    ],
+	}
+    }
+}
+
+impl fmt::Display for Table {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+	write!(f, "{}", self.as_str())
+    }
+}
+
+impl Filter {
+    pub fn parse(text: &str) -> Result<Self> {
+	let mut parser = Parser {
+	    tokens: tokenize(text)?,
+	    next: 0,
+	    end: text.chars().count(),
+	};
+	let filter = parser.or()?;
+	match parser.peek() {
+	    None => Ok(filter),
+	    Some(token) => Err(error(token.position, format!("expected and, or or the end of the filter, found {}", token))),
+	}
+    }
+
+    /// WHERE clause and its parameters, :f0, :f1, ...
+    pub fn compile(&self, table: Table) -> Result<(String, Vec<(String, Value)>)> {
+	let mut params = vec![];
+	let sql = self.sql(table, &mut params)?;
+	Ok((sql, params))
+    }
+
+    fn sql(&self, table: Table, params: &mut Vec<(String, Value)>) -> Result<String> {
+	Ok(match self {
+	    Filter::And(left, right) => format!("({} AND {})", left.sql(table, params)?, right.sql(table, params)?),
+	    Filter::Or(left, right) => format!("({} OR {})", left.sql(table, params)?, right.sql(table, params)?),
+	    Filter::Not(filter) => format!("NOT ({})", filter.sql(table, params)?),
+	    Filter::Tag(tag) => {
+		let (entity, id) = table.tagged();
+		format!(
+		    "{} IN (SELECT e.entity_id FROM entity_tag AS e JOIN tag AS t ON t.id = e.tag_id WHERE e.entity = '{}' AND t.name = {})",
+		    id, entity, param(params, Value::String(tag.clone())))
+	    },
+	    Filter::Compare(comparison) => {
+		let (column, kind) = field(table, comparison)?;
+		let value = value(kind, comparison, &comparison.value)?;
+		match (kind, comparison.op) {
+		    (Kind::Text | Kind::Ip, Op::Like) => {
+			let escaped = comparison.value.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+			format!("coalesce({}, '') LIKE {} ESCAPE '\\'", column, param(params, Value::String(format!("%{}%", escaped))))
+		    },
+		    (Kind::Text | Kind::Ip, Op::Contains) => format!("instr(coalesce({}, ''), {}) > 0", column, param(params, value)),
+		    // compared with the precision of the value
+		    (Kind::Time, op) => {
+			let name = param(params, value);
+			format!("substr({}, 1, length({})) {} {}", column, name, op.sql(), name)
+		    },
+		    // a missing text is the empty string, so not a=b is a!=b
+		    (Kind::Text | Kind::Ip, op) => format!("coalesce({}, '') {} {}", column, op.sql(), param(params, value)),
+		    (_, op) => format!("{} {} {}", column, op.sql(), param(params, value)),
+		}
+	    },
+	    Filter::In(comparison, values) => {
+		let (column, kind) = field(table, comparison)?;
+		let mut names = vec![];
+		for item in values {
+		    let item = value(kind, comparison, item)?;
+		    names.push(param(params, item));
+		}
+		match kind {
+		    Kind::Text | Kind::Ip => format!("coalesce({}, '') IN ({})", column, names.join(", ")),
+		    _ => format!("{} IN ({})", column, names.join(", ")),
+		}
+	    },
+	})
+    }
+}
+
+// Column and kind of the compared field, if the operator fits it.
+fn field(table: Table, comparison: &Comparison) -> Result<(&'static str, Kind)> {
+    let fields = table.fields();
+    let (_, column, kind) = fields.iter()
+	.find(|(name, _, _)| name.eq_ignore_ascii_case(&comparison.field))
+	.ok_or_else(|| {
+	    let names: Vec<&str> = fields.iter().map(|(name, _, _)| *name).collect();
+	    error(comparison.position, format!("unknown field {} of {}, use {} or tag:<tag>", comparison.field, table, names.join(", ")))
+	})?;
+    let fits = match kind {
+	Kind::Text | Kind::Ip => matches!(comparison.op, Op::Eq | Op::Ne | Op::Like | Op::Contains | Op::In),
+	Kind::Number => !matches!(comparison.op, Op::Like | Op::Contains),
+	Kind::Time => !matches!(comparison.op, Op::Like | Op::Contains | Op::In),
+	Kind::Bool => matches!(comparison.op, Op::Eq | Op::Ne),
+    };
+    if !fits {
+	let ops = match kind {
+	    Kind::Text | Kind::Ip => "= != ~ contains or in",
+	    Kind::Number => "= != < <= > >= or in",
+	    Kind::Time => "= != < <= > or >=",
+	    Kind::Bool => "= or !=",
+	};
+	return Err(error(comparison.position, format!("{} does not work on {}, use {}", comparison.op, comparison.field, ops)));
+    }
+    Ok((column, *kind))
+}
+
+// The value as the column stores it.
+fn value(kind: Kind, comparison: &Comparison, value: &str) -> Result<Value> {
+    match kind {
+	Kind::Text => Ok(Value::String(value.to_string())),
+	Kind::Ip => Ok(Value::String(canonical_ip(value).map_or_else(|| value.to_string(), |(ip, _)| ip))),
+	Kind::Number => value.parse()
+	    .map(Value::Integer)
+	    .map_err(|_| error(comparison.position, format!("{} is a number, not {:?}", comparison.field, value))),
+	Kind::Time => parse_time(value)
+	    .map(Value::String)
+	    .map_err(|e| error(comparison.position, format!("{}: {}", comparison.field, e))),
+	Kind::Bool => match value.to_lowercase().as_str() {
+	    "true" | "yes" | "1" => Ok(Value::Integer(1)),
+	    "false" | "no" | "0" => Ok(Value::Integer(0)),
+	    _ => Err(error(comparison.position, format!("{} is true or false, not {:?}", comparison.field, value))),
+	},
+    }
+}
+
+fn param(params: &mut Vec<(String, Value)>, value: Value) -> String {
+    let name = format!(":f{}", params.len());
+    params.push((name.clone(), value));
+    name
+}
+
+fn error(position: usize, message: String) -> Box<dyn std::error::Error> {
+    format!("invalid filter at column {}: {}", position + 1, message).into()
+}
+
+/// "2026-10-01", "2026-10-01 12:00" or "2026-10-01T12:00:00", returned in
+/// the form of the time columns.
+pub fn parse_time(value: &str) -> Result<String> {
+    let time = value.trim().replacen('T', " ", 1);
+    let valid = time.char_indices().all(|(i, c)| match i {
+	4 | 7 => c == '-',
+	10 => c == ' ',
+	13 | 16 => c == ':',
+	_ => c.is_ascii_digit(),
+    });
+    if !valid || ![10, 13, 16, 19].contains(&time.len()) {
+	return Err(format!("invalid time {:?}, use YYYY-MM-DD or YYYY-MM-DD HH:MM:SS", value).into());
+    }
+    Ok(time)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum TokenKind {
+    // a bare word, keywords included
+    Word(String),
+    // "quoted", never a keyword
+    Quoted(String),
+    Op(Op),
+    Open,
+    Close,
+    Comma,
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    kind: TokenKind,
+    // in chars
+    position: usize,
+}
+
+impl Token {
+    fn is_keyword(&self, keyword: &str) -> bool {
+	matches!(&self.kind, TokenKind::Word(word) if word.eq_ignore_ascii_case(keyword))
+    }
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+	match &self.kind {
+	    TokenKind::Word(word) => write!(f, "{}", word),
+	    TokenKind::Quoted(value) => write!(f, "{:?}", value),
+	    TokenKind::Op(op) => write!(f, "{}", op),
+	    TokenKind::Open => write!(f, "("),
+	    TokenKind::Close => write!(f, ")"),
+	    TokenKind::Comma => write!(f, ","),
+	}
+    }
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = vec![];
+    let mut i = 0;
+    while i < chars.len() {
+	let position = i;
+	let kind = match chars[i] {
+	    c if c.is_whitespace() => {
+		i += 1;
+		continue;
+	    },
+	    '(' => TokenKind::Open,
+	    ')' => TokenKind::Close,
+	    ',' => TokenKind::Comma,
+	    '"' => {
+		let mut value = String::new();
+		i += 1;
+		loop {
+		    match chars.get(i) {
+			None => return Err(error(position, "unterminated string".into())),
+			Some('"') => break,
+			Some('\\') if chars.get(i + 1).is_some() => {
+			    value.push(chars[i + 1]);
+			    i += 2;
+			},
+			Some(c) => {
+			    value.push(*c);
+			    i += 1;
+			},
+		    }
+		}
+		TokenKind::Quoted(value)
+	    },
+	    '=' | '!' | '<' | '>' | '~' => {
+		let two: String = chars[i..chars.len().min(i + 2)].iter().collect();
+		let (op, len) = match Op::parse(&two) {
+		    Some(op) if two.len() == 2 => (op, 2),
+		    _ => (Op::parse(&chars[i].to_string()).ok_or_else(|| error(position, format!("unknown operator {}", chars[i])))?, 1),
+		};
+		i += len - 1;
+		TokenKind::Op(op)
+	    },
+	    _ => {
+		let start = i;
+		while i < chars.len() && !chars[i].is_whitespace() && !"()=!<>~,\"".contains(chars[i]) {
+		    i += 1;
+		}
+		tokens.push(Token {
+		    kind: TokenKind::Word(chars[start..i].iter().collect()),
+		    position,
+		});
+		continue;
+	    },
+	};
+	tokens.push(Token {
+	    kind,
+	    position,
+	});
+	i += 1;
+    }
+    Ok(tokens)
+}
+
+// or   := and ("or" and)*
+// and  := not ("and" not)*
+// not  := "not" not | "(" or ")" | term
+// term := tag:<tag> | field op value | field contains value | field in (value, ...)
+struct Parser {
+    tokens: Vec<Token>,
+    next: usize,
+    // length of the text, where errors at the end point to
+    end: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+	self.tokens.get(self.next)
+    }
+
+    fn advance(&mut self, expected: &str) -> Result<Token> {
+	match self.tokens.get(self.next) {
+	    Some(token) => {
+		self.next += 1;
+		Ok(token.clone())
+	    },
+	    None => Err(error(self.end, format!("expected {}, found the end of the filter", expected))),
+	}
+    }
+
+    fn or(&mut self) -> Result<Filter> {
+	let mut filter = self.and()?;
+	while self.peek().is_some_and(|token| token.is_keyword("or")) {
+	    self.next += 1;
+	    filter = Filter::Or(Box::new(filter), Box::new(self.and()?));
+	}
+	Ok(filter)
+    }
+
+    fn and(&mut self) -> Result<Filter> {
+	let mut filter = self.not()?;
+	while self.peek().is_some_and(|token| token.is_keyword("and")) {
+	    self.next += 1;
+	    filter = Filter::And(Box::new(filter), Box::new(self.not()?));
+	}
+	Ok(filter)
+    }
+
+    fn not(&mut self) -> Result<Filter> {
+	let token = self.advance("a comparison")?;
+	if token.is_keyword("not") {
+	    return Ok(Filter::Not(Box::new(self.not()?)));
+	}
+	if token.kind == TokenKind::Open {
+	    let filter = self.or()?;
+	    let close = self.advance(")")?;
+	    if close.kind != TokenKind::Close {
+		return Err(error(close.position, format!("expected ), found {}", close)));
+	    }
+	    return Ok(filter);
+	}
+	let field = match &token.kind {
+	    TokenKind::Word(word) if !["and", "or", "in", "contains"].iter().any(|keyword| token.is_keyword(keyword)) => word.clone(),
+	    _ => return Err(error(token.position, format!("expected a field, tag:<tag>, not or (, found {}", token))),
+	};
+	if let Some(tag) = field.strip_prefix("tag:") {
+	    if tag.is_empty() {
+		return Err(error(token.position, "tag: needs a tag".into()));
+	    }
+	    return Ok(Filter::Tag(tag.to_string()));
+	}
+
+	let op_token = self.advance(&format!("an operator after {}", field))?;
+	let op = match &op_token.kind {
+	    TokenKind::Op(op) => *op,
+	    _ if op_token.is_keyword("contains") => Op::Contains,
+	    _ if op_token.is_keyword("in") => Op::In,
+	    _ => return Err(error(op_token.position, format!("expected an operator after {}, found {}", field, op_token))),
+	};
+	let mut comparison = Comparison {
+	    field,
+	    op,
+	    value: String::new(),
+	    position: token.position,
+	};
+	if op != Op::In {
+	    comparison.value = self.value(op)?;
+	    return Ok(Filter::Compare(comparison));
+	}
+
+	let open = self.advance("( after in")?;
+	if open.kind != TokenKind::Open {
+	    return Err(error(open.position, format!("expected ( after in, found {}", open)));
+	}
+	let mut values = vec![self.value(op)?];
+	loop {
+	    let token = self.advance(", or )")?;
+	    match token.kind {
+		TokenKind::Comma => values.push(self.value(op)?),
+		TokenKind::Close => break,
+		_ => return Err(error(token.position, format!("expected , or ), found {}", token))),
+	    }
+	}
+	Ok(Filter::In(comparison, values))
+    }
+
+    fn value(&mut self, op: Op) -> Result<String> {
+	let token = self.advance(&format!("a value after {}", op))?;
+	match token.kind {
+	    TokenKind::Word(word) => Ok(word),
+	    TokenKind::Quoted(value) => Ok(value),
+	    _ => Err(error(token.position, format!("expected a value after {}, found {}", op, token))),
+	}
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+
+    #[test]
+    fn parse_filter() {
+	let filter = Filter::parse(r#"port in (80,443) and service ~ "http" and product contains "Apache" and tag:prod and seen>2026-10-01"#).unwrap();
+	let (sql, params) = filter.compile(Table::Service).unwrap();
+	assert!(sql == "((((s.port IN (:f0, :f1) AND coalesce(s.service_name, '') LIKE :f2 ESCAPE '\\') \
+	    AND instr(coalesce(s.service_product, ''), :f3) > 0) \
+	    AND s.id IN (SELECT e.entity_id FROM entity_tag AS e JOIN tag AS t ON t.id = e.tag_id WHERE e.entity = 'service' AND t.name = :f4)) \
+	    AND substr(s.last_seen, 1, length(:f5)) > :f5)");
+	assert!(params == vec![
+	    (":f0".to_string(), Value::Integer(80)),
+	    (":f1".to_string(), Value::Integer(443)),
+	    (":f2".to_string(), Value::String("%http%".into())),
+	    (":f3".to_string(), Value::String("Apache".into())),
+	    (":f4".to_string(), Value::String("prod".into())),
+	    (":f5".to_string(), Value::String("2026-10-01".into())),
+	]);
+
+	// and binds tighter than or
+	let filter = Filter::parse("not ssl=true or port=80 and ip = 2001:db8:0::1").unwrap();
+	let (sql, params) = filter.compile(Table::Service).unwrap();
+	assert!(sql == "(NOT (coalesce(s.tunnel = 'ssl', 0) = :f0) OR (s.port = :f1 AND coalesce(s.ip, '') = :f2))");
+	assert!(params[2].1 == Value::String("2001:db8::1".into()));
+	let filter = Filter::parse(r#"output ~ "50%_off\"""#).unwrap();
+	let (_, params) = filter.compile(Table::Script).unwrap();
+	assert!(params[0].1 == Value::String("%50\\%\\_off\"%".into()));
+    }
+
+    #[test]
+    fn filter_errors() {
+	let message = |text: &str, table: Table| -> String {
+	    Filter::parse(text).and_then(|filter| filter.compile(table)).unwrap_err().to_string()
+	};
+	assert!(message("port in (80,", Table::Service) == "invalid filter at column 13: expected a value after in, found the end of the filter");
+	assert!(message("port = 80 443", Table::Service) == "invalid filter at column 11: expected and, or or the end of the filter, found 443");
+	assert!(message("(port = 80", Table::Service) == "invalid filter at column 11: expected ), found the end of the filter");
+	assert!(message("product = \"Apache", Table::Service) == "invalid filter at column 11: unterminated string");
+	assert!(message("port = http", Table::Service) == "invalid filter at column 1: port is a number, not \"http\"");
+	assert!(message("port ~ 80", Table::Service) == "invalid filter at column 1: ~ does not work on port, use = != < <= > >= or in");
+	assert!(message("and port = 80", Table::Service) == "invalid filter at column 1: expected a field, tag:<tag>, not or (, found and");
+	assert!(message("seen > yesterday", Table::Domain).starts_with("invalid filter at column 1: seen: invalid time"));
+	assert!(message("service = http", Table::Domain).starts_with("invalid filter at column 1: unknown field service of domains, use domain, name, ip"));
+	assert!(message("port ! 80", Table::Service) == "invalid filter at column 6: unknown operator !");
+	assert!(parse_time("2026-10-05T09:30").unwrap() == "2026-10-05 09:30");
+	assert!(parse_time("2026-10-5").is_err());
+    }
+}
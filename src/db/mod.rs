@@ -1,2 +1,7 @@
-
 pub mod sqlite;
+pub mod scope;
+pub mod tag;
+pub mod credential;
+pub mod crypto;
+pub mod filter;
+pub mod schema;
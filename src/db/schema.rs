@@ -0,0 +1,861 @@
+use std::collections::BTreeMap;
+
+use sqlite::{Connection, Value};
+
+use super::sqlite::canonical_ip;
+
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+// One change of the schema. Every step also works on a database that
+// already has it: databases from before the version was kept in
+// user_version are at 0 with whatever the release that made them had.
+enum Step {
+    Sql(&'static str),
+    // table, column and its definition, ALTER TABLE has no IF NOT EXISTS
+    AddColumn(&'static str, &'static str, &'static str),
+    // gives the table an id INTEGER PRIMARY KEY, set to the current rowids
+    AddId(&'static str),
+    // table and column that may be NULL from now on
+    DropNotNull(&'static str, &'static str),
+    // whatever SQL alone cannot do
+    Fn(fn(&Connection) -> Result<()>),
+}
+
+// MIGRATIONS[i] takes a database from version i to i + 1. Only ever append,
+// a released migration is never changed.
+const MIGRATIONS: &[&[Step]] = &[
+    // the schema before versions, as of the host table with ids. Older
+    // databases get their hosts first, which changes nothing on any other,
+    // so it could join this migration after its release.
+    &[
+	Step::Fn(add_host_ids),
+	Step::Sql(SCHEMA_V1),
+	Step::AddColumn("domain", "scope", "TEXT NOT NULL DEFAULT 'unknown'"),
+	Step::AddColumn("service", "scope", "TEXT NOT NULL DEFAULT 'unknown'"),
+	Step::AddColumn("host", "scope", "TEXT NOT NULL DEFAULT 'unknown'"),
+	Step::AddColumn("host", "shared_approved", "INTEGER NOT NULL DEFAULT 0"),
+    ],
+    // UTC import times for target lists, ssl if the service speaks TLS
+    &[
+	Step::AddColumn("domain", "first_seen", "TEXT"),
+	Step::AddColumn("domain", "last_seen", "TEXT"),
+	Step::AddColumn("service", "tunnel", "TEXT"),
+	Step::AddColumn("service", "first_seen", "TEXT"),
+	Step::AddColumn("service", "last_seen", "TEXT"),
+	Step::AddColumn("host", "first_seen", "TEXT"),
+    ],
+    &[Step::Sql(SCAN_TABLES)],
+    &[
+	Step::Sql(HISTORY_TABLE),
+	Step::AddColumn("service", "service_version", "TEXT"),
+    ],
+    // tags, notes and history point at these rows, a plain rowid changes
+    // with VACUUM
+    &[
+	Step::AddId("domain"),
+	Step::AddId("service"),
+	Step::AddId("script"),
+    ],
+    &[Step::Sql(SCAN_PORT_RANGES)],
+    // findings of a report host that did not resolve have its name instead
+    // of an address
+    &[
+	Step::DropNotNull("finding", "ip"),
+	Step::AddColumn("finding", "domain", "TEXT"),
+	Step::Sql("CREATE UNIQUE INDEX IF NOT EXISTS finding_domain_unique_idx ON finding (domain, port, protocol, scanner, plugin_id) WHERE ip IS NULL;"),
+    ],
+    // a name that does not resolve has no address rather than ''
+    &[
+	Step::DropNotNull("domain", "ip"),
+	Step::Sql(UNRESOLVED_DOMAINS),
+    ],
+    // scripts of a scan that reached the port by name
+    &[
+	Step::AddColumn("script", "vhost", "TEXT"),
+	Step::Sql(SCRIPT_VHOST),
+    ],
+];
+
+/// Version of the schema this build writes.
+pub const SCHEMA_VERSION: i64 = MIGRATIONS.len() as i64;
+
+pub fn version(conn: &Connection) -> Result<i64> {
+    let mut cursor = conn.prepare("PRAGMA user_version")?.into_cursor();
+    Ok(cursor.next()?.and_then(|row| row[0].as_integer()).unwrap_or(0))
+}
+
+/// Applies the migrations a database is missing up to version, each one in
+/// a transaction of its own. A new database goes through all of them.
+pub fn migrate(conn: &Connection, version: i64) -> Result<()> {
+    let current = self::version(conn)?;
+    if current > SCHEMA_VERSION {
+	return Err(format!("the database has schema version {}, this pendb only knows up to {}", current, SCHEMA_VERSION).into());
+    }
+    for (i, steps) in MIGRATIONS.iter().enumerate().take(version as usize).skip(current as usize) {
+	conn.execute("BEGIN")?;
+	match apply(conn, steps, i as i64 + 1) {
+	    Ok(()) => conn.execute("COMMIT")?,
+	    Err(e) => {
+		conn.execute("ROLLBACK")?;
+		return Err(format!("migration to schema version {} failed: {}", i + 1, e).into());
+	    },
+	}
+    }
+    Ok(())
+}
+
+// The steps and the new version, which only sticks with them.
+fn apply(conn: &Connection, steps: &[Step], version: i64) -> Result<()> {
+    for step in steps {
+	match step {
+	    Step::Sql(sql) => conn.execute(sql)?,
+	    Step::AddColumn(table, column, definition) => add_column(conn, table, column, definition)?,
+	    Step::AddId(table) => add_id(conn, table)?,
+	    Step::DropNotNull(table, column) => drop_not_null(conn, table, column)?,
+	    Step::Fn(f) => f(conn)?,
+	}
+    }
+    conn.execute(format!("PRAGMA user_version = {}", version))?;
+    Ok(())
+}
+
+fn add_column(conn: &Connection, table: &str, column: &str, definition: &str) -> Result<()> {
+    if !columns(conn, table)?.iter().any(|name| name == column) {
+	conn.execute(format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition))?;
+    }
+    Ok(())
+}
+
+// A primary key can only come with the table, so it is created anew with
+// the old definition behind the id, filled and renamed. Keeping the rowids
+// as ids keeps what already points at the rows valid.
+fn add_id(conn: &Connection, table: &str) -> Result<()> {
+    let columns = columns(conn, table)?;
+    if columns.iter().any(|name| name == "id") {
+	return Ok(());
+    }
+    let (definition, indexes) = definition(conn, table)?;
+    let (_, columns_sql) = definition.split_once('(').ok_or_else(|| format!("cannot read the definition of {}", table))?;
+
+    let columns = columns.join(", ");
+    conn.execute(format!(r#"
+CREATE TABLE {table}_with_id (id INTEGER PRIMARY KEY, {columns_sql};
+INSERT INTO {table}_with_id (id, {columns}) SELECT rowid, {columns} FROM {table};
+DROP TABLE {table};
+ALTER TABLE {table}_with_id RENAME TO {table};"#))?;
+    for index in indexes {
+	conn.execute(index)?;
+    }
+    Ok(())
+}
+
+// Like a primary key, NOT NULL can only go with the table, so it is created
+// anew without it.
+fn drop_not_null(conn: &Connection, table: &str, column: &str) -> Result<()> {
+    let (definition, indexes) = definition(conn, table)?;
+    let (_, columns_sql) = definition.split_once('(').ok_or_else(|| format!("cannot read the definition of {}", table))?;
+    let nullable: Vec<String> = columns_sql.split(',')
+	.map(|part| match part.split_whitespace().next() {
+	    Some(name) if name == column => part.replacen(" NOT NULL", "", 1),
+	    _ => part.to_string(),
+	})
+	.collect();
+    let nullable = nullable.join(",");
+    if nullable == columns_sql {
+	return Ok(());
+    }
+
+    let columns = columns(conn, table)?.join(", ");
+    conn.execute(format!(r#"
+CREATE TABLE {table}_nullable ({nullable};
+INSERT INTO {table}_nullable ({columns}) SELECT {columns} FROM {table};
+DROP TABLE {table};
+ALTER TABLE {table}_nullable RENAME TO {table};"#))?;
+    for index in indexes {
+	conn.execute(index)?;
+    }
+    Ok(())
+}
+
+// The CREATE TABLE of a table and those of its indexes.
+fn definition(conn: &Connection, table: &str) -> Result<(String, Vec<String>)> {
+    let mut definition = None;
+    let mut indexes = vec![];
+    let mut cursor = conn.prepare("SELECT type, sql FROM sqlite_master WHERE tbl_name = :table AND sql IS NOT NULL")?
+	.into_cursor();
+    cursor.bind_by_name(vec![(":table", Value::String(table.into()))])?;
+    while let Some(row) = cursor.next()? {
+	match (row[0].as_string(), row[1].as_string()) {
+	    (Some("table"), Some(sql)) => definition = Some(sql.to_string()),
+	    (Some("index"), Some(sql)) => indexes.push(sql.to_string()),
+	    _ => continue,
+	}
+    }
+    let definition = definition.ok_or_else(|| format!("no table {}", table))?;
+    Ok((definition, indexes))
+}
+
+// Columns SCHEMA_V1 indexes or the host links need that the releases
+// before host ids did not have.
+const HOST_ID_COLUMNS: [(&str, &str, &str); 6] = [
+    ("domain", "source", "TEXT"),
+    ("domain", "tool", "TEXT"),
+    ("domain", "wildcard", "INTEGER NOT NULL DEFAULT 0"),
+    ("domain", "host_id", "INTEGER REFERENCES host (id)"),
+    ("service", "host_id", "INTEGER REFERENCES host (id)"),
+    ("script", "host_id", "INTEGER REFERENCES host (id)"),
+];
+
+// Before host ids no row knew its host and the host table, if there was
+// one, had neither id nor ip_bin. Creates the host table with ids, a host
+// for every address the rows have and links the rows to them. A new
+// database has none of the tables and is left to SCHEMA_V1.
+fn add_host_ids(conn: &Connection) -> Result<()> {
+    let mut tables = vec![];
+    for table in ["domain", "service", "script"] {
+	if has_table(conn, table)? {
+	    tables.push(table);
+	}
+    }
+    if tables.is_empty() {
+	return Ok(());
+    }
+    for (table, column, definition) in HOST_ID_COLUMNS {
+	if tables.contains(&table) {
+	    add_column(conn, table, column, definition)?;
+	}
+    }
+    // its unique index goes with it, SCHEMA_V1 makes the one of the new table
+    let old_hosts = has_table(conn, "host")? && !columns(conn, "host")?.iter().any(|name| name == "ip_bin");
+    if old_hosts {
+	conn.execute("ALTER TABLE host RENAME TO host_without_ids")?;
+    }
+    conn.execute(r#"
+CREATE TABLE IF NOT EXISTS host (
+    id INTEGER PRIMARY KEY,
+    ip TEXT NOT NULL,
+    ip_bin BLOB NOT NULL,
+    os TEXT,
+    fqdn TEXT,
+    netbios_name TEXT
+)"#)?;
+
+    let mut selects: Vec<String> = tables.iter().map(|table| format!("SELECT ip FROM {} WHERE host_id IS NULL", table)).collect();
+    if old_hosts {
+	selects.push("SELECT ip FROM host_without_ids".into());
+    }
+    let mut addresses = vec![];
+    let mut cursor = conn.prepare(selects.join(" UNION "))?.into_cursor();
+    while let Some(row) = cursor.next()? {
+	if let Some(ip) = row[0].as_string() {
+	    addresses.push(ip.to_string());
+	}
+    }
+
+    // the rows keep their address as it was written, names without one
+    // stay without a host
+    let mut insert = conn.prepare("INSERT OR IGNORE INTO host (ip, ip_bin) VALUES (:ip, :ip_bin)")?.into_cursor();
+    let mut select = conn.prepare("SELECT id FROM host WHERE ip = :ip")?.into_cursor();
+    let mut host_ids = BTreeMap::new();
+    for address in addresses {
+	let Some((ip, ip_bin)) = canonical_ip(&address) else {
+	    continue;
+	};
+	insert.bind_by_name(vec![
+	    (":ip", Value::String(ip.clone())),
+	    (":ip_bin", Value::Binary(ip_bin)),
+	])?;
+	insert.next()?;
+	select.bind_by_name(vec![(":ip", Value::String(ip))])?;
+	if let Some(id) = select.next()?.and_then(|row| row[0].as_integer()) {
+	    host_ids.insert(address, id);
+	}
+    }
+    for table in tables {
+	let mut update = conn.prepare(format!("UPDATE {} SET host_id = :host_id WHERE ip = :ip AND host_id IS NULL", table))?
+	    .into_cursor();
+	for (address, id) in &host_ids {
+	    update.bind_by_name(vec![
+		(":host_id", Value::Integer(*id)),
+		(":ip", Value::String(address.clone())),
+	    ])?;
+	    update.next()?;
+	}
+    }
+
+    if old_hosts {
+	let mut update = conn.prepare(r#"
+UPDATE host SET os = coalesce(os, :os), fqdn = coalesce(fqdn, :fqdn), netbios_name = coalesce(netbios_name, :netbios_name)
+WHERE id = :host_id"#)?
+	    .into_cursor();
+	let mut cursor = conn.prepare("SELECT ip, os, fqdn, netbios_name FROM host_without_ids")?.into_cursor();
+	while let Some(row) = cursor.next()? {
+	    let Some(id) = row[0].as_string().and_then(|ip| host_ids.get(ip)) else {
+		continue;
+	    };
+	    update.bind_by_name(vec![
+		(":os", row[1].clone()),
+		(":fqdn", row[2].clone()),
+		(":netbios_name", row[3].clone()),
+		(":host_id", Value::Integer(*id)),
+	    ])?;
+	    update.next()?;
+	}
+	drop(cursor);
+	conn.execute("DROP TABLE host_without_ids")?;
+    }
+    Ok(())
+}
+
+fn has_table(conn: &Connection, table: &str) -> Result<bool> {
+    let mut cursor = conn.prepare("SELECT count(*) FROM sqlite_master WHERE type = 'table' AND name = :name")?
+	.into_cursor();
+    cursor.bind_by_name(vec![(":name", Value::String(table.into()))])?;
+    Ok(cursor.next()?.and_then(|row| row[0].as_integer()).unwrap_or(0) > 0)
+}
+
+fn columns(conn: &Connection, table: &str) -> Result<Vec<String>> {
+    let mut cursor = conn.prepare(format!("PRAGMA table_info({})", table))?.into_cursor();
+    let mut columns = vec![];
+    while let Some(row) = cursor.next()? {
+	if let Some(name) = row[1].as_string() {
+	    columns.push(name.to_string());
+	}
+    }
+    Ok(columns)
+}
+
+const SCHEMA_V1: &str = r#"
+CREATE TABLE IF NOT EXISTS domain  (
+    ip TEXT NOT NULL,
+    domain TEXT NOT NULL,
+    cidr TEXT,
+    asn NUMERIC,
+    description TEXT,
+    amass_tag TEXT,
+    source TEXT,
+    tool TEXT,
+    wildcard INTEGER NOT NULL DEFAULT 0,
+    host_id INTEGER REFERENCES host (id),
+    scope TEXT NOT NULL DEFAULT 'unknown');
+CREATE UNIQUE INDEX IF NOT EXISTS domain_unique_idx ON domain (domain, ip);
+CREATE INDEX IF NOT EXISTS domain_host_idx ON domain (host_id);
+
+CREATE TABLE IF NOT EXISTS service (
+    ip TEXT NOT NULL, 
+    port NUMERIC NOT NULL, 
+    protocol TEXT,
+    service_name TEXT, 
+    service_product TEXT,
+    service_method TEXT, 
+    service_conf NUMERIC,
+    state TEXT,
+    state_reason TEXT,
+    host_id INTEGER REFERENCES host (id),
+    scope TEXT NOT NULL DEFAULT 'unknown'
+);
+CREATE UNIQUE INDEX IF NOT EXISTS service_unique_idx ON service (ip, port); 
+CREATE INDEX IF NOT EXISTS service_host_idx ON service (host_id, port);
+
+CREATE TABLE IF NOT EXISTS script (
+    ip TEXT NOT NULL,
+    port NUMERIC NOT NULL,
+    script_id TEXT NOT NULL,
+    script_output TEXT NOT NULL,
+    host_id INTEGER REFERENCES host (id)
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS script_unique_idx ON script (ip, port, script_id); 
+CREATE INDEX IF NOT EXISTS script_host_idx ON script (host_id, port);
+
+CREATE TABLE IF NOT EXISTS web_path (
+    url TEXT NOT NULL,
+    base_url TEXT NOT NULL,
+    path TEXT NOT NULL,
+    status NUMERIC,
+    length NUMERIC,
+    words NUMERIC,
+    redirect TEXT,
+    tool TEXT,
+    wordlist TEXT
+);
+CREATE UNIQUE INDEX IF NOT EXISTS web_path_unique_idx ON web_path (url);
+
+-- ip is the canonical text form, ip_bin the 4 or 16 address bytes
+CREATE TABLE IF NOT EXISTS host (
+    id INTEGER PRIMARY KEY,
+    ip TEXT NOT NULL,
+    ip_bin BLOB NOT NULL,
+    os TEXT,
+    fqdn TEXT,
+    netbios_name TEXT,
+    scope TEXT NOT NULL DEFAULT 'unknown',
+    shared_approved INTEGER NOT NULL DEFAULT 0
+);
+CREATE UNIQUE INDEX IF NOT EXISTS host_unique_idx ON host (ip);
+CREATE UNIQUE INDEX IF NOT EXISTS host_ip_bin_idx ON host (ip_bin);
+
+CREATE TABLE IF NOT EXISTS finding (
+    id INTEGER PRIMARY KEY,
+    ip TEXT NOT NULL,
+    port NUMERIC NOT NULL,
+    protocol TEXT,
+    scanner TEXT NOT NULL,
+    plugin_id TEXT NOT NULL,
+    name TEXT,
+    severity TEXT,
+    cvss NUMERIC,
+    cve TEXT,
+    synopsis TEXT,
+    qod NUMERIC,
+    output TEXT
+);
+CREATE UNIQUE INDEX IF NOT EXISTS finding_unique_idx ON finding (ip, port, protocol, scanner, plugin_id);
+
+CREATE TABLE IF NOT EXISTS tls_protocol (
+    ip TEXT NOT NULL,
+    port NUMERIC NOT NULL,
+    protocol TEXT NOT NULL,
+    enabled NUMERIC NOT NULL,
+    tool TEXT
+);
+CREATE UNIQUE INDEX IF NOT EXISTS tls_protocol_unique_idx ON tls_protocol (ip, port, protocol);
+
+CREATE TABLE IF NOT EXISTS tls_cipher (
+    ip TEXT NOT NULL,
+    port NUMERIC NOT NULL,
+    protocol TEXT NOT NULL,
+    cipher TEXT NOT NULL,
+    bits NUMERIC,
+    strength TEXT,
+    tool TEXT
+);
+CREATE UNIQUE INDEX IF NOT EXISTS tls_cipher_unique_idx ON tls_cipher (ip, port, protocol, cipher);
+
+CREATE TABLE IF NOT EXISTS certificate (
+    id INTEGER PRIMARY KEY,
+    fingerprint TEXT,
+    subject TEXT,
+    issuer TEXT,
+    serial TEXT,
+    not_before TEXT,
+    not_after TEXT,
+    san TEXT,
+    key_type TEXT,
+    key_size NUMERIC,
+    signature_algorithm TEXT
+);
+CREATE INDEX IF NOT EXISTS certificate_fingerprint_idx ON certificate (fingerprint);
+
+CREATE TABLE IF NOT EXISTS service_certificate (
+    certificate_id INTEGER NOT NULL,
+    ip TEXT NOT NULL,
+    port NUMERIC NOT NULL
+);
+CREATE UNIQUE INDEX IF NOT EXISTS service_certificate_unique_idx ON service_certificate (certificate_id, ip, port);
+
+CREATE TABLE IF NOT EXISTS dns_record (
+    name TEXT NOT NULL,
+    type TEXT NOT NULL,
+    value TEXT NOT NULL,
+    ttl NUMERIC,
+    tool TEXT,
+    UNIQUE(name, type, value)
+);
+CREATE INDEX IF NOT EXISTS dns_record_value_idx ON dns_record (value);
+
+-- action is include or exclude, kind one of cidr, range, domain or host
+CREATE TABLE IF NOT EXISTS scope (
+    action TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    value TEXT NOT NULL,
+    UNIQUE(action, kind, value)
+);
+
+CREATE TABLE IF NOT EXISTS tag (
+    id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL
+);
+CREATE UNIQUE INDEX IF NOT EXISTS tag_unique_idx ON tag (name);
+
+-- entity is the table, entity_id the id column of the tagged row, which
+-- schema version 5 added to domain, service and script
+CREATE TABLE IF NOT EXISTS entity_tag (
+    tag_id INTEGER NOT NULL REFERENCES tag (id),
+    entity TEXT NOT NULL,
+    entity_id INTEGER NOT NULL
+);
+CREATE UNIQUE INDEX IF NOT EXISTS entity_tag_unique_idx ON entity_tag (tag_id, entity, entity_id);
+CREATE INDEX IF NOT EXISTS entity_tag_entity_idx ON entity_tag (entity, entity_id);
+
+-- body is Markdown, times are UTC
+CREATE TABLE IF NOT EXISTS note (
+    id INTEGER PRIMARY KEY,
+    author TEXT NOT NULL,
+    created TEXT NOT NULL,
+    updated TEXT NOT NULL,
+    body TEXT NOT NULL,
+    deleted INTEGER NOT NULL DEFAULT 0
+);
+
+-- rows a note is about, like entity_tag
+CREATE TABLE IF NOT EXISTS note_entity (
+    note_id INTEGER NOT NULL REFERENCES note (id),
+    entity TEXT NOT NULL,
+    entity_id INTEGER NOT NULL,
+    UNIQUE(note_id, entity, entity_id)
+);
+CREATE INDEX IF NOT EXISTS note_entity_idx ON note_entity (entity, entity_id);
+
+-- every version a note had before an edit or delete
+CREATE TABLE IF NOT EXISTS note_history (
+    note_id INTEGER NOT NULL REFERENCES note (id),
+    author TEXT NOT NULL,
+    time TEXT NOT NULL,
+    body TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS note_history_idx ON note_history (note_id);
+
+-- kind is one of password, ntlm, hash, key or token
+CREATE TABLE IF NOT EXISTS credential (
+    id INTEGER PRIMARY KEY,
+    username TEXT NOT NULL DEFAULT '',
+    realm TEXT NOT NULL DEFAULT '',
+    kind TEXT NOT NULL,
+    secret TEXT NOT NULL,
+    source TEXT,
+    status TEXT NOT NULL DEFAULT 'unknown',
+    created TEXT NOT NULL
+);
+CREATE UNIQUE INDEX IF NOT EXISTS credential_unique_idx ON credential (username, realm, kind, secret);
+
+-- services a credential was tried against, status is valid or invalid
+CREATE TABLE IF NOT EXISTS credential_service (
+    credential_id INTEGER NOT NULL REFERENCES credential (id),
+    ip TEXT NOT NULL,
+    port NUMERIC NOT NULL,
+    host_id INTEGER REFERENCES host (id),
+    status TEXT NOT NULL,
+    verified TEXT NOT NULL,
+    UNIQUE(credential_id, ip, port)
+);
+CREATE INDEX IF NOT EXISTS credential_service_idx ON credential_service (ip, port);
+
+-- one row once the database is encrypted, check_value is CRYPTO_CHECK encrypted
+CREATE TABLE IF NOT EXISTS crypto (
+    salt BLOB NOT NULL,
+    check_value TEXT NOT NULL
+);
+
+-- hash:plaintext pairs from hashcat and john potfiles
+CREATE TABLE IF NOT EXISTS cracked (
+    hash TEXT NOT NULL,
+    plaintext TEXT NOT NULL,
+    UNIQUE(hash)
+);
+"#;
+
+const SCAN_TABLES: &str = r#"
+-- one row per imported file with hosts, services or names, started is when
+-- the tool ran if it says so. The scan_ tables keep what nmap and amass
+-- scans reported for diffs.
+CREATE TABLE IF NOT EXISTS scan (
+    id INTEGER PRIMARY KEY,
+    tool TEXT NOT NULL,
+    args TEXT,
+    started TEXT,
+    imported TEXT NOT NULL
+);
+
+-- addresses a scan covered, ports it did not report there are not open
+CREATE TABLE IF NOT EXISTS scan_host (
+    scan_id INTEGER NOT NULL REFERENCES scan (id),
+    ip TEXT NOT NULL,
+    UNIQUE(scan_id, ip)
+);
+
+CREATE TABLE IF NOT EXISTS scan_service (
+    scan_id INTEGER NOT NULL REFERENCES scan (id),
+    ip TEXT NOT NULL,
+    port NUMERIC NOT NULL,
+    protocol TEXT NOT NULL,
+    state TEXT,
+    service_name TEXT,
+    product TEXT,
+    version TEXT,
+    UNIQUE(scan_id, ip, port, protocol)
+);
+
+CREATE TABLE IF NOT EXISTS scan_domain (
+    scan_id INTEGER NOT NULL REFERENCES scan (id),
+    domain TEXT NOT NULL,
+    ip TEXT NOT NULL,
+    UNIQUE(scan_id, domain, ip)
+);
+"#;
+
+const SCAN_PORT_RANGES: &str = r#"
+-- ports an nmap scan probed, from its scaninfo. A port of a covered address
+-- outside of them was not looked at, scans from before have none.
+CREATE TABLE IF NOT EXISTS scan_port_range (
+    scan_id INTEGER NOT NULL REFERENCES scan (id),
+    protocol TEXT NOT NULL,
+    first_port INTEGER NOT NULL,
+    last_port INTEGER NOT NULL
+);
+"#;
+
+const UNRESOLVED_DOMAINS: &str = r#"
+UPDATE domain SET ip = NULL WHERE ip = '';
+-- a name first seen without an address was recorded as new with ip ''
+UPDATE history SET column_name = 'domain', new_value = (SELECT domain FROM domain WHERE domain.id = history.entity_id)
+WHERE entity = 'domain' AND column_name = 'ip' AND old_value IS NULL AND new_value = '';
+UPDATE history SET old_value = NULL WHERE entity = 'domain' AND column_name = 'ip' AND old_value = '';
+-- NULLs are distinct in domain_unique_idx, a name is unresolved only once
+CREATE UNIQUE INDEX IF NOT EXISTS domain_unresolved_idx ON domain (domain) WHERE ip IS NULL;
+"#;
+
+const SCRIPT_VHOST: &str = r#"
+-- vhost is the name nmap was given, an http-title of it is the virtual
+-- host's page and kept apart from the page of the bare address
+DROP INDEX IF EXISTS script_unique_idx;
+CREATE UNIQUE INDEX IF NOT EXISTS script_unique_idx ON script (ip, port, script_id) WHERE vhost IS NULL;
+CREATE UNIQUE INDEX IF NOT EXISTS script_vhost_unique_idx ON script (ip, port, script_id, vhost) WHERE vhost IS NOT NULL;
+"#;
+
+const HISTORY_TABLE: &str = r#"
+-- every value an import or a command changed on service, domain and script
+-- rows, a new row has no old value. entity and entity_id are the table and
+-- id like in entity_tag, the cause is the scan or else the author.
+CREATE TABLE IF NOT EXISTS history (
+    entity TEXT NOT NULL,
+    entity_id INTEGER NOT NULL,
+    column_name TEXT NOT NULL,
+    old_value TEXT,
+    new_value TEXT,
+    scan_id INTEGER REFERENCES scan (id),
+    author TEXT,
+    time TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS history_entity_idx ON history (entity, entity_id);
+"#;
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::sqlite::Db;
+    use crate::db::scope::ScopeFilter;
+    use crate::db::tag::Entity;
+    use crate::parser::nmap::Nmap;
+
+    // rows as the last release before versions wrote them
+    const UNVERSIONED_ROWS: &str = r#"
+INSERT INTO host (id, ip, ip_bin, scope) VALUES (1, '10.0.0.1', X'0a000001', 'in');
+INSERT INTO service (ip, port, protocol, service_name, service_product, service_method, service_conf, state, state_reason, host_id, scope)
+VALUES ('10.0.0.1', 80, 'tcp', 'http', 'nginx', 'probed', 10, 'open', 'syn-ack', 1, 'in');
+INSERT INTO script (ip, port, script_id, script_output, host_id) VALUES ('10.0.0.1', 80, 'http-title', 'Welcome', 1);
+INSERT INTO domain (ip, domain, source, tool, host_id, scope) VALUES ('10.0.0.1', 'www.example.com', 'DNS', 'amass', 1, 'in');
+INSERT INTO note (id, author, created, updated, body) VALUES (1, 'alice', '2026-10-01 12:00:00', '2026-10-01 12:00:00', 'default vhost');
+INSERT INTO note_entity (note_id, entity, entity_id) VALUES (1, 'service', 1);
+INSERT INTO credential (username, realm, kind, secret, created) VALUES ('admin', '', 'password', 'hunter2', '2026-10-01 12:00:00');
+"#;
+
+    // the tables of the first release, as it created them
+    const BASELINE_SCHEMA: &str = r#"
+CREATE TABLE IF NOT EXISTS domain  (
+    ip TEXT NOT NULL,
+    domain TEXT NOT NULL,
+    cidr TEXT,
+    asn NUMERIC,
+    description TEXT,
+    amass_tag TEXT);
+CREATE UNIQUE INDEX IF NOT EXISTS domain_unique_idx ON domain (domain, ip);
+
+CREATE TABLE IF NOT EXISTS service (
+    ip TEXT NOT NULL, 
+    port NUMERIC NOT NULL, 
+    protocol TEXT,
+    service_name TEXT, 
+    service_product TEXT,
+    service_method TEXT, 
+    service_conf NUMERIC,
+    state TEXT,
+    state_reason TEXT
+);
+CREATE UNIQUE INDEX IF NOT EXISTS service_unique_idx ON service (ip, port); 
+
+CREATE TABLE IF NOT EXISTS script (
+    ip TEXT NOT NULL,
+    port NUMERIC NOT NULL,
+    script_id TEXT NOT NULL,
+    script_output TEXT NOT NULL
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS script_unique_idx ON script (ip, port, script_id); 
+"#;
+
+    const BASELINE_ROWS: &str = r#"
+INSERT INTO service VALUES ('10.0.0.1', 80, 'tcp', 'http', 'nginx', 'probed', 10, 'open', 'syn-ack');
+INSERT INTO service VALUES ('2001:DB8::1', 22, 'tcp', 'ssh', 'OpenSSH', 'probed', 10, 'open', 'syn-ack');
+INSERT INTO script VALUES ('10.0.0.1', 80, 'http-title', 'Welcome');
+INSERT INTO domain VALUES ('10.0.0.1', 'www.example.com', '10.0.0.0/24', 64500, 'EXAMPLE', 'dns');
+INSERT INTO domain VALUES ('10.0.0.2', 'mail.example.com', '10.0.0.0/24', 64500, 'EXAMPLE', 'dns');
+"#;
+
+    const NMAP_RESCAN: &str = r#"
+<nmaprun scanner="nmap" args="nmap -sV 10.0.0.1" start="1760000000" startstr="Thu Oct  9 08:53:20 2025" version="7.94" xmloutputversion="1.05">
+<host starttime="1760000000" endtime="1760000100"><status state="up" reason="syn-ack" reason_ttl="0"/>
+<address addr="10.0.0.1" addrtype="ipv4"/>
+<ports>
+<port protocol="tcp" portid="80"><state state="open" reason="syn-ack" reason_ttl="0"/><service name="http" product="nginx" version="1.25.3" method="probed" conf="10"/></port>
+</ports>
+</host>
+</nmaprun>
+"#;
+
+    fn unversioned(version: i64) -> Db {
+	let db = Db::new(":memory:").unwrap();
+	migrate(&db.conn, version).unwrap();
+	db.conn.execute(UNVERSIONED_ROWS).unwrap();
+	db.conn.execute("PRAGMA user_version = 0").unwrap();
+	db
+    }
+
+    #[test]
+    fn upgrade() {
+	// as the first versioned schema and as the schema right before versions
+	for version in [1, SCHEMA_VERSION] {
+	    let db = unversioned(version);
+	    db.create_table().unwrap();
+	    assert!(self::version(&db.conn).unwrap() == SCHEMA_VERSION);
+	    for (table, column) in [("service", "service_version"), ("service", "tunnel"), ("domain", "first_seen"), ("host", "first_seen")] {
+		assert!(columns(&db.conn, table).unwrap().iter().any(|name| name == column));
+	    }
+
+	    let services = db.services().unwrap();
+	    assert!(services.len() == 1);
+	    assert!(services[0].product.as_deref() == Some("nginx") && services[0].scope.as_str() == "in");
+	    assert!(services[0].first_seen.is_none());
+	    assert!(db.scripts().unwrap()[0].output == "Welcome");
+	    assert!(db.domains(true, ScopeFilter::Any, &[]).unwrap()[0].domain == "www.example.com");
+	    assert!(db.notes().unwrap()[0].body == "default vhost");
+	    assert!(db.entity_notes(Entity::Service).unwrap().contains_key(&services[0].id));
+	    assert!(db.credentials().unwrap()[0].secret == "hunter2");
+
+	    // old rows take part in the new features
+	    db.insert_nmap_scan(Nmap::new(NMAP_RESCAN.as_bytes()).unwrap()).unwrap();
+	    let history = db.history("10.0.0.1", Some(80)).unwrap();
+	    assert!(history.len() == 1 && history[0].new_value.as_deref() == Some("1.25.3"));
+	    assert!(db.services().unwrap()[0].last_seen.is_some());
+
+	    // a second open changes nothing
+	    db.create_table().unwrap();
+	    assert!(db.services().unwrap().len() == 1);
+	}
+    }
+
+    #[test]
+    fn unknown_versions() {
+	let db = Db::new(":memory:").unwrap();
+	db.create_table().unwrap();
+	db.conn.execute(format!("PRAGMA user_version = {}", SCHEMA_VERSION + 1)).unwrap();
+	assert!(db.create_table().is_err());
+    }
+
+    #[test]
+    fn upgrade_baseline() {
+	let db = Db::new(":memory:").unwrap();
+	db.conn.execute(BASELINE_SCHEMA).unwrap();
+	db.conn.execute(BASELINE_ROWS).unwrap();
+	db.create_table().unwrap();
+	assert!(self::version(&db.conn).unwrap() == SCHEMA_VERSION);
+
+	let hosts: Vec<String> = db.hosts().unwrap().into_iter().map(|host| host.ip).collect();
+	assert!(hosts == vec!["10.0.0.1", "10.0.0.2", "2001:db8::1"]);
+	for table in ["service", "script", "domain"] {
+	    let mut cursor = db.conn.prepare(format!("SELECT count(*) FROM {} WHERE host_id IS NULL", table)).unwrap().into_cursor();
+	    assert!(cursor.next().unwrap().unwrap()[0].as_integer() == Some(0));
+	}
+	let services = db.services().unwrap();
+	assert!(services.len() == 2 && services[0].product.as_deref() == Some("nginx"));
+	assert!(db.scripts().unwrap()[0].output == "Welcome");
+	let domains = db.domains(true, ScopeFilter::Any, &[]).unwrap();
+	assert!(domains.len() == 2 && domains.iter().all(|domain| !domain.wildcard));
+
+	// scope follows the new host ids
+	db.add_scope_rule(&crate::db::scope::Rule::parse(true, "10.0.0.0/24").unwrap()).unwrap();
+	db.apply_scope("alice").unwrap();
+	let services = db.services().unwrap();
+	assert!(services[0].scope.as_str() == "in" && services[1].scope.as_str() == "unknown");
+	db.insert_nmap_scan(Nmap::new(NMAP_RESCAN.as_bytes()).unwrap()).unwrap();
+	assert!(db.hosts().unwrap().len() == 3);
+    }
+
+    #[test]
+    fn upgrade_host_table_without_ids() {
+	let db = Db::new(":memory:").unwrap();
+	db.conn.execute(BASELINE_SCHEMA).unwrap();
+	db.conn.execute(BASELINE_ROWS).unwrap();
+	db.conn.execute(r#"
+CREATE TABLE IF NOT EXISTS host (ip TEXT NOT NULL, os TEXT, fqdn TEXT, netbios_name TEXT);
+CREATE UNIQUE INDEX IF NOT EXISTS host_unique_idx ON host (ip);
+INSERT INTO host VALUES ('10.0.0.1', 'Linux', 'web.corp.local', 'WEB');
+INSERT INTO host VALUES ('10.0.0.3', 'Windows', NULL, 'DC01');"#).unwrap();
+	db.create_table().unwrap();
+
+	let hosts: Vec<String> = db.hosts().unwrap().into_iter().map(|host| host.ip).collect();
+	assert!(hosts == vec!["10.0.0.1", "10.0.0.2", "10.0.0.3", "2001:db8::1"]);
+	let mut cursor = db.conn.prepare("SELECT os, netbios_name FROM host WHERE ip = '10.0.0.3'").unwrap().into_cursor();
+	let row = cursor.next().unwrap().unwrap();
+	assert!(row[0].as_string() == Some("Windows") && row[1].as_string() == Some("DC01"));
+	assert!(!has_table(&db.conn, "host_without_ids").unwrap());
+	// the index of the new table, not the old one
+	let mut cursor = db.conn.prepare("SELECT tbl_name FROM sqlite_master WHERE name = 'host_unique_idx'").unwrap().into_cursor();
+	assert!(cursor.next().unwrap().unwrap()[0].as_string() == Some("host"));
+    }
+
+    #[test]
+    fn upgrade_unresolved_names() {
+	let db = Db::new(":memory:").unwrap();
+	migrate(&db.conn, 7).unwrap();
+	db.conn.execute(r#"
+INSERT INTO domain (id, ip, domain, tool) VALUES (1, '', 'mail.example.com', 'assetfinder');
+INSERT INTO domain (id, ip, domain, tool) VALUES (2, '93.184.216.34', 'www.example.com', 'dnsx');
+INSERT INTO history (entity, entity_id, column_name, old_value, new_value, time) VALUES ('domain', 1, 'ip', NULL, '', '2026-10-01 12:00:00');
+INSERT INTO history (entity, entity_id, column_name, old_value, new_value, time) VALUES ('domain', 2, 'ip', NULL, '93.184.216.34', '2026-10-01 12:00:00');
+INSERT INTO history (entity, entity_id, column_name, old_value, new_value, time) VALUES ('domain', 2, 'ip', '', '93.184.216.34', '2026-10-01 12:00:00');"#).unwrap();
+	db.create_table().unwrap();
+
+	let ips: Vec<Option<String>> = db.domains(true, ScopeFilter::Any, &[]).unwrap().into_iter().map(|domain| domain.ip).collect();
+	assert!(ips == vec![None, Some("93.184.216.34".to_string())]);
+	let mut cursor = db.conn.prepare("SELECT column_name, old_value, new_value FROM history ORDER BY rowid").unwrap().into_cursor();
+	let mut rows = vec![];
+	while let Some(row) = cursor.next().unwrap() {
+	    rows.push((row[0].as_string().unwrap().to_string(), row[1].as_string().map(|value| value.to_string()), row[2].as_string().unwrap().to_string()));
+	}
+	assert!(rows == vec![
+	    ("domain".to_string(), None, "mail.example.com".to_string()),
+	    ("ip".to_string(), None, "93.184.216.34".to_string()),
+	    ("ip".to_string(), None, "93.184.216.34".to_string()),
+	]);
+	// the name is unresolved once however often a tool reports it
+	assert!(db.conn.execute("INSERT INTO domain (ip, domain, tool) VALUES (NULL, 'mail.example.com', 'zone')").is_err());
+    }
+
+    #[test]
+    fn failed_upgrade() {
+	// the script view cannot take the index of SCHEMA_V1
+	let db = Db::new(":memory:").unwrap();
+	db.conn.execute(BASELINE_SCHEMA.replace("CREATE TABLE IF NOT EXISTS script", "CREATE TABLE IF NOT EXISTS old_script")
+	    .replace("ON script", "ON old_script")).unwrap();
+	db.conn.execute("CREATE VIEW script AS SELECT * FROM old_script").unwrap();
+	db.conn.execute(BASELINE_ROWS.replace("INTO script", "INTO old_script")).unwrap();
+	let message = db.create_table().unwrap_err().to_string();
+	assert!(message.starts_with("migration to schema version 1 failed"));
+	assert!(version(&db.conn).unwrap() == 0);
+	assert!(!has_table(&db.conn, "host").unwrap());
+	assert!(!columns(&db.conn, "service").unwrap().iter().any(|name| name == "host_id"));
+    }
+}
@@ -0,0 +1,301 @@
+use std::fmt;
+use std::net::IpAddr;
+
+use crate::parser::dns::normalize_name;
+
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scope {
+    In,
+    Out,
+    // no rule says anything about it
+    Unknown,
+}
+
+impl Scope {
+    pub fn as_str(&self) -> &'static str {
+	match self {
+	    Scope::In => "in",
+	    Scope::Out => "out",
+	    Scope::Unknown => "unknown",
+	}
+    }
+
+    /// Parses the scope column, anything unexpected is unknown.
+    pub fn from_column(value: &str) -> Scope {
+	match value {
+	    "in" => Scope::In,
+	    "out" => Scope::Out,
+	    _ => Scope::Unknown,
+	}
+    }
+
+    /// Scope of something that is known by several identities, e.g. a domain
+    /// and the address it resolves to. One excluded identity is enough to
+    /// keep it out.
+    pub fn combine(self, other: Scope) -> Scope {
+	match (self, other) {
+	    (Scope::Out, _) | (_, Scope::Out) => Scope::Out,
+	    (Scope::In, _) | (_, Scope::In) => Scope::In,
+	    _ => Scope::Unknown,
+	}
+    }
+}
+
+// Which rows listings and exports show, ScopeRules::shows by default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScopeFilter {
+    Default,
+    Only(Scope),
+    Any,
+}
+
+impl ScopeFilter {
+    /// "in", "out", "unknown" or "any"
+    pub fn parse(value: &str) -> Result<Self> {
+	match value {
+	    "in" => Ok(ScopeFilter::Only(Scope::In)),
+	    "out" => Ok(ScopeFilter::Only(Scope::Out)),
+	    "unknown" => Ok(ScopeFilter::Only(Scope::Unknown)),
+	    "any" => Ok(ScopeFilter::Any),
+	    _ => Err(format!("unknown scope {}, use in, out, unknown or any", value).into()),
+	}
+    }
+
+    pub fn allows(&self, rules: &ScopeRules, scope: Scope) -> bool {
+	match self {
+	    ScopeFilter::Default => rules.shows(scope),
+	    ScopeFilter::Only(only) => *only == scope,
+	    ScopeFilter::Any => true,
+	}
+    }
+}
+
+// What a scope rule covers:
+//   10.0.0.0/8                  Cidr
+//   10.0.0.1-10.0.0.50          Range
+//   *.example.com, .example.com Domain, every name below example.com
+//   10.0.0.1                    Ip, exactly this address
+//   example.com                 Host, exactly this name
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Target {
+    Cidr(IpAddr, u8),
+    Range(IpAddr, IpAddr),
+    Domain(String),
+    Ip(IpAddr),
+    Host(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rule {
+    pub include: bool,
+    pub target: Target,
+}
+
+impl Rule {
+    pub fn parse(include: bool, value: &str) -> Result<Self> {
+	let value = value.trim();
+	let target = if let Some((ip, bits)) = value.split_once('/') {
+	    let ip: IpAddr = ip.parse()?;
+	    let bits: u8 = bits.parse()?;
+	    if bits > max_bits(&ip) {
+		return Err(format!("invalid prefix length in {}", value).into());
+	    }
+	    Target::Cidr(ip, bits)
+	} else if let Some((start, end)) = value.split_once('-').filter(|(start, _)| start.parse::<IpAddr>().is_ok()) {
+	    let start: IpAddr = start.parse()?;
+	    let end: IpAddr = end.parse()?;
+	    if start.is_ipv4() != end.is_ipv4() || start > end {
+		return Err(format!("invalid address range {}", value).into());
+	    }
+	    Target::Range(start, end)
+	} else if let Some(suffix) = value.strip_prefix("*.").or_else(|| value.strip_prefix('.')) {
+	    Target::Domain(normalize_name(suffix))
+	} else if let Ok(ip) = value.parse::<IpAddr>() {
+	    Target::Ip(ip)
+	} else if !value.is_empty() && !value.contains(char::is_whitespace) {
+	    Target::Host(normalize_name(value))
+	} else {
+	    return Err(format!("invalid scope rule {:?}", value).into());
+	};
+	Ok(Self {
+	    include,
+	    target,
+	})
+    }
+
+    /// Column values of the scope table.
+    pub fn action(&self) -> &'static str {
+	if self.include { "include" } else { "exclude" }
+    }
+
+    pub fn kind(&self) -> &'static str {
+	match self.target {
+	    Target::Cidr(..) => "cidr",
+	    Target::Range(..) => "range",
+	    Target::Domain(_) => "domain",
+	    Target::Ip(_) | Target::Host(_) => "host",
+	}
+    }
+
+    pub fn value(&self) -> String {
+	match &self.target {
+	    Target::Cidr(ip, bits) => format!("{}/{}", ip, bits),
+	    Target::Range(start, end) => format!("{}-{}", start, end),
+	    Target::Domain(suffix) => format!("*.{}", suffix),
+	    Target::Ip(ip) => ip.to_string(),
+	    Target::Host(host) => host.clone(),
+	}
+    }
+
+    pub fn matches_ip(&self, ip: &IpAddr) -> bool {
+	match &self.target {
+	    Target::Cidr(network, bits) => match (network, ip) {
+		(IpAddr::V4(network), IpAddr::V4(ip)) => {
+		    let mask = u32::MAX.checked_shl(32 - *bits as u32).unwrap_or(0);
+		    u32::from(*network) & mask == u32::from(*ip) & mask
+		},
+		(IpAddr::V6(network), IpAddr::V6(ip)) => {
+		    let mask = u128::MAX.checked_shl(128 - *bits as u32).unwrap_or(0);
+		    u128::from(*network) & mask == u128::from(*ip) & mask
+		},
+		_ => false,
+	    },
+	    Target::Range(start, end) => start.is_ipv4() == ip.is_ipv4() && start <= ip && ip <= end,
+	    Target::Ip(address) => address == ip,
+	    Target::Domain(_) | Target::Host(_) => false,
+	}
+    }
+
+    pub fn matches_name(&self, name: &str) -> bool {
+	match &self.target {
+	    Target::Domain(suffix) => name.strip_suffix(suffix.as_str()).is_some_and(|sub| sub.ends_with('.')),
+	    Target::Host(host) => host == name,
+	    _ => false,
+	}
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+	write!(f, "{} {}", self.action(), self.value())
+    }
+}
+
+fn max_bits(ip: &IpAddr) -> u8 {
+    if ip.is_ipv4() { 32 } else { 128 }
+}
+
+// All rules of an engagement. Exclusions win over inclusions, anything no
+// rule talks about is unknown.
+#[derive(Debug, Default)]
+pub struct ScopeRules {
+    pub rules: Vec<Rule>,
+}
+
+impl ScopeRules {
+    pub fn ip(&self, ip: &str) -> Scope {
+	match ip.parse::<IpAddr>() {
+	    Ok(ip) => self.scope(|rule| rule.matches_ip(&ip)),
+	    Err(_) => Scope::Unknown,
+	}
+    }
+
+    pub fn name(&self, name: &str) -> Scope {
+	let name = normalize_name(name);
+	self.scope(|rule| rule.matches_name(&name))
+    }
+
+    fn scope<F: Fn(&Rule) -> bool>(&self, matches: F) -> Scope {
+	if self.rules.iter().any(|rule| !rule.include && matches(rule)) {
+	    Scope::Out
+	} else if self.rules.iter().any(|rule| rule.include && matches(rule)) {
+	    Scope::In
+	} else {
+	    Scope::Unknown
+	}
+    }
+
+    /// Default filter of listings and exports: once the engagement names
+    /// what is in scope only that is shown, without any include rules
+    /// everything that is not explicitly excluded.
+    pub fn shows(&self, scope: Scope) -> bool {
+	if self.rules.iter().any(|rule| rule.include) {
+	    scope == Scope::In
+	} else {
+	    scope != Scope::Out
+	}
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+
+    fn rules(rules: &[(bool, &str)]) -> ScopeRules {
+	ScopeRules {
+	    rules: rules.iter().map(|(include, value)| Rule::parse(*include, value).unwrap()).collect(),
+	}
+    }
+
+    #[test]
+    fn parse_rule() {
+	assert!(Rule::parse(true, "10.0.0.0/8").unwrap().kind() == "cidr");
+	assert!(Rule::parse(true, "10.0.0.1-10.0.0.50").unwrap().kind() == "range");
+	assert!(Rule::parse(true, "*.Example.com").unwrap().value() == "*.example.com");
+	assert!(Rule::parse(true, ".example.com").unwrap().value() == "*.example.com");
+	assert!(Rule::parse(false, "2001:db8:0::1").unwrap().to_string() == "exclude 2001:db8::1");
+	assert!(Rule::parse(false, "2001:db8:0::1").unwrap().kind() == "host");
+	assert!(Rule::parse(true, "vpn.example.com").unwrap().kind() == "host");
+	assert!(Rule::parse(true, "10.0.0.0/33").is_err());
+	assert!(Rule::parse(true, "10.0.0.50-10.0.0.1").is_err());
+	assert!(Rule::parse(true, "10.0.0.1-::1").is_err());
+	assert!(Rule::parse(true, "not a host").is_err());
+    }
+
+    #[test]
+    fn scope_ip() {
+	let scope = rules(&[
+	    (true, "10.0.0.0/16"),
+	    (true, "192.0.2.10-192.0.2.20"),
+	    (true, "2001:db8::/32"),
+	    (false, "10.0.5.0/24"),
+	    (false, "192.0.2.15"),
+	]);
+	assert!(scope.ip("10.0.1.1") == Scope::In);
+	assert!(scope.ip("10.0.5.1") == Scope::Out);
+	assert!(scope.ip("10.1.0.1") == Scope::Unknown);
+	assert!(scope.ip("192.0.2.20") == Scope::In);
+	assert!(scope.ip("192.0.2.15") == Scope::Out);
+	assert!(scope.ip("192.0.2.21") == Scope::Unknown);
+	assert!(scope.ip("2001:db8:ffff::1") == Scope::In);
+	assert!(scope.ip("www.example.com") == Scope::Unknown);
+	assert!(rules(&[(true, "0.0.0.0/0")]).ip("8.8.8.8") == Scope::In);
+    }
+
+    #[test]
+    fn scope_name() {
+	let scope = rules(&[
+	    (true, "*.example.com"),
+	    (true, "example.org"),
+	    (false, "vpn.example.com"),
+	]);
+	assert!(scope.name("WWW.example.com.") == Scope::In);
+	assert!(scope.name("a.b.example.com") == Scope::In);
+	assert!(scope.name("vpn.example.com") == Scope::Out);
+	// the suffix rule does not cover the apex or look-alikes
+	assert!(scope.name("example.com") == Scope::Unknown);
+	assert!(scope.name("badexample.com") == Scope::Unknown);
+	assert!(scope.name("example.org") == Scope::In);
+	assert!(scope.name("www.example.org") == Scope::Unknown);
+
+	assert!(!scope.shows(Scope::Unknown));
+	assert!(ScopeRules::default().shows(Scope::Unknown));
+	assert!(!rules(&[(false, "vpn.example.com")]).shows(Scope::Out));
+    }
+}
@@ -1,108 +1,322 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::net::IpAddr;
 use std::path::Path;
 
-use sqlite::{self, Connection};
+use sqlite::{self, Connection, Cursor};
 use sqlite::Value;
 
 use crate::parser::nmap::*;
 use crate::parser::amass::*;
+use crate::parser::ffuf::*;
+use crate::parser::feroxbuster::*;
+use crate::parser::gobuster::*;
+use crate::parser::nessus::*;
+use crate::parser::openvas::*;
+use crate::parser::tls::*;
+use crate::parser::dns::*;
+use crate::parser::testssl::*;
+use crate::parser::sslscan::*;
+use crate::parser::subfinder::*;
+use crate::parser::assetfinder::*;
+use crate::parser::dnsx::*;
+use crate::parser::zone::*;
+use crate::parser::crtsh::*;
+use crate::parser::secretsdump::*;
+use crate::parser::potfile::*;
+use super::scope::*;
+use super::tag::*;
+use super::credential::*;
+use super::crypto::{self, Cipher};
+use super::filter::{Filter, Table};
+use super::schema;
 
 
 
 type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
 
 pub struct Db {
-   pub conn: Connection
+    pub conn: Connection,
+    // set by unlock or encrypt, for the encrypted columns
+    cipher: Option<Cipher>,
+}
+
+// Checked on unlock to tell a wrong passphrase from a right one.
+const CRYPTO_CHECK: &str = "pendb";
+
+// (table, column, associated data) of everything Db::encrypt protects.
+const ENCRYPTED_COLUMNS: [(&str, &str, &str); 5] = [
+    ("credential", "secret", crypto::SECRET),
+    ("cracked", "hash", crypto::SECRET),
+    ("cracked", "plaintext", crypto::SECRET),
+    ("note", "body", crypto::NOTE),
+    ("note_history", "body", crypto::NOTE),
+];
+
+// Children of one parent that resolve to the very same addresses before the
+// parent is taken for a wildcard zone.
+pub const WILDCARD_MIN_NAMES: usize = 10;
+
+// Second level labels country code TLDs register names under, co.uk,
+// com.au and the like. Not a public suffix list, enough to keep unrelated
+// registrations from looking like a wildcard zone.
+const SECOND_LEVEL_SUFFIXES: [&str; 12] = ["ac", "co", "com", "edu", "gob", "gov", "go", "ltd", "ne", "net", "or", "org"];
+
+#[derive(Debug)]
+pub struct DomainEntry {
+    pub domain: String,
+    // None until some tool resolves the name
+    pub ip: Option<String>,
+    pub source: Option<String>,
+    pub tool: Option<String>,
+    pub wildcard: bool,
+    pub scope: Scope,
+    pub first_seen: Option<String>,
+    // id, for tags and notes
+    pub id: i64,
+}
+
+#[derive(Debug)]
+pub struct HostEntry {
+    pub ip: String,
+    pub scope: Scope,
+    pub first_seen: Option<String>,
+    pub id: i64,
+}
+
+#[derive(Debug)]
+pub struct ServiceEntry {
+    pub ip: String,
+    pub port: u16,
+    pub protocol: Option<String>,
+    pub service_name: Option<String>,
+    pub product: Option<String>,
+    pub state: Option<String>,
+    // speaks TLS
+    pub ssl: bool,
+    pub scope: Scope,
+    pub first_seen: Option<String>,
+    pub last_seen: Option<String>,
+    // id, for tags and notes
+    pub id: i64,
+}
+
+#[derive(Debug)]
+pub struct ScriptEntry {
+    pub ip: String,
+    pub port: u16,
+    pub script_id: String,
+    pub output: String,
+    // the name the scan reached the port by, None for the bare address
+    pub vhost: Option<String>,
+    pub id: i64,
+}
+
+// An imported nmap or amass file and how much it reported.
+#[derive(Debug)]
+pub struct ScanEntry {
+    pub id: i64,
+    pub tool: String,
+    pub args: Option<String>,
+    pub started: Option<String>,
+    pub imported: String,
+    pub hosts: i64,
+    pub services: i64,
+    pub domains: i64,
+}
+
+impl ScanEntry {
+    // when the tool ran, or when we imported it if it does not say
+    pub fn time(&self) -> &str {
+	self.started.as_deref().unwrap_or(&self.imported)
+    }
+}
+
+// One changed value of a service, script or domain row. target is ip:port,
+// ip:port and the script id or the name.
+#[derive(Debug)]
+pub struct HistoryEntry {
+    pub time: String,
+    pub entity: String,
+    pub target: String,
+    pub column: String,
+    pub old_value: Option<String>,
+    pub new_value: Option<String>,
+    // "scan 3 (nmap)" or the author
+    pub cause: String,
+}
+
+// A port as one scan reported it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScanService {
+    pub state: Option<String>,
+    pub service_name: Option<String>,
+    pub product: Option<String>,
+    pub version: Option<String>,
+}
+
+// What a series of scans reported, later scans replace what earlier ones
+// said about the same port or name.
+#[derive(Debug, Default)]
+pub struct Snapshot {
+    // (ip, port, protocol)
+    pub services: BTreeMap<(String, u16, String), ScanService>,
+    // addresses the scans covered and the ports they probed there
+    pub hosts: BTreeMap<String, Vec<PortRange>>,
+    // name to addresses
+    pub domains: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl Snapshot {
+    /// Whether a scan probed the port of the address, so that a port it did
+    /// not report open is not open.
+    pub fn covers(&self, ip: &str, port: u16, protocol: &str) -> bool {
+	self.hosts.get(ip).is_some_and(|ranges| in_ranges(ranges, port, protocol))
+    }
+}
+
+fn in_ranges(ranges: &[PortRange], port: u16, protocol: &str) -> bool {
+    ranges.iter().any(|range| range.protocol == protocol && range.first <= port && port <= range.last)
+}
+
+#[derive(Debug, Clone)]
+pub struct Note {
+    pub id: i64,
+    pub author: String,
+    pub created: String,
+    pub updated: String,
+    pub body: String,
+}
+
+// An earlier version of a note, author and time are of that version.
+#[derive(Debug)]
+pub struct NoteVersion {
+    pub author: String,
+    pub time: String,
+    pub body: String,
+}
+
+// A resolved domain row and the network amass reported for its address.
+#[derive(Debug)]
+pub struct NetworkEntry {
+    pub domain: String,
+    pub ip: String,
+    pub cidr: Option<String>,
+    pub asn: Option<i64>,
+    pub description: Option<String>,
+    pub scope: Scope,
+    // the address is on shared infrastructure but may be targeted anyway
+    pub approved: bool,
+    // id of the domain row, for tags and notes
+    pub id: i64,
 }
 
 impl Db {
     pub fn new<T: AsRef<Path>>(path: T) -> Result<Self> {
 	Ok(Self {
 	    conn: sqlite::open(&path)?,
+	    cipher: None,
 	})
     }
 
-    pub fn create_table(&self) -> Result<()> {
-	self.conn.execute("
-CREATE TABLE IF NOT EXISTS domain  (
-    ip TEXT NOT NULL,
-    domain TEXT NOT NULL,
-    cidr TEXT,
-    asn NUMERIC,
-    description TEXT,
-    amass_tag TEXT);
-CREATE UNIQUE INDEX IF NOT EXISTS domain_unique_idx ON domain (domain, ip);
-
-CREATE TABLE IF NOT EXISTS service (
-    ip TEXT NOT NULL, 
-    port NUMERIC NOT NULL, 
-    protocol TEXT,
-    service_name TEXT, 
-    service_product TEXT,
-    service_method TEXT, 
-    service_conf NUMERIC,
-    state TEXT,
-    state_reason TEXT
-);
-CREATE UNIQUE INDEX IF NOT EXISTS service_unique_idx ON service (ip, port); 
-
-CREATE TABLE IF NOT EXISTS script (
-    ip TEXT NOT NULL,
-    port NUMERIC NOT NULL,
-    script_id TEXT NOT NULL,
-    script_output TEXT NOT NULL
-);
-
-CREATE UNIQUE INDEX IF NOT EXISTS script_unique_idx ON script (ip, port, script_id); 
-")?;
+    /// Gathers the statistics the query planner orders joins by. Without
+    /// them a join on host_id walks all services and looks up each domain.
+    pub fn analyze(&self) -> Result<()> {
+	self.conn.execute("PRAGMA analysis_limit = 400; ANALYZE;")?;
 	Ok(())
     }
 
+    /// Creates the tables of a new database or brings an older one up to
+    /// the schema of this build.
+    pub fn create_table(&self) -> Result<()> {
+	schema::migrate(&self.conn, schema::SCHEMA_VERSION)
+    }
+
+    // Records an import for the scan_ tables, started is a unix time.
+    fn insert_scan(&self, tool: &str, args: Option<&str>, started: Option<i64>) -> Result<i64> {
+	let mut cursor = self.conn.prepare(r#"
+INSERT INTO scan (tool, args, started, imported)
+VALUES (:tool, :args, datetime(:started, 'unixepoch'), datetime('now'))"#)?
+	    .into_cursor();
+	cursor.bind_by_name(vec![
+	    (":tool", Value::String(tool.into())),
+	    (":args", optional_string(args)),
+	    (":started", started.map_or(Value::Null, Value::Integer)),
+	])?;
+	cursor.next()?;
+	self.last_insert_rowid()
+    }
+
+    // Values of new_row that change old_row. Empty values never replace
+    // anything and only nmap, the one tool with a confidence of its own,
+    // overrules what another tool said about the service. Every tool may
+    // change the state.
     fn merge<'t>(old_row: &[Value], new_row: &[(&'t str, Value)]) -> Vec<(&'t str, Value)> {
 	assert!(old_row.len() == new_row.len());
-	vec![]
+	let empty = |value: &Value| *value == Value::Null || value.as_string() == Some("");
+	let confident = new_row.iter().any(|(name, value)| *name == ":conf" && !empty(value));
+	old_row.iter()
+	    .zip(new_row)
+	    .filter(|(old, (name, new))| {
+		!empty(new) && *old != new
+		    && !matches!(*name, ":ip" | ":port")
+		    && (confident || empty(old) || matches!(*name, ":state" | ":reason"))
+	    })
+	    .map(|(_, (name, new))| (*name, new.clone()))
+	    .collect()
     }
 
     pub fn insert_nmap_scan(&self, nmap: Nmap) -> Result<u32> {
-	// TODO: merge/update existing entries
-	// 
-	// if entry exist then goto update path, else insert new entry
-	// update path:
-	// - check which fields should be upated --> merge_heuristic(old_row, new_row) -> update_columns
-	// - create single update stmt via --> update_service(update_columns)
-	//   UPDATE service SET X = 1, Y = 2,.. WHERE ip = <ip> AND port = <port>;
-
 	let mut counter = 0;
-	let mut insert_service = self.conn.prepare(
-	    "INSERT INTO service VALUES (:ip, :port, :protocol, :name, :product, :method, :conf, :state, :reason)")?
-	    .into_cursor();
-	let mut insert_script = self.conn.prepare("INSERT OR IGNORE INTO script VALUES (:ip, :port, :script_id, :script_output)")?
-	    .into_cursor();
-	let mut select_cursor = self.conn.prepare("SELECT * FROM service WHERE ip = :ip AND port = :port")?
-	    .into_cursor();
-	let mut update_service = self.conn.prepare(r#"
-UPDATE service SET 
-    ip = :ip, 
-    port = :port, 
-    protocol = :protocol, 
-    service_name = :name, 
-    service_product = :product, 
-    service_method = :method, 
-    service_conf = :conf, 
-    state = :state, 
-    state_reason = :reason  
-WHERE ip = :ip AND port = :port"#)?.into_cursor();
-	
+	let scan_id = self.insert_scan("nmap", Some(&nmap.args), Some(nmap.start as i64))?;
+	let mut scan_range = self.conn.prepare(r#"
+INSERT INTO scan_port_range (scan_id, protocol, first_port, last_port)
+VALUES (:scan_id, :protocol, :first, :last)"#)?
+	    .into_cursor();
+	for range in nmap.port_ranges()? {
+	    scan_range.bind_by_name(vec![
+		(":scan_id", Value::Integer(scan_id)),
+		(":protocol", Value::String(range.protocol)),
+		(":first", Value::Integer(range.first as i64)),
+		(":last", Value::Integer(range.last as i64)),
+	    ])?;
+	    scan_range.next()?;
+	}
+	let mut services = ServiceCursors::new(&self.conn, scan_id)?;
+	let mut hosts = HostCursors::new(&self.conn)?;
+	let mut scripts = ScriptCursors::new(&self.conn, scan_id)?;
+	let mut certificates = CertificateCursors::new(&self.conn)?;
+	let mut domains = DomainCursors::new(&self.conn, scan_id)?;
+	let mut scan_host = self.conn.prepare("INSERT OR IGNORE INTO scan_host (scan_id, ip) VALUES (:scan_id, :ip)")?
+	    .into_cursor();
+	let mut scan_service = self.conn.prepare(r#"
+INSERT OR IGNORE INTO scan_service (scan_id, ip, port, protocol, state, service_name, product, version)
+VALUES (:scan_id, :ip, :port, :protocol, :state, :name, :product, :version)"#)?
+	    .into_cursor();
+
 	for host in nmap.hosts {
 	    match host {
 		RunElement::Host(host) => {
+		    let (host_id, ip) = hosts.id(&host.address.addr)?;
+		    let vhost = host.user_hostname().filter(|name| canonical_ip(name).is_none());
+		    scan_host.bind_by_name(vec![
+			(":scan_id", Value::Integer(scan_id)),
+			(":ip", Value::String(ip.clone())),
+		    ])?;
+		    scan_host.next()?;
 		    for port in &host.ports.ports {
-			select_cursor.bind_by_name(vec![
-			    (":ip", Value::String(host.address.addr.clone())),
-			    (":port", Value::Integer(port.portid as i64))
+			scan_service.bind_by_name(vec![
+			    (":scan_id", Value::Integer(scan_id)),
+			    (":ip", Value::String(ip.clone())),
+			    (":port", Value::Integer(port.portid as i64)),
+			    (":protocol", Value::String(port.protocol.clone())),
+			    (":state", Value::String(port.state.state.clone())),
+			    (":name", optional_string(Some(port.service.name.as_str()).filter(|name| !name.is_empty()))),
+			    (":product", optional_string(port.service.product.as_deref())),
+			    (":version", optional_string(port.service.version.as_deref())),
 			])?;
-			let old_row = select_cursor.next()?;
+			scan_service.next()?;
 			let new_row = vec![
-				(":ip", Value::String(host.address.addr.clone())),
+				(":ip", Value::String(ip.clone())),
 				(":port", Value::Integer(port.portid as i64)),
 				(":protocol", Value::String(port.protocol.clone())),
 				(":name", Value::String(port.service.name.clone())),
@@ -111,33 +325,18 @@ WHERE ip = :ip AND port = :port"#)?.into_cursor();
 				(":conf", Value::Integer(port.service.conf as i64)),
 				(":state", Value::String(port.state.state.clone())),
 				(":reason", Value::String(port.state.reason.clone())),
+				(":tunnel", optional_string(port.service.tunnel.as_deref())),
+				(":version", optional_string(port.service.version.as_deref())),
 			];
-
-			// insert or update service
-			if old_row.is_none() {
-			    // we have new data, just insert it
-			    insert_service.bind_by_name(new_row)?;
+			if services.insert(new_row)? {
 			    counter += 1;
-			    insert_service.next()?;
-			} else {
-			    // we already have the service in the db, merge and update the row
-			    //assert!(select_cursor.next()?.is_none());
-			    let old_row = old_row.unwrap();
-			    let update_row = Db::merge(&old_row, &new_row);
-			    update_service.bind_by_name(update_row)?;
-			    update_service.next()?;
 			}
 
 			// insert script tags
-			if let Some(ref scripts) = port.scripts {
-			    for script in scripts {
-				insert_script.bind_by_name(vec![
-				    (":ip", Value::String(host.address.addr.clone())),
-				    (":port", Value::Integer(port.portid as i64)),
-				    (":script_id", Value::String(script.id.clone())),
-				    (":script_output", Value::String(script.output.clone())),
-				])?;
-				insert_script.next()?;
+			for script in port.scripts.iter().flatten() {
+			    scripts.insert(&ip, port.portid, script, host_id, vhost)?;
+			    if let Some(certificate) = script.certificate() {
+				certificates.insert_for_service(&mut domains, &certificate, &ip, port.portid, "nmap")?;
 			    }
 			}
 		    }
@@ -148,143 +347,3425 @@ WHERE ip = :ip AND port = :port"#)?.into_cursor();
 	Ok(counter)
     }
 
+    pub fn insert_nessus_scan(&self, nessus: Nessus) -> Result<u32> {
+	let mut counter = 0;
+	let scan_id = self.insert_scan("nessus", None, None)?;
+	let mut services = ServiceCursors::new(&self.conn, scan_id)?;
+	let mut insert_finding = self.conn.prepare(INSERT_FINDING)?.into_cursor();
+	let mut hosts = HostCursors::new(&self.conn)?;
+	let mut update_host = self.conn.prepare(UPDATE_HOST)?.into_cursor();
+	let mut domains = DomainCursors::new(&self.conn, scan_id)?;
 
-    pub fn insert_amass_scan(&self, amass: Amass) -> Result<u32> {
-	// TODO: merge/update existing entries
+	for host in &nessus.report.hosts {
+	    // a report host without an address we know only keeps its findings,
+	    // under its name
+	    let Some(ip) = self.lookup_ip(host.ip())? else {
+		for item in &host.items {
+		    if self.insert_nessus_finding(&mut insert_finding, Value::Null, Some(host.ip()), item)? {
+			counter += 1;
+		    }
+		}
+		continue;
+	    };
+	    let (host_id, ip) = hosts.id(&ip)?;
+	    let ip = ip.as_str();
+	    let fqdn = host.properties.get("host-fqdn");
+	    if let Some(host_id) = host_id {
+		update_host.bind_by_name(vec![
+		    (":id", Value::Integer(host_id)),
+		    (":os", optional_string(host.properties.get("operating-system"))),
+		    (":fqdn", optional_string(fqdn)),
+		    (":netbios_name", optional_string(host.properties.get("netbios-name"))),
+		])?;
+		update_host.next()?;
+	    }
+	    if let Some(fqdn) = fqdn {
+		domains.insert(DomainRow {
+		    ip: Some(ip),
+		    domain: fqdn,
+		    tool: "nessus",
+		    ..Default::default()
+		})?;
+	    }
 
-	let mut cursor = self.conn.prepare(
-	    "INSERT INTO domain VALUES (:ip, :domain, :cidr, :asn, :description, :amass_tag)")?
-	    .into_cursor();
+	    for item in &host.items {
+		// port 0 carries host level results only, there is no service behind it
+		if item.port != 0 && services.insert(vec![
+		    (":ip", Value::String(ip.into())),
+		    (":port", Value::Integer(item.port as i64)),
+		    (":protocol", Value::String(item.protocol.clone())),
+		    (":name", Value::String(item.service_name().into())),
+		    (":product", Value::String("".into())),
+		    (":method", Value::String("nessus".into())),
+		    (":conf", Value::Null),
+		    (":state", Value::String("open".into())),
+		    (":reason", Value::Null),
+		    (":tunnel", Value::Null),
+		    (":version", Value::Null),
+		])? {
+		    counter += 1;
+		}
+		if self.insert_nessus_finding(&mut insert_finding, Value::String(ip.into()), None, item)? {
+		    counter += 1;
+		}
+	    }
+	}
+	Ok(counter)
+    }
+
+    // A finding of the address ip, or of the name domain when the address is
+    // unknown. Returns true if it is new.
+    fn insert_nessus_finding(&self, insert_finding: &mut Cursor, ip: Value, domain: Option<&str>, item: &ReportItem) -> Result<bool> {
+	insert_finding.bind_by_name(vec![
+	    (":ip", ip),
+	    (":domain", optional_string(domain)),
+	    (":port", Value::Integer(item.port as i64)),
+	    (":protocol", Value::String(item.protocol.clone())),
+	    (":scanner", Value::String("nessus".into())),
+	    (":plugin_id", Value::String(item.plugin_id.clone())),
+	    (":name", Value::String(item.plugin_name.clone())),
+	    (":severity", Value::String(item.severity_name().into())),
+	    (":cvss", item.cvss().map_or(Value::Null, Value::Float)),
+	    (":cve", if item.cves.is_empty() { Value::Null } else { Value::String(item.cves.join(", ")) }),
+	    (":synopsis", optional_string(item.synopsis.as_deref())),
+	    (":qod", Value::Null),
+	    (":output", optional_string(item.plugin_output.as_deref())),
+	])?;
+	insert_finding.next()?;
+	Ok(self.conn.change_count() > 0)
+    }
+
+    pub fn insert_openvas_scan(&self, openvas: OpenVas) -> Result<u32> {
 	let mut counter = 0;
+	let scan_id = self.insert_scan("openvas", None, None)?;
+	let mut services = ServiceCursors::new(&self.conn, scan_id)?;
+	let mut insert_finding = self.conn.prepare(INSERT_FINDING)?.into_cursor();
+	let mut hosts = HostCursors::new(&self.conn)?;
+	let mut update_host = self.conn.prepare(UPDATE_HOST)?.into_cursor();
+	let mut domains = DomainCursors::new(&self.conn, scan_id)?;
+
+	for result in openvas.results() {
+	    let (host_id, ip) = hosts.id(&result.host.ip)?;
+	    let ip = &ip;
+	    let hostname = result.host.hostname.as_deref();
+	    if let Some(host_id) = host_id {
+		update_host.bind_by_name(vec![
+		    (":id", Value::Integer(host_id)),
+		    (":os", Value::Null),
+		    (":fqdn", optional_string(hostname)),
+		    (":netbios_name", Value::Null),
+		])?;
+		update_host.next()?;
+	    }
+	    if let Some(hostname) = hostname {
+		domains.insert(DomainRow {
+		    ip: Some(ip),
+		    domain: hostname,
+		    tool: "openvas",
+		    ..Default::default()
+		})?;
+	    }
+
+	    // GMP reports do not name the service, only that something answered on the port
+	    let (port, protocol) = result.port();
+	    if port != 0 && services.insert(vec![
+		(":ip", Value::String(ip.clone())),
+		(":port", Value::Integer(port as i64)),
+		(":protocol", Value::String(protocol.into())),
+		(":name", Value::Null),
+		(":product", Value::String("".into())),
+		(":method", Value::String("openvas".into())),
+		(":conf", Value::Null),
+		(":state", Value::String("open".into())),
+		(":reason", Value::Null),
+		(":tunnel", Value::Null),
+		(":version", Value::Null),
+	    ])? {
+		counter += 1;
+	    }
+
+	    let cves = result.nvt.cves();
+	    insert_finding.bind_by_name(vec![
+		(":ip", Value::String(ip.clone())),
+		(":domain", Value::Null),
+		(":port", Value::Integer(port as i64)),
+		(":protocol", Value::String(protocol.into())),
+		(":scanner", Value::String("openvas".into())),
+		(":plugin_id", Value::String(result.nvt.oid.clone())),
+		(":name", Value::String(result.name.clone())),
+		(":severity", Value::String(result.severity_name())),
+		(":cvss", Value::Float(result.severity)),
+		(":cve", if cves.is_empty() { Value::Null } else { Value::String(cves.join(", ")) }),
+		(":synopsis", optional_string(result.nvt.tag("summary"))),
+		(":qod", result.qod.as_ref().map_or(Value::Null, |qod| Value::Integer(qod.value as i64))),
+		(":output", optional_string(result.description.as_deref())),
+	    ])?;
+	    insert_finding.next()?;
+	    counter += self.conn.change_count() as u32;
+	}
+	Ok(counter)
+    }
+
+
+    pub fn insert_amass_scan(&self, amass: Amass) -> Result<u32> {
+	let scan_id = self.insert_scan("amass", None, None)?;
+	let mut domains = DomainCursors::new(&self.conn, scan_id)?;
+	let mut counter = 0;
+	let mut scan_domain = self.conn.prepare("INSERT OR IGNORE INTO scan_domain (scan_id, domain, ip) VALUES (:scan_id, :domain, :ip)")?
+	    .into_cursor();
 	for entry in &amass.entries {
+	    let sources = entry.sources.join(", ");
 	    for addr in &entry.addresses { 
-		cursor.bind_by_name(vec![
-		    (":ip", Value::String(addr.ip.clone())),
-		    (":domain", Value::String(entry.name.clone())),
-		    (":cidr", Value::String(addr.cidr.clone())),
-		    (":asn", Value::Integer(addr.asn as i64)),
-		    (":description", Value::String(addr.desc.clone())),
-		    (":amass_tag", Value::String(entry.tag.clone())),
+		scan_domain.bind_by_name(vec![
+		    (":scan_id", Value::Integer(scan_id)),
+		    (":domain", Value::String(normalize_name(&entry.name))),
+		    (":ip", Value::String(canonical_text(&addr.ip))),
 		])?;
+		scan_domain.next()?;
+		if domains.insert(DomainRow {
+		    ip: Some(&addr.ip),
+		    domain: &entry.name,
+		    cidr: Some(&addr.cidr),
+		    asn: Some(addr.asn),
+		    description: Some(&addr.desc),
+		    amass_tag: Some(&entry.tag),
+		    source: Some(&sources),
+		    tool: "amass",
+		})? {
+		    counter += 1;
+		}
+	    }
+	}
+	self.insert_dns_records("amass", amass.entries.iter().flat_map(|entry| entry.records()))?;
+	Ok(counter)
+    }
+
+    pub fn insert_subfinder_scan(&self, subfinder: Subfinder) -> Result<u32> {
+	let scan_id = self.insert_scan("subfinder", None, None)?;
+	let mut domains = DomainCursors::new(&self.conn, scan_id)?;
+	let mut counter = 0;
+	for entry in &subfinder.entries {
+	    let sources = entry.sources();
+	    if domains.insert(DomainRow {
+		ip: entry.ip.as_deref(),
+		domain: &entry.host,
+		source: Some(&sources),
+		tool: "subfinder",
+		..Default::default()
+	    })? {
 		counter += 1;
-		cursor.next()?;
 	    }
 	}
+	self.insert_dns_records("subfinder", subfinder.entries.iter().filter_map(|entry| entry.record()))?;
 	Ok(counter)
     }
 
+    pub fn insert_assetfinder_scan(&self, assetfinder: Assetfinder) -> Result<u32> {
+	let scan_id = self.insert_scan("assetfinder", None, None)?;
+	let mut domains = DomainCursors::new(&self.conn, scan_id)?;
+	let mut counter = 0;
+	for name in &assetfinder.names {
+	    if domains.insert(DomainRow {
+		domain: name,
+		tool: "assetfinder",
+		..Default::default()
+	    })? {
+		counter += 1;
+	    }
+	}
+	Ok(counter)
+    }
 
-}
+    pub fn insert_dnsx_scan(&self, dnsx: Dnsx) -> Result<u32> {
+	let scan_id = self.insert_scan("dnsx", None, None)?;
+	let mut domains = DomainCursors::new(&self.conn, scan_id)?;
+	let mut counter = 0;
+	for entry in &dnsx.entries {
+	    // CNAME only answers and NXDOMAIN are kept as unresolved names,
+	    // like a zone transfer does
+	    let mut ips: Vec<Option<&str>> = entry.addresses().map(|ip| Some(ip.as_str())).collect();
+	    if ips.is_empty() {
+		ips.push(None);
+	    }
+	    for ip in ips {
+		if domains.insert(DomainRow {
+		    ip,
+		    domain: &entry.host,
+		    source: Some("dns"),
+		    tool: "dnsx",
+		    ..Default::default()
+		})? {
+		    counter += 1;
+		}
+	    }
+	}
+	// the other answers only go to dns_record, the count stays the
+	// number of new domain entries like for the other name tools
+	self.insert_dns_records("dnsx", dnsx.entries.iter().flat_map(|entry| entry.records()))?;
+	Ok(counter)
+    }
 
+    pub fn insert_zone_scan(&self, zone: Zone) -> Result<u32> {
+	let scan_id = self.insert_scan("zone", None, None)?;
+	let mut domains = DomainCursors::new(&self.conn, scan_id)?;
+	for record in &zone.records {
+	    // _sip._tcp style service names and wildcards are not hosts
+	    if record.name.split('.').any(|label| label.starts_with('_') || label == "*") {
+		continue;
+	    }
+	    let ip = match record.kind.as_str() {
+		"A" | "AAAA" => Some(record.value.as_str()),
+		// kept as an unresolved name unless an address record shows up
+		_ => None,
+	    };
+	    domains.insert(DomainRow {
+		ip,
+		domain: &record.name,
+		source: Some("zone-transfer"),
+		tool: "zone",
+		..Default::default()
+	    })?;
+	}
+	self.insert_dns_records("zone", zone.records)
+    }
 
+    pub fn insert_crtsh_scan(&self, crtsh: Crtsh) -> Result<u32> {
+	let scan_id = self.insert_scan("crtsh", None, None)?;
+	let mut domains = DomainCursors::new(&self.conn, scan_id)?;
+	let mut certificates = CertificateCursors::new(&self.conn)?;
+	let mut counter = 0;
+	for entry in &crtsh.entries {
+	    // the precertificate and the certificate share issuer and serial
+	    let (_, new_certificate) = certificates.insert(&entry.certificate())?;
+	    if new_certificate {
+		counter += 1;
+	    }
+	    for name in entry.names() {
+		if domains.insert(DomainRow {
+		    domain: &name,
+		    source: Some("crt.sh"),
+		    tool: "crtsh",
+		    ..Default::default()
+		})? {
+		    counter += 1;
+		}
+	    }
+	}
+	Ok(counter)
+    }
 
+    /// Addresses a name resolves to. CNAME chains are followed through
+    /// dns_record, the addresses of every name on the way are taken from
+    /// dns_record and the domain table.
+    pub fn resolve(&self, name: &str) -> Result<Vec<String>> {
+	let mut select_cname = self.conn.prepare("SELECT value FROM dns_record WHERE name = :name AND type = 'CNAME'")?
+	    .into_cursor();
+	let mut select_ip = self.conn.prepare(r#"
+SELECT value FROM dns_record WHERE name = :name AND type IN ('A', 'AAAA')
+UNION
+SELECT ip FROM domain WHERE domain = :name AND ip IS NOT NULL"#)?
+	    .into_cursor();
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use serde_xml_rs::from_str;
-    
+	// names seen so far, a CNAME loop ends when it comes back to one of them
+	let mut names = vec![normalize_name(name)];
+	let mut ips: Vec<String> = vec![];
+	let mut i = 0;
+	while i < names.len() {
+	    select_ip.bind_by_name(vec![(":name", Value::String(names[i].clone()))])?;
+	    while let Some(row) = select_ip.next()? {
+		if let Some(ip) = row[0].as_string() {
+		    ips.push(ip.to_string());
+		}
+	    }
+	    select_cname.bind_by_name(vec![(":name", Value::String(names[i].clone()))])?;
+	    while let Some(row) = select_cname.next()? {
+		if let Some(target) = row[0].as_string() {
+		    if !names.iter().any(|name| name == target) {
+			names.push(target.to_string());
+		    }
+		}
+	    }
+	    i += 1;
+	}
+	ips.sort();
+	ips.dedup();
+	Ok(ips)
+    }
 
-    const NMAP_XML: &str = r#"
-<nmaprun scanner="nmap" args="nmap -sTV -iL ips.lst -oA asdf" start="1643060432" startstr="Mon Jan 24 21:40:32 2022" version="7.92" xmloutputversion="1.05">
+    pub fn insert_ffuf_scan(&self, ffuf: Ffuf) -> Result<u32> {
+	let paths = ffuf.results.iter().map(|result| WebPath {
+	    url: result.url.clone(),
+	    status: result.status,
+	    length: Some(result.length),
+	    words: Some(result.words),
+	    redirect: Some(result.redirect_location.clone()).filter(|r| !r.is_empty()),
+	});
+	self.insert_web_paths("ffuf", ffuf.wordlist(), paths)
+    }
 
-<host starttime="1643060432" endtime="1643060451">
-<status state="up" reason="syn-ack" reason_ttl="0"/>
-<address addr="104.19.128.108" addrtype="ipv4"/>
-<ports>
-    <port protocol="tcp" portid="80"><state state="open" reason="syn-ack" reason_ttl="52"/><service name="http" product="OpenResty web app server" method="probed" conf="10"><cpe>cpe:/a:openresty:ngx_openresty</cpe></service><script id="http-methods" output="&#xa;  Supported Methods: GET HEAD POST OPTIONS"><table key="Supported Methods">
-<elem>GET</elem>
-<elem>HEAD</elem>
-<elem>POST</elem>
-<elem>OPTIONS</elem>
-</table>
-</script><script id="http-server-header" output="openresty"><elem>openresty</elem>
-</script><script id="http-title" output="Not found."><elem key="title">Not found.</elem>
-</script></port>
-    <port protocol="tcp" portid="443">
-    	<state state="open" reason="syn-ack" reason_ttl="0"/>
-    	<service name="http" product="Cloudflare http proxy" tunnel="ssl" method="probed" conf="10"/>
-    </port>
-    <port protocol="tcp" portid="8080">
-    	<state state="open" reason="syn-ack" reason_ttl="0"/>
-    	<service name="http" product="Cloudflare http proxy" method="probed" conf="10"/>
-    </port>
-    <port protocol="tcp" portid="8443">
-    	<state state="open" reason="syn-ack" reason_ttl="0"/>
-    	<service name="http" product="Cloudflare http proxy" tunnel="ssl" method="probed" conf="10"/>
-    </port>
-</ports>
-</host>
-</nmaprun>
-"#;
-    const AMASS_JSON: &str = r#"{"name":"1.thumbs.4chan.org","domain":"4chan.org","addresses":[{"ip":"104.19.129.108","cidr":"104.16.0.0/14","asn":13335,"desc":"CLOUDFLARENET - Cloudflare, Inc."},{"ip":"104.19.128.108","cidr":"104.16.0.0/14","asn":13335,"desc":"CLOUDFLARENET - Cloudflare, Inc."}],"tag":"api","sources":["AlienVault"]}
-{"name":"blog.4chan.org","domain":"4chan.org","addresses":[{"ip":"74.114.154.18","cidr":"74.114.152.0/22","asn":2635,"desc":"AUTOMATTIC - Automattic, Inc"},{"ip":"74.114.154.22","cidr":"74.114.152.0/22","asn":2635,"desc":"AUTOMATTIC - Automattic, Inc"}],"tag":"api","sources":["AlienVault"]}
-{"name":"4chan.org","domain":"4chan.org","addresses":[{"ip":"104.19.128.108","cidr":"104.16.0.0/14","asn":13335,"desc":"CLOUDFLARENET - Cloudflare, Inc."},{"ip":"104.19.129.108","cidr":"104.16.0.0/14","asn":13335,"desc":"CLOUDFLARENET - Cloudflare, Inc."}],"tag":"dns","sources":["DNS","AlienVault","SonarSearch"]}"#;
+    pub fn insert_feroxbuster_scan(&self, ferox: Feroxbuster) -> Result<u32> {
+	let paths = ferox.entries.iter().map(|response| WebPath {
+	    url: response.url.clone(),
+	    status: response.status,
+	    length: Some(response.content_length),
+	    words: Some(response.word_count),
+	    redirect: response.location().map(|location| location.to_string()),
+	});
+	self.insert_web_paths("feroxbuster", ferox.wordlist.as_deref(), paths)
+    }
 
+    pub fn insert_gobuster_scan(&self, gobuster: Gobuster) -> Result<u32> {
+	// without the banner we only know the target when gobuster ran in expanded mode (-e)
+	let mut paths = vec![];
+	for entry in &gobuster.entries {
+	    let url = if entry.path.contains("://") {
+		entry.path.clone()
+	    } else if let Some(ref url) = gobuster.url {
+		format!("{}{}", url.trim_end_matches('/'), entry.path)
+	    } else {
+		return Err(format!("gobuster result {} has no target url, use -e or keep the banner", entry.path).into());
+	    };
+	    paths.push(WebPath {
+		url,
+		status: entry.status,
+		length: entry.size,
+		words: None,
+		redirect: entry.redirect.clone(),
+	    });
+	}
+	self.insert_web_paths("gobuster", gobuster.wordlist.as_deref(), paths)
+    }
 
+    pub fn insert_testssl_scan(&self, testssl: Testssl) -> Result<u32> {
+	self.insert_tls_services("testssl", testssl.services())
+    }
 
-    #[test]
-    fn create_table() {
-	let db = Db::new(":memory:").unwrap();
-	assert!(db.create_table().is_ok());
+    pub fn insert_sslscan_scan(&self, sslscan: Sslscan) -> Result<u32> {
+	self.insert_tls_services("sslscan", sslscan.services())
     }
 
-    #[test]
-    fn insert_nmap_scan() {
-	let db = Db::new(":memory:").unwrap();
-	db.create_table().unwrap();
+    fn insert_tls_services(&self, tool: &str, tls_services: Vec<TlsService>) -> Result<u32> {
+	let mut counter = 0;
+	let scan_id = self.insert_scan(tool, None, None)?;
+	let mut services = ServiceCursors::new(&self.conn, scan_id)?;
+	let mut certificates = CertificateCursors::new(&self.conn)?;
+	let mut domains = DomainCursors::new(&self.conn, scan_id)?;
+	let mut insert_protocol = self.conn.prepare(r#"
+INSERT INTO tls_protocol VALUES (:ip, :port, :protocol, :enabled, :tool)
+ON CONFLICT (ip, port, protocol) DO UPDATE SET enabled = excluded.enabled, tool = excluded.tool"#)?
+	    .into_cursor();
+	let mut insert_cipher = self.conn.prepare(
+	    "INSERT OR IGNORE INTO tls_cipher VALUES (:ip, :port, :protocol, :cipher, :bits, :strength, :tool)")?
+	    .into_cursor();
+	let mut insert_finding = self.conn.prepare(INSERT_FINDING)?.into_cursor();
 
-	let nmap = Nmap::new(NMAP_XML.as_bytes()).unwrap();
-	let count = db.insert_nmap_scan(nmap);
-	//count.unwrap();
-	assert!(count.is_ok());
-	assert!(count.unwrap() == 4);
+	for tls in &tls_services {
+	    // nothing to hang the results on for a name we never resolved
+	    let Some(ip) = self.lookup_ip(&tls.ip)? else {
+		continue;
+	    };
+	    let port = Value::Integer(tls.port as i64);
+	    services.insert(vec![
+		(":ip", Value::String(ip.clone())),
+		(":port", port.clone()),
+		(":protocol", Value::String("tcp".into())),
+		(":name", optional_string(tls.service.as_deref())),
+		(":product", Value::String("".into())),
+		(":method", Value::String(tool.into())),
+		(":conf", Value::Null),
+		(":state", Value::String("open".into())),
+		(":reason", Value::Null),
+		// testssl and sslscan only report what speaks TLS
+		(":tunnel", Value::String("ssl".into())),
+		(":version", Value::Null),
+	    ])?;
 
-	let mut cursor = db.conn.prepare("SELECT ip, port, service_name, state FROM service").unwrap().into_cursor();
-	let mut count = 0;
-	while let Some(row) = cursor.next().unwrap() {
-	    assert!(row[3].as_string() == "open".into());
-	    count += 1;
+	    for protocol in &tls.protocols {
+		insert_protocol.bind_by_name(vec![
+		    (":ip", Value::String(ip.clone())),
+		    (":port", port.clone()),
+		    (":protocol", Value::String(protocol.name.clone())),
+		    (":enabled", Value::Integer(protocol.enabled as i64)),
+		    (":tool", Value::String(tool.into())),
+		])?;
+		insert_protocol.next()?;
+	    }
+	    for cipher in &tls.ciphers {
+		insert_cipher.bind_by_name(vec![
+		    (":ip", Value::String(ip.clone())),
+		    (":port", port.clone()),
+		    (":protocol", Value::String(cipher.protocol.clone())),
+		    (":cipher", Value::String(cipher.name.clone())),
+		    (":bits", cipher.bits.map_or(Value::Null, |bits| Value::Integer(bits as i64))),
+		    (":strength", optional_string(cipher.strength.as_deref())),
+		    (":tool", Value::String(tool.into())),
+		])?;
+		insert_cipher.next()?;
+		counter += self.conn.change_count() as u32;
+	    }
+	    for certificate in &tls.certificates {
+		if certificates.insert_for_service(&mut domains, certificate, &ip, tls.port, tool)? {
+		    counter += 1;
+		}
+	    }
+	    for issue in &tls.issues {
+		insert_finding.bind_by_name(vec![
+		    (":ip", Value::String(ip.clone())),
+		    (":domain", Value::Null),
+		    (":port", port.clone()),
+		    (":protocol", Value::String("tcp".into())),
+		    (":scanner", Value::String(tool.into())),
+		    (":plugin_id", Value::String(issue.id.clone())),
+		    (":name", Value::String(issue.id.clone())),
+		    (":severity", Value::String(issue.severity.clone())),
+		    (":cvss", Value::Null),
+		    (":cve", optional_string(issue.cve.as_deref())),
+		    (":synopsis", Value::Null),
+		    (":qod", Value::Null),
+		    (":output", Value::String(issue.finding.clone())),
+		])?;
+		insert_finding.next()?;
+		counter += self.conn.change_count() as u32;
+	    }
 	}
-	assert!(count == 4);
+	Ok(counter)
+    }
 
-	// check if we also have the script tags added to the db
-	let mut cursor = db.conn.prepare(r#"
-SELECT sc.ip, sc.port, sc.script_id, sc.script_output, s.service_name, s.state
-FROM script as sc, service as s 
-WHERE sc.ip = s.ip AND 
-      sc.port = s.port
-"#).unwrap().into_cursor();
-	let script_ids = &["http-methods", "http-server-header", "http-title"];
-	let mut count = 0;
-	while let Some(row) = cursor.next().unwrap() {
-	    assert!(script_ids.contains(&row[2].as_string().unwrap()));
-	    count += 1; 
+    /// Marks the names that only resolve because of a wildcard record. A
+    /// parent is a wildcard zone if it has a *. record with addresses or if at
+    /// least min_names of its children resolve to the same set of addresses.
+    /// Returns the number of newly marked names.
+    pub fn mark_wildcards(&self, min_names: usize, author: &str) -> Result<u32> {
+	let mut cursor = self.conn.prepare(r#"
+SELECT domain, ip FROM domain WHERE ip IS NOT NULL
+UNION
+SELECT name, value FROM dns_record WHERE type IN ('A', 'AAAA')
+ORDER BY 1, 2"#)?.into_cursor();
+	let mut addresses: BTreeMap<String, Vec<String>> = BTreeMap::new();
+	while let Some(row) = cursor.next()? {
+	    if let (Some(name), Some(ip)) = (row[0].as_string(), row[1].as_string()) {
+		addresses.entry(name.to_string()).or_default().push(ip.to_string());
+	    }
 	}
-	assert!(count == 3);
+
+	// parent -> address set -> names below the parent resolving to it
+	let mut children: HashMap<&str, HashMap<&[String], Vec<&str>>> = HashMap::new();
+	for (name, ips) in &addresses {
+	    match name.split_once('.') {
+		// registered domains under a TLD sharing a hoster are not a wildcard
+		Some((_, parent)) if !is_public_suffix(parent) => {
+		    children.entry(parent).or_default().entry(ips).or_default().push(name);
+		},
+		_ => continue,
+	    }
+	}
+
+	let mut history = self.conn.prepare(r#"
+INSERT INTO history (entity, entity_id, column_name, old_value, new_value, author, time)
+SELECT 'domain', id, 'wildcard', '0', '1', :author, datetime('now') FROM domain WHERE domain = :domain AND wildcard = 0"#)?
+	    .into_cursor();
+	let mut update = self.conn.prepare("UPDATE domain SET wildcard = 1 WHERE domain = :domain AND wildcard = 0")?
+	    .into_cursor();
+	let mut counter = 0;
+	for (parent, sets) in &children {
+	    let wildcard_ips = addresses.get(&format!("*.{}", parent)).map(|ips| ips.as_slice());
+	    for (ips, names) in sets {
+		if names.len() < min_names && wildcard_ips != Some(ips) {
+		    continue;
+		}
+		for name in names {
+		    history.bind_by_name(vec![
+			(":domain", Value::String(name.to_string())),
+			(":author", Value::String(author.into())),
+		    ])?;
+		    history.next()?;
+		    update.bind_by_name(vec![(":domain", Value::String(name.to_string()))])?;
+		    update.next()?;
+		    counter += self.conn.change_count() as u32;
+		}
+	    }
+	}
+	Ok(counter)
     }
 
-    #[test]
-    fn insert_amass_scan() {
-	let db = Db::new(":memory:").unwrap();
-	db.create_table().unwrap();
+    /// Domain rows ordered by name, without the wildcard ones unless all is
+    /// set and only those carrying all of the tags.
+    pub fn domains(&self, all: bool, filter: ScopeFilter, tags: &[String]) -> Result<Vec<DomainEntry>> {
+	let rules = self.scope_rules()?;
+	let tagged = self.tagged(Entity::Domain, tags)?;
+	let mut cursor = self.conn.prepare(
+	    "SELECT domain, ip, source, tool, wildcard, scope, id, first_seen FROM domain WHERE :all OR wildcard = 0 ORDER BY domain, ip")?
+	    .into_cursor();
+	cursor.bind_by_name(vec![(":all", Value::Integer(all as i64))])?;
+	let mut domains = vec![];
+	while let Some(row) = cursor.next()? {
+	    let scope = Scope::from_column(row[5].as_string().unwrap_or(""));
+	    if !filter.allows(&rules, scope) {
+		continue;
+	    }
+	    if let (Some(tagged), Some(id)) = (&tagged, row[6].as_integer()) {
+		if !tagged.contains(&id) {
+		    continue;
+		}
+	    }
+	    domains.push(DomainEntry {
+		domain: row[0].as_string().unwrap_or("").to_string(),
+		ip: row[1].as_string().map(|ip| ip.to_string()),
+		source: row[2].as_string().map(|source| source.to_string()),
+		tool: row[3].as_string().map(|tool| tool.to_string()),
+		wildcard: row[4].as_integer() == Some(1),
+		scope,
+		first_seen: row[7].as_string().map(|first_seen| first_seen.to_string()),
+		id: row[6].as_integer().unwrap_or(0),
+	    });
+	}
+	Ok(domains)
+    }
 
-	let amass = Amass::new(AMASS_JSON.as_bytes()).unwrap();
-	let count = db.insert_amass_scan(amass);
-	assert!(&count.is_ok());
-	assert!(count.unwrap() == 6);
+    /// Host rows ordered by address, IPv4 first.
+    pub fn hosts(&self) -> Result<Vec<HostEntry>> {
+	let mut cursor = self.conn.prepare("SELECT ip, scope, first_seen, id FROM host ORDER BY length(ip_bin), ip_bin")?
+	    .into_cursor();
+	let mut hosts = vec![];
+	while let Some(row) = cursor.next()? {
+	    hosts.push(HostEntry {
+		ip: row[0].as_string().unwrap_or("").to_string(),
+		scope: Scope::from_column(row[1].as_string().unwrap_or("")),
+		first_seen: row[2].as_string().map(|first_seen| first_seen.to_string()),
+		id: row[3].as_integer().unwrap_or(0),
+	    });
+	}
+	Ok(hosts)
+    }
 
-	let mut cursor = db.conn.prepare("SELECT ip, domain, cidr, asn, description, amass_tag FROM domain WHERE domain = '4chan.org'")
-	    .unwrap()
+    /// Service rows ordered by address and port, IPv4 first. A service
+    /// speaks TLS if nmap saw a tunnel or testssl or sslscan talked to it.
+    pub fn services(&self) -> Result<Vec<ServiceEntry>> {
+	let mut cursor = self.conn.prepare(r#"
+SELECT s.ip, s.port, s.protocol, s.service_name, s.service_product, s.state, s.tunnel = 'ssl', s.scope,
+    s.first_seen, s.last_seen, s.id
+FROM service AS s LEFT JOIN host AS h ON h.id = s.host_id
+ORDER BY length(h.ip_bin), h.ip_bin, s.ip, s.port"#)?
 	    .into_cursor();
-	let mut count = 0;
-	while let Some(row) = cursor.next().unwrap() {
-	    assert!(row[1].as_string().unwrap() == "4chan.org");
-	    assert!(row[3].as_integer().unwrap() == 13335);
-	    count += 1;
+	let mut services = vec![];
+	while let Some(row) = cursor.next()? {
+	    services.push(ServiceEntry {
+		ip: row[0].as_string().unwrap_or("").to_string(),
+		port: row[1].as_integer().unwrap_or(0) as u16,
+		protocol: row[2].as_string().map(|protocol| protocol.to_string()),
+		service_name: row[3].as_string().filter(|name| !name.is_empty()).map(|name| name.to_string()),
+		product: row[4].as_string().filter(|product| !product.is_empty()).map(|product| product.to_string()),
+		state: row[5].as_string().map(|state| state.to_string()),
+		ssl: row[6].as_integer() == Some(1),
+		scope: Scope::from_column(row[7].as_string().unwrap_or("")),
+		first_seen: row[8].as_string().map(|first_seen| first_seen.to_string()),
+		last_seen: row[9].as_string().map(|last_seen| last_seen.to_string()),
+		id: row[10].as_integer().unwrap_or(0),
+	    });
 	}
-	assert!(count == 2);
+	Ok(services)
+    }
+
+    /// Returns false if the rule was already there.
+    pub fn add_scope_rule(&self, rule: &Rule) -> Result<bool> {
+	let mut cursor = self.conn.prepare("INSERT OR IGNORE INTO scope VALUES (:action, :kind, :value)")?
+	    .into_cursor();
+	cursor.bind_by_name(scope_row(rule))?;
+	cursor.next()?;
+	Ok(self.conn.change_count() > 0)
+    }
+
+    /// Returns false if there was no such rule.
+    pub fn remove_scope_rule(&self, rule: &Rule) -> Result<bool> {
+	let mut cursor = self.conn.prepare("DELETE FROM scope WHERE action = :action AND kind = :kind AND value = :value")?
+	    .into_cursor();
+	cursor.bind_by_name(scope_row(rule))?;
+	cursor.next()?;
+	Ok(self.conn.change_count() > 0)
+    }
+
+    pub fn scope_rules(&self) -> Result<ScopeRules> {
+	let mut cursor = self.conn.prepare("SELECT action, value FROM scope ORDER BY action DESC, kind, value")?
+	    .into_cursor();
+	let mut rules = vec![];
+	while let Some(row) = cursor.next()? {
+	    let include = row[0].as_string() == Some("include");
+	    rules.push(Rule::parse(include, row[1].as_string().unwrap_or(""))?);
+	}
+	Ok(ScopeRules {
+	    rules,
+	})
     }
 
-   
+    /// Marks every host, domain and service as in, out of or of unknown
+    /// scope. Run after imports and whenever the rules change.
+    ///
+    /// A domain row is out if its name or its address is excluded. An
+    /// address nothing says anything about is in scope when an in scope
+    /// name resolves to it, services follow their host.
+    pub fn apply_scope(&self, author: &str) -> Result<()> {
+	let rules = self.scope_rules()?;
+	self.transaction(|| self.update_scope(&rules, author))
+    }
+
+    fn update_scope(&self, rules: &ScopeRules, author: &str) -> Result<()> {
+
+	let mut hosts = vec![];
+	let mut cursor = self.conn.prepare("SELECT id, ip FROM host")?.into_cursor();
+	while let Some(row) = cursor.next()? {
+	    if let (Some(id), Some(ip)) = (row[0].as_integer(), row[1].as_string()) {
+		hosts.push((id, rules.ip(ip)));
+	    }
+	}
+	let mut update = self.conn.prepare("UPDATE host SET scope = :scope WHERE id = :id")?.into_cursor();
+	for (id, scope) in hosts {
+	    update.bind_by_name(vec![
+		(":scope", Value::String(scope.as_str().into())),
+		(":id", Value::Integer(id)),
+	    ])?;
+	    update.next()?;
+	}
+
+	let mut domains = vec![];
+	let mut cursor = self.conn.prepare("SELECT id, domain, ip, scope FROM domain")?.into_cursor();
+	while let Some(row) = cursor.next()? {
+	    if let (Some(id), Some(domain)) = (row[0].as_integer(), row[1].as_string()) {
+		let ip = row[2].as_string().map_or(Scope::Unknown, |ip| rules.ip(ip));
+		let scope = rules.name(domain).combine(ip);
+		if row[3].as_string() != Some(scope.as_str()) {
+		    domains.push((id, row[3].clone(), scope));
+		}
+	    }
+	}
+	let mut history = History::user(&self.conn, author)?;
+	let mut update = self.conn.prepare("UPDATE domain SET scope = :scope WHERE id = :id")?.into_cursor();
+	for (id, old_scope, scope) in domains {
+	    let scope = Value::String(scope.as_str().into());
+	    update.bind_by_name(vec![
+		(":scope", scope.clone()),
+		(":id", Value::Integer(id)),
+	    ])?;
+	    update.next()?;
+	    history.record("domain", id, "scope", &old_scope, &scope)?;
+	}
+
+	self.conn.execute(r#"
+UPDATE host SET scope = 'in'
+WHERE scope = 'unknown' AND id IN (SELECT host_id FROM domain WHERE scope = 'in');"#)?;
+	let mut cursor = self.conn.prepare(r#"
+INSERT INTO history (entity, entity_id, column_name, old_value, new_value, author, time)
+SELECT 'service', id, 'scope', scope, new_scope, :author, datetime('now') FROM (
+    SELECT id, scope, coalesce((SELECT scope FROM host WHERE host.id = service.host_id), 'unknown') AS new_scope
+    FROM service)
+WHERE scope != new_scope"#)?
+	    .into_cursor();
+	cursor.bind_by_name(vec![(":author", Value::String(author.into()))])?;
+	cursor.next()?;
+	self.conn.execute(
+	    "UPDATE service SET scope = coalesce((SELECT scope FROM host WHERE host.id = service.host_id), 'unknown')")?;
+	Ok(())
+    }
+
+    pub fn domain_networks(&self) -> Result<Vec<NetworkEntry>> {
+	let mut cursor = self.conn.prepare(r#"
+SELECT d.domain, d.ip, d.cidr, d.asn, d.description, d.scope, coalesce(h.shared_approved, 0), d.id
+FROM domain AS d LEFT JOIN host AS h ON h.id = d.host_id
+WHERE d.ip IS NOT NULL
+ORDER BY d.domain, d.ip"#)?
+	    .into_cursor();
+	let mut entries = vec![];
+	while let Some(row) = cursor.next()? {
+	    entries.push(NetworkEntry {
+		domain: row[0].as_string().unwrap_or("").to_string(),
+		ip: row[1].as_string().unwrap_or("").to_string(),
+		cidr: row[2].as_string().map(|cidr| cidr.to_string()),
+		asn: row[3].as_integer(),
+		description: row[4].as_string().map(|description| description.to_string()),
+		scope: Scope::from_column(row[5].as_string().unwrap_or("")),
+		approved: row[6].as_integer() == Some(1),
+		id: row[7].as_integer().unwrap_or(0),
+	    });
+	}
+	Ok(entries)
+    }
+
+    /// Approves an address on shared infrastructure as a target, or takes
+    /// the approval back. Returns false if nothing changed.
+    pub fn approve_shared(&self, ip: &str, approved: bool) -> Result<bool> {
+	let id = match self.host_id(ip)? {
+	    Some(id) => id,
+	    None => return Err(format!("no host {} in the database", ip).into()),
+	};
+	let mut cursor = self.conn.prepare(
+	    "UPDATE host SET shared_approved = :approved WHERE id = :id AND shared_approved != :approved")?
+	    .into_cursor();
+	cursor.bind_by_name(vec![
+	    (":approved", Value::Integer(approved as i64)),
+	    (":id", Value::Integer(id)),
+	])?;
+	cursor.next()?;
+	Ok(self.conn.change_count() > 0)
+    }
+
+    /// Adds the tag to, or removes it from, every row of the entity matching
+    /// all conditions. Returns the number of rows that changed.
+    pub fn tag(&self, tag: &str, entity: Entity, conditions: &[Condition], add: bool) -> Result<u32> {
+	let tag = check_tag(tag)?;
+	let ids = self.select_ids(entity, conditions)?;
+	let mut cursor = self.conn.prepare("INSERT OR IGNORE INTO tag (name) VALUES (:tag)")?.into_cursor();
+	cursor.bind_by_name(vec![(":tag", Value::String(tag.into()))])?;
+	cursor.next()?;
+	let sql = if add {
+	    "INSERT OR IGNORE INTO entity_tag SELECT id, :entity, :id FROM tag WHERE name = :tag"
+	} else {
+	    "DELETE FROM entity_tag WHERE tag_id = (SELECT id FROM tag WHERE name = :tag) AND entity = :entity AND entity_id = :id"
+	};
+	let mut cursor = self.conn.prepare(sql)?.into_cursor();
+	let mut counter = 0;
+	for id in ids {
+	    cursor.bind_by_name(vec![
+		(":tag", Value::String(tag.into())),
+		(":entity", Value::String(entity.as_str().into())),
+		(":id", Value::Integer(id)),
+	    ])?;
+	    cursor.next()?;
+	    counter += self.conn.change_count() as u32;
+	}
+	self.conn.execute("DELETE FROM tag WHERE id NOT IN (SELECT tag_id FROM entity_tag)")?;
+	Ok(counter)
+    }
+
+    /// Every tag with the number of rows of each entity carrying it.
+    pub fn tags(&self) -> Result<Vec<TagEntry>> {
+	let mut cursor = self.conn.prepare(r#"
+SELECT t.name, e.entity, count(*)
+FROM tag AS t, entity_tag AS e
+WHERE e.tag_id = t.id
+GROUP BY t.name, e.entity
+ORDER BY t.name, e.entity"#)?
+	    .into_cursor();
+	let mut tags = vec![];
+	while let Some(row) = cursor.next()? {
+	    tags.push(TagEntry {
+		tag: row[0].as_string().unwrap_or("").to_string(),
+		entity: row[1].as_string().unwrap_or("").to_string(),
+		count: row[2].as_integer().unwrap_or(0),
+	    });
+	}
+	Ok(tags)
+    }
+
+    /// Ids of the entity carrying all of the tags, None without tags,
+    /// i.e. no tag filter.
+    pub fn tagged(&self, entity: Entity, tags: &[String]) -> Result<Option<HashSet<i64>>> {
+	let mut tagged: Option<HashSet<i64>> = None;
+	let mut cursor = self.conn.prepare(r#"
+SELECT e.entity_id FROM entity_tag AS e, tag AS t
+WHERE e.tag_id = t.id AND t.name = :tag AND e.entity = :entity"#)?
+	    .into_cursor();
+	for tag in tags {
+	    cursor.bind_by_name(vec![
+		(":tag", Value::String(tag.clone())),
+		(":entity", Value::String(entity.as_str().into())),
+	    ])?;
+	    let mut ids = HashSet::new();
+	    while let Some(row) = cursor.next()? {
+		ids.extend(row[0].as_integer());
+	    }
+	    tagged = Some(match tagged {
+		Some(tagged) => tagged.intersection(&ids).copied().collect(),
+		None => ids,
+	    });
+	}
+	Ok(tagged)
+    }
+
+    /// Ids of the rows of the table the filter matches, None without a
+    /// filter, like tagged.
+    pub fn filtered(&self, table: Table, filter: Option<&Filter>) -> Result<Option<HashSet<i64>>> {
+	let filter = match filter {
+	    Some(filter) => filter,
+	    None => return Ok(None),
+	};
+	let (sql, params) = filter.compile(table)?;
+	let mut cursor = self.conn.prepare(format!("SELECT {} FROM {} WHERE {}", table.id(), table.from(), sql))?
+	    .into_cursor();
+	cursor.bind_by_name(params)?;
+	let mut ids = HashSet::new();
+	while let Some(row) = cursor.next()? {
+	    ids.extend(row[0].as_integer());
+	}
+	Ok(Some(ids))
+    }
+
+    /// Adds a note about every row of the entity matching all conditions
+    /// and returns its id.
+    pub fn add_note(&self, author: &str, body: &str, entity: Entity, conditions: &[Condition]) -> Result<i64> {
+	let ids = self.select_ids(entity, conditions)?;
+	if ids.is_empty() {
+	    return Err(format!("no {} matches, the note would be about nothing", entity).into());
+	}
+	let mut cursor = self.conn.prepare(r#"
+INSERT INTO note (author, created, updated, body)
+VALUES (:author, datetime('now'), datetime('now'), :body)"#)?
+	    .into_cursor();
+	cursor.bind_by_name(vec![
+	    (":author", Value::String(author.into())),
+	    (":body", self.seal(body, crypto::NOTE)?),
+	])?;
+	cursor.next()?;
+	let note_id = self.last_insert_rowid()?;
+
+	let mut cursor = self.conn.prepare("INSERT OR IGNORE INTO note_entity VALUES (:note_id, :entity, :id)")?
+	    .into_cursor();
+	for id in ids {
+	    cursor.bind_by_name(vec![
+		(":note_id", Value::Integer(note_id)),
+		(":entity", Value::String(entity.as_str().into())),
+		(":id", Value::Integer(id)),
+	    ])?;
+	    cursor.next()?;
+	}
+	Ok(note_id)
+    }
+
+    /// Replaces the body, the old one goes to the history.
+    pub fn edit_note(&self, id: i64, author: &str, body: &str) -> Result<()> {
+	self.archive_note(id)?;
+	let mut cursor = self.conn.prepare(
+	    "UPDATE note SET author = :author, updated = datetime('now'), body = :body WHERE id = :id")?
+	    .into_cursor();
+	cursor.bind_by_name(vec![
+	    (":author", Value::String(author.into())),
+	    (":body", self.seal(body, crypto::NOTE)?),
+	    (":id", Value::Integer(id)),
+	])?;
+	cursor.next()?;
+	Ok(())
+    }
+
+    /// Hides the note, its last version stays in the history.
+    pub fn delete_note(&self, id: i64, author: &str) -> Result<()> {
+	self.archive_note(id)?;
+	let mut cursor = self.conn.prepare(
+	    "UPDATE note SET author = :author, updated = datetime('now'), deleted = 1 WHERE id = :id")?
+	    .into_cursor();
+	cursor.bind_by_name(vec![
+	    (":author", Value::String(author.into())),
+	    (":id", Value::Integer(id)),
+	])?;
+	cursor.next()?;
+	Ok(())
+    }
+
+    // Copies the current version of a note to the history.
+    fn archive_note(&self, id: i64) -> Result<()> {
+	let mut cursor = self.conn.prepare(r#"
+INSERT INTO note_history (note_id, author, time, body)
+SELECT id, author, updated, body FROM note WHERE id = :id AND deleted = 0"#)?
+	    .into_cursor();
+	cursor.bind_by_name(vec![(":id", Value::Integer(id))])?;
+	cursor.next()?;
+	if self.conn.change_count() == 0 {
+	    return Err(format!("there is no note {}", id).into());
+	}
+	Ok(())
+    }
+
+    /// All notes that were not deleted, oldest first.
+    pub fn notes(&self) -> Result<Vec<Note>> {
+	let mut cursor = self.conn.prepare(
+	    "SELECT id, author, created, updated, body FROM note WHERE deleted = 0 ORDER BY id")?
+	    .into_cursor();
+	let mut notes = vec![];
+	while let Some(row) = cursor.next()? {
+	    notes.push(note_row(row, self.open(&row[4], crypto::NOTE)?));
+	}
+	Ok(notes)
+    }
+
+    /// Notes of the entity by id, for showing them next to the rows.
+    pub fn entity_notes(&self, entity: Entity) -> Result<HashMap<i64, Vec<Note>>> {
+	let mut cursor = self.conn.prepare(r#"
+SELECT n.id, n.author, n.created, n.updated, n.body, e.entity_id
+FROM note AS n, note_entity AS e
+WHERE e.note_id = n.id AND e.entity = :entity AND n.deleted = 0
+ORDER BY n.id"#)?
+	    .into_cursor();
+	cursor.bind_by_name(vec![(":entity", Value::String(entity.as_str().into()))])?;
+	let mut notes: HashMap<i64, Vec<Note>> = HashMap::new();
+	while let Some(row) = cursor.next()? {
+	    if let Some(id) = row[5].as_integer() {
+		let note = note_row(row, self.open(&row[4], crypto::NOTE)?);
+		notes.entry(id).or_default().push(note);
+	    }
+	}
+	Ok(notes)
+    }
+
+    /// Earlier versions of a note, oldest first.
+    pub fn note_history(&self, id: i64) -> Result<Vec<NoteVersion>> {
+	let mut cursor = self.conn.prepare(
+	    "SELECT author, time, body FROM note_history WHERE note_id = :id ORDER BY rowid")?
+	    .into_cursor();
+	cursor.bind_by_name(vec![(":id", Value::Integer(id))])?;
+	let mut versions = vec![];
+	while let Some(row) = cursor.next()? {
+	    versions.push(NoteVersion {
+		author: row[0].as_string().unwrap_or("").to_string(),
+		time: row[1].as_string().unwrap_or("").to_string(),
+		body: self.open(&row[2], crypto::NOTE)?,
+	    });
+	}
+	Ok(versions)
+    }
+
+    /// Adds a credential and the services it was tried against. Returns its
+    /// id and whether it is new, a known one only gets a missing source.
+    /// Nothing is added if one of the services is not in the database.
+    pub fn insert_credential(&self, credential: &Credential) -> Result<(i64, bool)> {
+	self.transaction(|| self.insert_credential_services(credential))
+    }
+
+    fn insert_credential_services(&self, credential: &Credential) -> Result<(i64, bool)> {
+	let secret = self.seal(&credential.secret, crypto::SECRET)?;
+	let mut cursor = self.conn.prepare(r#"
+INSERT OR IGNORE INTO credential (username, realm, kind, secret, source, created)
+VALUES (:username, :realm, :kind, :secret, :source, datetime('now'))"#)?
+	    .into_cursor();
+	cursor.bind_by_name(credential_row(credential, secret.clone()))?;
+	cursor.next()?;
+	let new = self.conn.change_count() > 0;
+
+	let mut cursor = self.conn.prepare(r#"
+UPDATE credential SET source = coalesce(source, :source)
+WHERE username = :username AND realm = :realm AND kind = :kind AND secret = :secret"#)?
+	    .into_cursor();
+	cursor.bind_by_name(credential_row(credential, secret.clone()))?;
+	cursor.next()?;
+	let mut cursor = self.conn.prepare(
+	    "SELECT id FROM credential WHERE username = :username AND realm = :realm AND kind = :kind AND secret = :secret")?
+	    .into_cursor();
+	cursor.bind_by_name(credential_row(credential, secret))?;
+	let id = cursor.next()?
+	    .and_then(|row| row[0].as_integer())
+	    .ok_or("credential vanished after insert")?;
+
+	for service in &credential.services {
+	    self.verify_credential(id, &service.ip, service.port, service.status)?;
+	}
+	Ok((id, new))
+    }
+
+    pub fn insert_secretsdump_scan(&self, secretsdump: Secretsdump) -> Result<u32> {
+	let mut counter = 0;
+	for entry in &secretsdump.entries {
+	    // a former password is no way in, and cracking it would pass it
+	    // off as the current one
+	    if entry.history.is_some() {
+		continue;
+	    }
+	    let (kind, secret) = match &entry.secret {
+		Secret::Ntlm(hash) => (CredentialKind::Ntlm, hash.clone()),
+		Secret::Cleartext(password) => (CredentialKind::Password, password.clone()),
+		Secret::Kerberos(etype, key) => (CredentialKind::Key, format!("{}:{}", etype, key)),
+		Secret::Dcc2(hash) => (CredentialKind::Hash, hash.clone()),
+	    };
+	    let mut credential = Credential::new(&entry.username, &entry.realm, kind, &secret);
+	    credential.source = Some("secretsdump".into());
+	    if self.insert_credential(&credential)?.1 {
+		counter += 1;
+	    }
+	}
+	Ok(counter)
+    }
+
+    /// Stores the cracked hashes, crack_credentials joins them to the
+    /// credentials.
+    pub fn insert_potfile(&self, potfile: Potfile) -> Result<u32> {
+	let mut cursor = self.conn.prepare("INSERT OR IGNORE INTO cracked VALUES (:hash, :plaintext)")?
+	    .into_cursor();
+	let mut counter = 0;
+	for entry in &potfile.entries {
+	    cursor.bind_by_name(vec![
+		(":hash", self.seal(&entry.hash, crypto::SECRET)?),
+		(":plaintext", self.seal(&entry.plaintext, crypto::SECRET)?),
+	    ])?;
+	    cursor.next()?;
+	    counter += self.conn.change_count() as u32;
+	}
+	Ok(counter)
+    }
+
+    /// Adds the plaintext of every cracked ntlm or hash credential as a
+    /// password of the same account, no matter whether the potfile or the
+    /// hashes were imported first. Returns the number of new passwords.
+    pub fn crack_credentials(&self) -> Result<u32> {
+	self.conn.execute(r#"
+INSERT OR IGNORE INTO credential (username, realm, kind, secret, source, created)
+SELECT c.username, c.realm, 'password', k.plaintext, 'cracked ' || c.kind || ' ' || c.id, datetime('now')
+FROM credential AS c, cracked AS k
+WHERE c.kind IN ('ntlm', 'hash') AND c.secret = k.hash"#)?;
+	Ok(self.conn.change_count() as u32)
+    }
+
+    /// Cracked ntlm and hash credentials with their plaintext.
+    pub fn cracked_credentials(&self) -> Result<Vec<(Credential, String)>> {
+	let mut cursor = self.conn.prepare(r#"
+SELECT c.id, c.username, c.realm, c.kind, c.secret, c.source, c.status, k.plaintext
+FROM credential AS c, cracked AS k
+WHERE c.kind IN ('ntlm', 'hash') AND c.secret = k.hash
+ORDER BY c.realm, c.username, c.id"#)?
+	    .into_cursor();
+	let mut cracked = vec![];
+	while let Some(row) = cursor.next()? {
+	    cracked.push((credential_from_row(row, self.open(&row[4], crypto::SECRET)?)?, self.open(&row[7], crypto::SECRET)?));
+	}
+	Ok(cracked)
+    }
+
+    /// Records whether a service accepted the credential, the credential
+    /// is valid once any service did. The service has to be in the database.
+    pub fn verify_credential(&self, id: i64, ip: &str, port: u16, status: CredentialStatus) -> Result<()> {
+	let host_id = self.host_id(ip)?.ok_or_else(|| format!("no host {} in the database", ip))?;
+	let mut cursor = self.conn.prepare("SELECT 1 FROM service WHERE host_id = :host_id AND port = :port")?
+	    .into_cursor();
+	cursor.bind_by_name(vec![
+	    (":host_id", Value::Integer(host_id)),
+	    (":port", Value::Integer(port as i64)),
+	])?;
+	if cursor.next()?.is_none() {
+	    return Err(format!("no service {} port {} in the database", ip, port).into());
+	}
+	let mut cursor = self.conn.prepare(r#"
+INSERT OR REPLACE INTO credential_service (credential_id, ip, port, host_id, status, verified)
+SELECT id, :ip, :port, :host_id, :status, datetime('now') FROM credential WHERE id = :id"#)?
+	    .into_cursor();
+	cursor.bind_by_name(vec![
+	    (":id", Value::Integer(id)),
+	    (":ip", Value::String(canonical_text(ip))),
+	    (":port", Value::Integer(port as i64)),
+	    (":host_id", Value::Integer(host_id)),
+	    (":status", Value::String(status.as_str().into())),
+	])?;
+	cursor.next()?;
+	if self.conn.change_count() == 0 {
+	    return Err(format!("there is no credential {}", id).into());
+	}
+
+	let mut cursor = self.conn.prepare(r#"
+UPDATE credential SET status = CASE
+    WHEN EXISTS (SELECT 1 FROM credential_service WHERE credential_id = :id AND status = 'valid') THEN 'valid'
+    WHEN EXISTS (SELECT 1 FROM credential_service WHERE credential_id = :id AND status = 'invalid') THEN 'invalid'
+    ELSE 'unknown' END
+WHERE id = :id"#)?
+	    .into_cursor();
+	cursor.bind_by_name(vec![(":id", Value::Integer(id))])?;
+	cursor.next()?;
+	Ok(())
+    }
+
+    /// All credentials with the services they were tried against.
+    pub fn credentials(&self) -> Result<Vec<Credential>> {
+	let mut cursor = self.conn.prepare(
+	    "SELECT id, username, realm, kind, secret, source, status FROM credential ORDER BY realm, username, kind, id")?
+	    .into_cursor();
+	let mut credentials = vec![];
+	while let Some(row) = cursor.next()? {
+	    credentials.push(credential_from_row(row, self.open(&row[4], crypto::SECRET)?)?);
+	}
+	for credential in credentials.iter_mut() {
+	    credential.services = self.credential_services(credential.id)?;
+	}
+	Ok(credentials)
+    }
+
+    /// Services a credential was tried against, with the service name if
+    /// the port is known.
+    pub fn credential_services(&self, id: i64) -> Result<Vec<CredentialService>> {
+	let mut cursor = self.conn.prepare(r#"
+SELECT c.ip, c.port, s.service_name, c.status, c.verified
+FROM credential_service AS c LEFT JOIN service AS s ON s.ip = c.ip AND s.port = c.port
+WHERE c.credential_id = :id
+ORDER BY c.host_id, c.port"#)?
+	    .into_cursor();
+	cursor.bind_by_name(vec![(":id", Value::Integer(id))])?;
+	let mut services = vec![];
+	while let Some(row) = cursor.next()? {
+	    services.push(CredentialService {
+		ip: row[0].as_string().unwrap_or("").to_string(),
+		port: row[1].as_integer().unwrap_or(0) as u16,
+		service_name: row[2].as_string().map(|name| name.to_string()),
+		status: CredentialStatus::from_column(row[3].as_string().unwrap_or("")),
+		verified: row[4].as_string().unwrap_or("").to_string(),
+	    });
+	}
+	Ok(services)
+    }
+
+    pub fn is_encrypted(&self) -> Result<bool> {
+	let mut cursor = self.conn.prepare("SELECT count(*) FROM crypto")?.into_cursor();
+	Ok(cursor.next()?.and_then(|row| row[0].as_integer()).unwrap_or(0) > 0)
+    }
+
+    /// Derives the key of an encrypted database, fails on a wrong passphrase.
+    pub fn unlock(&mut self, passphrase: &str) -> Result<()> {
+	let mut cursor = self.conn.prepare("SELECT salt, check_value FROM crypto")?.into_cursor();
+	let row = cursor.next()?.ok_or("the database is not encrypted")?;
+	let salt = row[0].as_binary().ok_or("no salt")?.to_vec();
+	let check = row[1].as_string().unwrap_or("").to_string();
+	let cipher = Cipher::new(passphrase, &salt)?;
+	if cipher.decrypt(&check, crypto::CHECK).ok().as_deref() != Some(CRYPTO_CHECK) {
+	    return Err("wrong passphrase".into());
+	}
+	self.cipher = Some(cipher);
+	Ok(())
+    }
+
+    /// Encrypts credential secrets, cracked hashes and notes with a key
+    /// derived from the passphrase, from now on every open needs an unlock.
+    /// Returns the number of values encrypted.
+    pub fn encrypt(&mut self, passphrase: &str) -> Result<u32> {
+	if self.is_encrypted()? {
+	    return Err("the database is already encrypted".into());
+	}
+	let salt = Cipher::salt()?;
+	let cipher = Cipher::new(passphrase, &salt)?;
+
+	let counter = self.transaction(|| self.encrypt_columns(&cipher, salt))?;
+	// the plaintext would linger in free pages otherwise
+	self.conn.execute("VACUUM")?;
+	self.cipher = Some(cipher);
+	Ok(counter)
+    }
+
+    fn encrypt_columns(&self, cipher: &Cipher, salt: Vec<u8>) -> Result<u32> {
+	let mut cursor = self.conn.prepare("INSERT INTO crypto (salt, check_value) VALUES (:salt, :check)")?
+	    .into_cursor();
+	cursor.bind_by_name(vec![
+	    (":salt", Value::Binary(salt)),
+	    (":check", Value::String(cipher.encrypt(CRYPTO_CHECK, crypto::CHECK)?)),
+	])?;
+	cursor.next()?;
+	let mut counter = 0;
+	for (table, column, aad) in ENCRYPTED_COLUMNS {
+	    let mut values = vec![];
+	    let mut cursor = self.conn.prepare(format!("SELECT rowid, {} FROM {}", column, table))?.into_cursor();
+	    while let Some(row) = cursor.next()? {
+		if let (Some(id), Some(value)) = (row[0].as_integer(), row[1].as_string()) {
+		    if !crypto::is_encrypted(value) {
+			values.push((id, cipher.encrypt(value, aad)?));
+		    }
+		}
+	    }
+	    let mut update = self.conn.prepare(format!("UPDATE {} SET {} = :value WHERE rowid = :id", table, column))?
+		.into_cursor();
+	    for (id, value) in values {
+		update.bind_by_name(vec![
+		    (":value", Value::String(value)),
+		    (":id", Value::Integer(id)),
+		])?;
+		update.next()?;
+		counter += 1;
+	    }
+	}
+	Ok(counter)
+    }
+
+    // Column value of a sensitive string, encrypted for the kind of column
+    // in aad if the database is encrypted.
+    fn seal(&self, value: &str, aad: &str) -> Result<Value> {
+	match &self.cipher {
+	    Some(cipher) => Ok(Value::String(cipher.encrypt(value, aad)?)),
+	    None if self.is_encrypted()? => Err("the database is encrypted, unlock it first".into()),
+	    None => Ok(Value::String(value.into())),
+	}
+    }
+
+    // Reverse of seal, plaintext from before the encryption passes.
+    fn open(&self, value: &Value, aad: &str) -> Result<String> {
+	let value = value.as_string().unwrap_or("");
+	if !crypto::is_encrypted(value) {
+	    return Ok(value.to_string());
+	}
+	match &self.cipher {
+	    Some(cipher) => cipher.decrypt(value, aad),
+	    None => Err("the database is encrypted, unlock it first".into()),
+	}
+    }
+
+    // Runs f in a transaction that is rolled back if f fails.
+    fn transaction<T>(&self, f: impl FnOnce() -> Result<T>) -> Result<T> {
+	self.conn.execute("BEGIN")?;
+	match f() {
+	    Ok(value) => {
+		self.conn.execute("COMMIT")?;
+		Ok(value)
+	    },
+	    Err(e) => {
+		self.conn.execute("ROLLBACK")?;
+		Err(e)
+	    },
+	}
+    }
+
+    // Id of the host row of an address, None if it was never imported.
+    fn host_id(&self, ip: &str) -> Result<Option<i64>> {
+	let ip_bin = match canonical_ip(ip) {
+	    Some((_, ip_bin)) => ip_bin,
+	    None => return Err(format!("{} is not an address", ip).into()),
+	};
+	let mut cursor = self.conn.prepare("SELECT id FROM host WHERE ip_bin = :ip_bin")?.into_cursor();
+	cursor.bind_by_name(vec![(":ip_bin", Value::Binary(ip_bin))])?;
+	Ok(cursor.next()?.and_then(|row| row[0].as_integer()))
+    }
+
+    fn last_insert_rowid(&self) -> Result<i64> {
+	let mut cursor = self.conn.prepare("SELECT last_insert_rowid()")?.into_cursor();
+	Ok(cursor.next()?.and_then(|row| row[0].as_integer()).unwrap_or(0))
+    }
+
+    // Ids of the entity rows matching all conditions.
+    fn select_ids(&self, entity: Entity, conditions: &[Condition]) -> Result<Vec<i64>> {
+	let columns = self.columns(entity.as_str())?;
+	let mut sql = format!("SELECT id FROM {} WHERE 1", entity);
+	let mut values = vec![];
+	for (i, condition) in conditions.iter().enumerate() {
+	    // column names cannot be bound, only known ones make it into the query
+	    if !columns.contains(&condition.column) {
+		return Err(format!("{} has no column {}, use one of {}", entity, condition.column, columns.join(", ")).into());
+	    }
+	    sql += &format!(" AND {} = :value{}", condition.column, i);
+	    values.push((format!(":value{}", i), Value::String(condition.value.clone())));
+	}
+	let mut cursor = self.conn.prepare(sql)?.into_cursor();
+	cursor.bind_by_name(values)?;
+	let mut ids = vec![];
+	while let Some(row) = cursor.next()? {
+	    ids.extend(row[0].as_integer());
+	}
+	Ok(ids)
+    }
+
+    fn columns(&self, table: &str) -> Result<Vec<String>> {
+	let mut cursor = self.conn.prepare(format!("PRAGMA table_info({})", table))?.into_cursor();
+	let mut columns = vec![];
+	while let Some(row) = cursor.next()? {
+	    columns.extend(row[1].as_string().map(|column| column.to_string()));
+	}
+	Ok(columns)
+    }
+
+    /// All (name, target) pairs of the CNAME records.
+    pub fn cname_records(&self) -> Result<Vec<(String, String)>> {
+	let mut cursor = self.conn.prepare("SELECT name, value FROM dns_record WHERE type = 'CNAME' ORDER BY name, value")?
+	    .into_cursor();
+	let mut records = vec![];
+	while let Some(row) = cursor.next()? {
+	    if let (Some(name), Some(target)) = (row[0].as_string(), row[1].as_string()) {
+		records.push((name.to_string(), target.to_string()));
+	    }
+	}
+	Ok(records)
+    }
+
+    /// Script rows ordered by address, port and script.
+    pub fn scripts(&self) -> Result<Vec<ScriptEntry>> {
+	let mut cursor = self.conn.prepare(r#"
+SELECT c.ip, c.port, c.script_id, c.script_output, c.id, c.vhost
+FROM script AS c LEFT JOIN host AS h ON h.id = c.host_id
+ORDER BY length(h.ip_bin), h.ip_bin, c.ip, c.port, c.script_id, c.vhost"#)?
+	    .into_cursor();
+	let mut scripts = vec![];
+	while let Some(row) = cursor.next()? {
+	    scripts.push(ScriptEntry {
+		ip: row[0].as_string().unwrap_or("").to_string(),
+		port: row[1].as_integer().unwrap_or(0) as u16,
+		script_id: row[2].as_string().unwrap_or("").to_string(),
+		output: row[3].as_string().unwrap_or("").to_string(),
+		vhost: row[5].as_string().map(|vhost| vhost.to_string()),
+		id: row[4].as_integer().unwrap_or(0),
+	    });
+	}
+	Ok(scripts)
+    }
+
+    /// Imported scans, oldest first by the time the tool ran.
+    pub fn scans(&self) -> Result<Vec<ScanEntry>> {
+	let mut cursor = self.conn.prepare(r#"
+SELECT id, tool, args, started, imported,
+    (SELECT count(*) FROM scan_host WHERE scan_id = scan.id),
+    (SELECT count(*) FROM scan_service WHERE scan_id = scan.id AND state = 'open'),
+    (SELECT count(DISTINCT domain) FROM scan_domain WHERE scan_id = scan.id)
+FROM scan ORDER BY coalesce(started, imported), id"#)?
+	    .into_cursor();
+	let mut scans = vec![];
+	while let Some(row) = cursor.next()? {
+	    scans.push(ScanEntry {
+		id: row[0].as_integer().unwrap_or(0),
+		tool: row[1].as_string().unwrap_or("").to_string(),
+		args: row[2].as_string().map(|args| args.to_string()),
+		started: row[3].as_string().map(|started| started.to_string()),
+		imported: row[4].as_string().unwrap_or("").to_string(),
+		hosts: row[5].as_integer().unwrap_or(0),
+		services: row[6].as_integer().unwrap_or(0),
+		domains: row[7].as_integer().unwrap_or(0),
+	    });
+	}
+	Ok(scans)
+    }
+
+    /// Changes of the services and scripts of an address, or of one port, and
+    /// of the names resolving to the address, oldest first.
+    pub fn history(&self, ip: &str, port: Option<u16>) -> Result<Vec<HistoryEntry>> {
+	let mut cursor = self.conn.prepare(r#"
+SELECT h.time, h.entity, coalesce(s.ip, c.ip, d.ip), coalesce(s.port, c.port), c.script_id || coalesce(' (' || c.vhost || ')', ''), d.domain,
+    h.column_name, h.old_value, h.new_value, h.scan_id, sc.tool, h.author
+FROM history AS h
+LEFT JOIN service AS s ON h.entity = 'service' AND s.id = h.entity_id
+LEFT JOIN script AS c ON h.entity = 'script' AND c.id = h.entity_id
+LEFT JOIN domain AS d ON h.entity = 'domain' AND d.id = h.entity_id
+LEFT JOIN scan AS sc ON sc.id = h.scan_id
+WHERE coalesce(s.ip, c.ip, d.ip) = :ip AND (:port IS NULL OR coalesce(s.port, c.port) = :port)
+ORDER BY h.time, h.rowid"#)?
+	    .into_cursor();
+	cursor.bind_by_name(vec![
+	    (":ip", Value::String(canonical_text(ip))),
+	    (":port", port.map_or(Value::Null, |port| Value::Integer(port as i64))),
+	])?;
+	let string = |value: &Value| value.as_string().map(|value| value.to_string());
+	let mut entries = vec![];
+	while let Some(row) = cursor.next()? {
+	    let ip = row[2].as_string().unwrap_or("");
+	    let address = if ip.contains(':') { format!("[{}]", ip) } else { ip.to_string() };
+	    let target = match (row[3].as_integer(), row[4].as_string(), row[5].as_string()) {
+		(Some(port), Some(script_id), _) => format!("{}:{} {}", address, port, script_id),
+		(Some(port), None, _) => format!("{}:{}", address, port),
+		(None, _, Some(domain)) => domain.to_string(),
+		_ => address,
+	    };
+	    let cause = match (row[9].as_integer(), row[10].as_string()) {
+		(Some(scan_id), Some(tool)) => format!("scan {} ({})", scan_id, tool),
+		_ => row[11].as_string().unwrap_or("").to_string(),
+	    };
+	    entries.push(HistoryEntry {
+		time: row[0].as_string().unwrap_or("").to_string(),
+		entity: row[1].as_string().unwrap_or("").to_string(),
+		target,
+		column: row[6].as_string().unwrap_or("").to_string(),
+		old_value: string(&row[7]),
+		new_value: string(&row[8]),
+		cause,
+	    });
+	}
+	Ok(entries)
+    }
+
+    /// What the scans reported, applied in the given order. A scan replaces
+    /// the ports it probed on every address it covered and the addresses of
+    /// every name it resolved.
+    pub fn snapshot(&self, scan_ids: &[i64]) -> Result<Snapshot> {
+	let mut ranges = self.conn.prepare("SELECT protocol, first_port, last_port FROM scan_port_range WHERE scan_id = :scan_id")?
+	    .into_cursor();
+	let mut hosts = self.conn.prepare("SELECT ip FROM scan_host WHERE scan_id = :scan_id")?
+	    .into_cursor();
+	let mut services = self.conn.prepare(
+	    "SELECT ip, port, protocol, state, service_name, product, version FROM scan_service WHERE scan_id = :scan_id")?
+	    .into_cursor();
+	let mut domains = self.conn.prepare("SELECT domain, ip FROM scan_domain WHERE scan_id = :scan_id")?
+	    .into_cursor();
+	let string = |value: &Value| value.as_string().map(|value| value.to_string());
+
+	let mut snapshot = Snapshot::default();
+	for &scan_id in scan_ids {
+	    let mut probed = vec![];
+	    ranges.bind_by_name(vec![(":scan_id", Value::Integer(scan_id))])?;
+	    while let Some(row) = ranges.next()? {
+		probed.push(PortRange {
+		    protocol: row[0].as_string().unwrap_or("").to_string(),
+		    first: row[1].as_integer().unwrap_or(0) as u16,
+		    last: row[2].as_integer().unwrap_or(0) as u16,
+		});
+	    }
+	    hosts.bind_by_name(vec![(":scan_id", Value::Integer(scan_id))])?;
+	    while let Some(row) = hosts.next()? {
+		let ip = row[0].as_string().unwrap_or("").to_string();
+		snapshot.services.retain(|(service_ip, port, protocol), _| {
+		    *service_ip != ip || !in_ranges(&probed, *port, protocol)
+		});
+		snapshot.hosts.entry(ip).or_default().extend(probed.iter().cloned());
+	    }
+	    services.bind_by_name(vec![(":scan_id", Value::Integer(scan_id))])?;
+	    while let Some(row) = services.next()? {
+		let key = (
+		    row[0].as_string().unwrap_or("").to_string(),
+		    row[1].as_integer().unwrap_or(0) as u16,
+		    row[2].as_string().unwrap_or("").to_string(),
+		);
+		snapshot.services.insert(key, ScanService {
+		    state: string(&row[3]),
+		    service_name: string(&row[4]),
+		    product: string(&row[5]),
+		    version: string(&row[6]),
+		});
+	    }
+	    let mut resolved: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+	    domains.bind_by_name(vec![(":scan_id", Value::Integer(scan_id))])?;
+	    while let Some(row) = domains.next()? {
+		resolved.entry(row[0].as_string().unwrap_or("").to_string())
+		    .or_default()
+		    .insert(row[1].as_string().unwrap_or("").to_string());
+	    }
+	    snapshot.domains.extend(resolved);
+	}
+	Ok(snapshot)
+    }
+
+    /// Script outputs of scans that reached any port of an address by the
+    /// given name, what the address serves for that name.
+    pub fn vhost_outputs(&self, ip: &str, name: &str) -> Result<Vec<String>> {
+	let ip_bin = match canonical_ip(ip) {
+	    Some((_, ip_bin)) => ip_bin,
+	    None => return Ok(vec![]),
+	};
+	let mut cursor = self.conn.prepare(r#"
+SELECT c.script_output FROM script AS c JOIN host AS h ON h.id = c.host_id
+WHERE h.ip_bin = :ip_bin AND c.vhost = :vhost
+ORDER BY c.port, c.script_id"#)?
+	    .into_cursor();
+	cursor.bind_by_name(vec![
+	    (":ip_bin", Value::Binary(ip_bin)),
+	    (":vhost", Value::String(normalize_name(name))),
+	])?;
+	let mut outputs = vec![];
+	while let Some(row) = cursor.next()? {
+	    if let Some(output) = row[0].as_string() {
+		outputs.push(output.to_string());
+	    }
+	}
+	Ok(outputs)
+    }
+
+    // Tools like sslscan only know the target as it was given to them, which
+    // can be a hostname. Use the address we have on record for it, None if
+    // there is none.
+    fn lookup_ip(&self, host: &str) -> Result<Option<String>> {
+	if canonical_ip(host).is_some() {
+	    return Ok(Some(canonical_text(host)));
+	}
+	Ok(self.resolve(host)?.into_iter().next())
+    }
+
+    // Returns the number of records that were not in the db before. The
+    // first tool to report a record is kept.
+    fn insert_dns_records<I>(&self, tool: &str, records: I) -> Result<u32>
+    where I: IntoIterator<Item = DnsRecord>
+    {
+	let mut cursor = self.conn.prepare(
+	    "INSERT OR IGNORE INTO dns_record (name, type, value, ttl, tool) VALUES (:name, :type, :value, :ttl, :tool)")?
+	    .into_cursor();
+	let mut counter = 0;
+	for record in records {
+	    cursor.bind_by_name(vec![
+		(":name", Value::String(record.name)),
+		(":type", Value::String(record.kind)),
+		(":value", Value::String(record.value)),
+		(":ttl", record.ttl.map_or(Value::Null, |ttl| Value::Integer(ttl as i64))),
+		(":tool", Value::String(tool.into())),
+	    ])?;
+	    cursor.next()?;
+	    counter += self.conn.change_count() as u32;
+	}
+	Ok(counter)
+    }
+
+    fn insert_web_paths<I>(&self, tool: &str, wordlist: Option<&str>, paths: I) -> Result<u32>
+    where I: IntoIterator<Item = WebPath>
+    {
+	// the first tool that found an url wins, later duplicates are ignored
+	let mut cursor = self.conn.prepare(
+	    "INSERT OR IGNORE INTO web_path VALUES (:url, :base_url, :path, :status, :length, :words, :redirect, :tool, :wordlist)")?
+	    .into_cursor();
+	let mut counter = 0;
+	for web_path in paths {
+	    let (base_url, path) = split_url(&web_path.url);
+	    cursor.bind_by_name(vec![
+		(":url", Value::String(web_path.url.clone())),
+		(":base_url", Value::String(base_url.into())),
+		(":path", Value::String(path.into())),
+		(":status", Value::Integer(web_path.status as i64)),
+		(":length", web_path.length.map_or(Value::Null, |length| Value::Integer(length as i64))),
+		(":words", web_path.words.map_or(Value::Null, |words| Value::Integer(words as i64))),
+		(":redirect", web_path.redirect.map_or(Value::Null, Value::String)),
+		(":tool", Value::String(tool.into())),
+		(":wordlist", optional_string(wordlist)),
+	    ])?;
+	    cursor.next()?;
+	    counter += self.conn.change_count() as u32;
+	}
+	Ok(counter)
+    }
+}
+
+
+const INSERT_FINDING: &str = r#"
+INSERT OR IGNORE INTO finding (ip, domain, port, protocol, scanner, plugin_id, name, severity, cvss, cve, synopsis, qod, output)
+VALUES (:ip, :domain, :port, :protocol, :scanner, :plugin_id, :name, :severity, :cvss, :cve, :synopsis, :qod, :output)"#;
+
+// scanners only know some of the host properties, keep what we already have
+const UPDATE_HOST: &str = r#"
+UPDATE host SET
+    os = coalesce(:os, os),
+    fqdn = coalesce(:fqdn, fqdn),
+    netbios_name = coalesce(:netbios_name, netbios_name)
+WHERE id = :id"#;
+
+// Columns of the service select, in the order of the rows importers build.
+const SERVICE_COLUMNS: [&str; 11] = [
+    "ip", "port", "protocol", "service_name", "service_product", "service_method", "service_conf", "state", "state_reason", "tunnel",
+    "service_version",
+];
+
+// Prepared statements of the service insert path, shared by all importers
+// that know about open ports.
+struct ServiceCursors<'l> {
+    conn: &'l Connection,
+    select: Cursor<'l>,
+    insert: Cursor<'l>,
+    seen: Cursor<'l>,
+    hosts: HostCursors<'l>,
+    history: History<'l>,
+}
+
+impl<'l> ServiceCursors<'l> {
+    fn new(conn: &'l Connection, scan_id: i64) -> Result<Self> {
+	let select = conn.prepare(format!(
+	    "SELECT {}, id FROM service WHERE ip = :ip AND port = :port", SERVICE_COLUMNS.join(", ")))?
+	    .into_cursor();
+	let insert = conn.prepare(r#"
+INSERT INTO service (ip, port, protocol, service_name, service_product, service_method, service_conf, state, state_reason, tunnel, service_version, host_id, first_seen, last_seen)
+VALUES (:ip, :port, :protocol, :name, :product, :method, :conf, :state, :reason, :tunnel, :version, :host_id, datetime('now'), datetime('now'))"#)?
+	    .into_cursor();
+	let seen = conn.prepare("UPDATE service SET last_seen = datetime('now') WHERE id = :id")?
+	    .into_cursor();
+	Ok(Self {
+	    conn,
+	    select,
+	    insert,
+	    seen,
+	    hosts: HostCursors::new(conn)?,
+	    history: History::scan(conn, scan_id)?,
+	})
+    }
+
+    // Returns true if the service was not in the db before.
+    fn insert(&mut self, mut new_row: Vec<(&str, Value)>) -> Result<bool> {
+	let (host_id, ip) = match new_row[0].1 {
+	    Value::String(ref ip) => self.hosts.id(ip)?,
+	    _ => (None, String::new()),
+	};
+	new_row[0].1 = Value::String(ip);
+	self.select.bind_by_name(vec![
+	    (":ip", new_row[0].1.clone()),
+	    (":port", new_row[1].1.clone()),
+	])?;
+	let old_row = match self.select.next()? {
+	    Some(old_row) => old_row.to_vec(),
+	    None => {
+		// we have new data, just insert it
+		let state = new_row[7].1.clone();
+		new_row.push((":host_id", host_id.map_or(Value::Null, Value::Integer)));
+		self.insert.bind_by_name(new_row)?;
+		self.insert.next()?;
+		self.history.record_new("service", "state", &state)?;
+		return Ok(true);
+	    },
+	};
+
+	// we already have the service in the db, update what changed
+	let id = old_row[SERVICE_COLUMNS.len()].as_integer().unwrap_or(0);
+	let changes = Db::merge(&old_row[..SERVICE_COLUMNS.len()], &new_row);
+	if !changes.is_empty() {
+	    let mut columns = vec![];
+	    for (name, value) in &changes {
+		let i = new_row.iter().position(|(new_name, _)| new_name == name).unwrap_or(0);
+		columns.push(format!("{} = {}", SERVICE_COLUMNS[i], name));
+		self.history.record("service", id, SERVICE_COLUMNS[i], &old_row[i], value)?;
+	    }
+	    let mut update = self.conn.prepare(format!("UPDATE service SET {} WHERE id = :id", columns.join(", ")))?
+		.into_cursor();
+	    let mut values = changes;
+	    values.push((":id", Value::Integer(id)));
+	    update.bind_by_name(values)?;
+	    update.next()?;
+	}
+	self.seen.bind_by_name(vec![(":id", Value::Integer(id))])?;
+	self.seen.next()?;
+	Ok(false)
+    }
+}
+
+// Prepared statements of the nmap script path. A script that reports
+// something else than last time replaces its output.
+struct ScriptCursors<'l> {
+    select: Cursor<'l>,
+    insert: Cursor<'l>,
+    update: Cursor<'l>,
+    history: History<'l>,
+}
+
+impl<'l> ScriptCursors<'l> {
+    fn new(conn: &'l Connection, scan_id: i64) -> Result<Self> {
+	let select = conn.prepare(
+	    "SELECT id, script_output FROM script WHERE ip = :ip AND port = :port AND script_id = :script_id AND vhost IS :vhost")?
+	    .into_cursor();
+	let insert = conn.prepare(r#"
+INSERT INTO script (ip, port, script_id, script_output, host_id, vhost)
+VALUES (:ip, :port, :script_id, :script_output, :host_id, :vhost)"#)?
+	    .into_cursor();
+	let update = conn.prepare("UPDATE script SET script_output = :script_output WHERE id = :id")?
+	    .into_cursor();
+	Ok(Self {
+	    select,
+	    insert,
+	    update,
+	    history: History::scan(conn, scan_id)?,
+	})
+    }
+
+    // vhost is the name the scan reached the port by, if any
+    fn insert(&mut self, ip: &str, port: u32, script: &Script, host_id: Option<i64>, vhost: Option<&str>) -> Result<()> {
+	let output = Value::String(script.output.clone());
+	let vhost = vhost.map_or(Value::Null, |vhost| Value::String(normalize_name(vhost)));
+	self.select.bind_by_name(vec![
+	    (":ip", Value::String(ip.into())),
+	    (":port", Value::Integer(port as i64)),
+	    (":script_id", Value::String(script.id.clone())),
+	    (":vhost", vhost.clone()),
+	])?;
+	match self.select.next()?.map(|row| row.to_vec()) {
+	    Some(old_row) if old_row[1] != output => {
+		let id = old_row[0].as_integer().unwrap_or(0);
+		self.update.bind_by_name(vec![
+		    (":script_output", output.clone()),
+		    (":id", Value::Integer(id)),
+		])?;
+		self.update.next()?;
+		self.history.record("script", id, "script_output", &old_row[1], &output)?;
+	    },
+	    Some(_) => {},
+	    None => {
+		self.insert.bind_by_name(vec![
+		    (":ip", Value::String(ip.into())),
+		    (":port", Value::Integer(port as i64)),
+		    (":script_id", Value::String(script.id.clone())),
+		    (":script_output", output.clone()),
+		    (":host_id", host_id.map_or(Value::Null, Value::Integer)),
+		    (":vhost", vhost),
+		])?;
+		self.insert.next()?;
+		self.history.record_new("script", "script_output", &output)?;
+	    },
+	}
+	Ok(())
+    }
+}
+
+// Prepared history insert, for one import or one command of a user.
+struct History<'l> {
+    insert: Cursor<'l>,
+    last_id: Cursor<'l>,
+    scan_id: Option<i64>,
+    author: Option<String>,
+}
+
+impl<'l> History<'l> {
+    fn scan(conn: &'l Connection, scan_id: i64) -> Result<Self> {
+	Self::new(conn, Some(scan_id), None)
+    }
+
+    fn user(conn: &'l Connection, author: &str) -> Result<Self> {
+	Self::new(conn, None, Some(author.into()))
+    }
+
+    fn new(conn: &'l Connection, scan_id: Option<i64>, author: Option<String>) -> Result<Self> {
+	let insert = conn.prepare(r#"
+INSERT INTO history (entity, entity_id, column_name, old_value, new_value, scan_id, author, time)
+VALUES (:entity, :entity_id, :column_name, :old_value, :new_value, :scan_id, :author, datetime('now'))"#)?
+	    .into_cursor();
+	let last_id = conn.prepare("SELECT last_insert_rowid()")?.into_cursor();
+	Ok(Self {
+	    insert,
+	    last_id,
+	    scan_id,
+	    author,
+	})
+    }
+
+    // Nothing is recorded if the value stays the same.
+    fn record(&mut self, entity: &str, entity_id: i64, column: &str, old: &Value, new: &Value) -> Result<()> {
+	let (old, new) = (history_value(old), history_value(new));
+	if old == new {
+	    return Ok(());
+	}
+	self.insert.bind_by_name(vec![
+	    (":entity", Value::String(entity.into())),
+	    (":entity_id", Value::Integer(entity_id)),
+	    (":column_name", Value::String(column.into())),
+	    (":old_value", old),
+	    (":new_value", new),
+	    (":scan_id", self.scan_id.map_or(Value::Null, Value::Integer)),
+	    (":author", optional_string(self.author.as_deref())),
+	])?;
+	self.insert.next()?;
+	Ok(())
+    }
+
+    // The first value of the row that was inserted last.
+    fn record_new(&mut self, entity: &str, column: &str, new: &Value) -> Result<()> {
+	self.last_id.bind(&[])?;
+	let id = self.last_id.next()?.and_then(|row| row[0].as_integer()).unwrap_or(0);
+	self.record(entity, id, column, &Value::Null, new)
+    }
+}
+
+// One name to address pair as an importer sees it. Names without a known
+// address are stored without an ip until some tool resolves them.
+#[derive(Default)]
+struct DomainRow<'a> {
+    ip: Option<&'a str>,
+    domain: &'a str,
+    cidr: Option<&'a str>,
+    asn: Option<u32>,
+    description: Option<&'a str>,
+    amass_tag: Option<&'a str>,
+    source: Option<&'a str>,
+    tool: &'a str,
+}
+
+// Prepared statements of the certificate path, a certificate is stored once
+// however many services present it.
+struct CertificateCursors<'l> {
+    conn: &'l Connection,
+    find: Cursor<'l>,
+    insert: Cursor<'l>,
+    update: Cursor<'l>,
+    link: Cursor<'l>,
+}
+
+impl<'l> CertificateCursors<'l> {
+    fn new(conn: &'l Connection) -> Result<Self> {
+	// not every tool reports fingerprints or serials, fall back to what
+	// identifies a certificate well enough for the same tool
+	let find = conn.prepare(r#"
+SELECT id FROM certificate
+WHERE fingerprint = :fingerprint
+   OR (issuer = :issuer AND lower(serial) = lower(:serial))
+   OR (:fingerprint IS NULL AND :serial IS NULL AND subject = :subject AND issuer = :issuer AND not_after = :not_after)
+ORDER BY fingerprint = :fingerprint DESC
+LIMIT 1"#)?.into_cursor();
+	let insert = conn.prepare(r#"
+INSERT INTO certificate (fingerprint, subject, issuer, serial, not_before, not_after, san, key_type, key_size, signature_algorithm)
+VALUES (:fingerprint, :subject, :issuer, :serial, :not_before, :not_after, :san, :key_type, :key_size, :signature_algorithm)"#)?
+	    .into_cursor();
+	// a later scan may know more about the same certificate, e.g. the fingerprint
+	let update = conn.prepare(r#"
+UPDATE certificate SET
+    fingerprint = coalesce(fingerprint, :fingerprint),
+    serial = coalesce(serial, :serial),
+    not_before = coalesce(not_before, :not_before),
+    san = coalesce(san, :san),
+    key_type = coalesce(key_type, :key_type),
+    key_size = coalesce(key_size, :key_size),
+    signature_algorithm = coalesce(signature_algorithm, :signature_algorithm)
+WHERE id = :id"#)?.into_cursor();
+	let link = conn.prepare("INSERT OR IGNORE INTO service_certificate VALUES (:id, :ip, :port)")?
+	    .into_cursor();
+	Ok(Self {
+	    conn,
+	    find,
+	    insert,
+	    update,
+	    link,
+	})
+    }
+
+    // Returns the certificate id and whether it was not in the db before.
+    fn insert(&mut self, certificate: &Certificate) -> Result<(i64, bool)> {
+	match self.find(certificate)? {
+	    Some(id) => {
+		let mut row = certificate_row(certificate);
+		row.push((":id", Value::Integer(id)));
+		self.update.bind_by_name(row)?;
+		self.update.next()?;
+		Ok((id, false))
+	    },
+	    None => {
+		self.insert.bind_by_name(certificate_row(certificate))?;
+		self.insert.next()?;
+		let id = self.find(certificate)?.ok_or("certificate vanished after insert")?;
+		Ok((id, true))
+	    },
+	}
+    }
+
+    fn find(&mut self, certificate: &Certificate) -> Result<Option<i64>> {
+	self.find.bind_by_name(certificate_row(certificate))?;
+	Ok(self.find.next()?.and_then(|row| row[0].as_integer()))
+    }
+
+    // Store a certificate once and link it to the service that presented it.
+    // Returns true if the link is new.
+    fn insert_for_service(&mut self, domains: &mut DomainCursors, certificate: &Certificate, ip: &str, port: u32, tool: &str) -> Result<bool> {
+	let (id, _) = self.insert(certificate)?;
+	self.link.bind_by_name(vec![
+	    (":id", Value::Integer(id)),
+	    (":ip", Value::String(ip.into())),
+	    (":port", Value::Integer(port as i64)),
+	])?;
+	self.link.next()?;
+	let new_link = self.conn.change_count() > 0;
+
+	// the names a certificate is valid for are likely virtual hosts on the same address
+	for san in certificate.sans.iter().filter(|san| !san.starts_with("*.")) {
+	    domains.insert(DomainRow {
+		ip: Some(ip),
+		domain: san,
+		source: Some("certificate"),
+		tool,
+		..Default::default()
+	    })?;
+	}
+	Ok(new_link)
+    }
+}
+
+// Prepared statements of the domain insert path. Every tool adds to the same
+// set of (domain, ip) pairs, the first tool to report a pair is kept as its
+// tool and source, later ones only fill in what is missing.
+struct DomainCursors<'l> {
+    select: Cursor<'l>,
+    insert: Cursor<'l>,
+    update: Cursor<'l>,
+    select_unresolved: Cursor<'l>,
+    resolve: Cursor<'l>,
+    hosts: HostCursors<'l>,
+    history: History<'l>,
+}
+
+// Columns an update of a domain row fills in, in the order of the values in
+// DomainCursors::insert after ip and domain.
+const DOMAIN_FILLED_COLUMNS: [&str; 6] = ["cidr", "asn", "description", "amass_tag", "source", "tool"];
+
+impl<'l> DomainCursors<'l> {
+    fn new(conn: &'l Connection, scan_id: i64) -> Result<Self> {
+	let select = conn.prepare(format!(
+	    "SELECT id, {} FROM domain WHERE domain = :domain AND (ip = :ip OR :ip IS NULL) LIMIT 1", DOMAIN_FILLED_COLUMNS.join(", ")))?
+	    .into_cursor();
+	let insert = conn.prepare(r#"
+INSERT INTO domain (ip, domain, cidr, asn, description, amass_tag, source, tool, wildcard, host_id, first_seen, last_seen)
+VALUES (:ip, :domain, :cidr, :asn, :description, :amass_tag, :source, :tool, :wildcard, :host_id, datetime('now'), datetime('now'))"#)?
+	    .into_cursor();
+	let update = conn.prepare(r#"
+UPDATE domain SET
+    cidr = coalesce(cidr, :cidr),
+    asn = coalesce(asn, :asn),
+    description = coalesce(description, :description),
+    amass_tag = coalesce(amass_tag, :amass_tag),
+    source = coalesce(source, :source),
+    tool = coalesce(tool, :tool),
+    last_seen = datetime('now')
+WHERE domain = :domain AND ip = :ip"#)?.into_cursor();
+	let select_unresolved = conn.prepare("SELECT id FROM domain WHERE domain = :domain AND ip IS NULL")?
+	    .into_cursor();
+	let resolve = conn.prepare(r#"
+UPDATE domain SET
+    ip = :ip,
+    cidr = coalesce(:cidr, cidr),
+    asn = coalesce(:asn, asn),
+    description = coalesce(:description, description),
+    amass_tag = coalesce(:amass_tag, amass_tag),
+    source = coalesce(:source, source),
+    tool = :tool,
+    host_id = :host_id,
+    last_seen = datetime('now')
+WHERE id = :id"#)?.into_cursor();
+	Ok(Self {
+	    select,
+	    insert,
+	    update,
+	    select_unresolved,
+	    resolve,
+	    hosts: HostCursors::new(conn)?,
+	    history: History::scan(conn, scan_id)?,
+	})
+    }
+
+    // Returns true if the pair was not in the db before.
+    fn insert(&mut self, row: DomainRow) -> Result<bool> {
+	let domain = normalize_name(row.domain);
+	let (host_id, ip) = match row.ip {
+	    Some(ip) => {
+		let (host_id, ip) = self.hosts.id(ip)?;
+		(host_id, Value::String(ip))
+	    }
+	    None => (None, Value::Null),
+	};
+	let values = vec![
+	    (":ip", ip),
+	    (":domain", Value::String(domain.clone())),
+	    (":cidr", optional_string(row.cidr)),
+	    (":asn", row.asn.map_or(Value::Null, |asn| Value::Integer(asn as i64))),
+	    (":description", optional_string(row.description)),
+	    (":amass_tag", optional_string(row.amass_tag)),
+	    (":source", optional_string(row.source)),
+	    (":tool", Value::String(row.tool.into())),
+	    // *. names are never hosts of their own, mark_wildcards finds the rest
+	    (":wildcard", Value::Integer(domain.starts_with("*.") as i64)),
+	    (":host_id", host_id.map_or(Value::Null, Value::Integer)),
+	];
+
+	self.select.bind_by_name(values[..2].to_vec())?;
+	if let Some(old_row) = self.select.next()?.map(|row| row.to_vec()) {
+	    // an unresolved name only tells us what we already know
+	    if row.ip.is_some() {
+		let id = old_row[0].as_integer().unwrap_or(0);
+		for (i, column) in DOMAIN_FILLED_COLUMNS.iter().enumerate() {
+		    if old_row[i + 1] == Value::Null {
+			self.history.record("domain", id, column, &Value::Null, &values[i + 2].1)?;
+		    }
+		}
+		self.update.bind_by_name(values)?;
+		self.update.next()?;
+	    }
+	    return Ok(false);
+	}
+	// the first address of a name known without one goes into its row, the
+	// row keeps its id and with it tags, notes and history
+	if row.ip.is_some() {
+	    self.select_unresolved.bind_by_name(vec![(":domain", Value::String(domain))])?;
+	    if let Some(id) = self.select_unresolved.next()?.and_then(|row| row[0].as_integer()) {
+		let ip = values[0].1.clone();
+		let mut values: Vec<_> = values.into_iter().filter(|(name, _)| *name != ":domain" && *name != ":wildcard").collect();
+		values.push((":id", Value::Integer(id)));
+		self.resolve.bind_by_name(values)?;
+		self.resolve.next()?;
+		self.history.record("domain", id, "ip", &Value::Null, &ip)?;
+		return Ok(true);
+	    }
+	}
+	let (column, value) = match values[0].1 {
+	    Value::Null => ("domain", values[1].1.clone()),
+	    ref ip => ("ip", ip.clone()),
+	};
+	self.insert.bind_by_name(values)?;
+	self.insert.next()?;
+	// a name without an address is new by its name
+	self.history.record_new("domain", column, &value)?;
+	Ok(true)
+    }
+}
+
+// Prepared statements to find or create the host row of an address.
+struct HostCursors<'l> {
+    insert: Cursor<'l>,
+    select: Cursor<'l>,
+}
+
+impl<'l> HostCursors<'l> {
+    fn new(conn: &'l Connection) -> Result<Self> {
+	let insert = conn.prepare("INSERT OR IGNORE INTO host (ip, ip_bin, first_seen) VALUES (:ip, :ip_bin, datetime('now'))")?
+	    .into_cursor();
+	let select = conn.prepare("SELECT id FROM host WHERE ip_bin = :ip_bin")?
+	    .into_cursor();
+	Ok(Self {
+	    insert,
+	    select,
+	})
+    }
+
+    // Host id and canonical form of an address. Anything that does not parse
+    // as an address, like an unresolved hostname, has no host and is
+    // returned as it is.
+    fn id(&mut self, ip: &str) -> Result<(Option<i64>, String)> {
+	let (ip, ip_bin) = match canonical_ip(ip) {
+	    Some(canonical) => canonical,
+	    None => return Ok((None, ip.to_string())),
+	};
+	self.insert.bind_by_name(vec![
+	    (":ip", Value::String(ip.clone())),
+	    (":ip_bin", Value::Binary(ip_bin.clone())),
+	])?;
+	self.insert.next()?;
+	self.select.bind_by_name(vec![(":ip_bin", Value::Binary(ip_bin))])?;
+	let id = self.select.next()?.and_then(|row| row[0].as_integer());
+	Ok((id, ip))
+    }
+}
+
+// id, author, created, updated and the opened body
+fn note_row(row: &[Value], body: String) -> Note {
+    Note {
+	id: row[0].as_integer().unwrap_or(0),
+	author: row[1].as_string().unwrap_or("").to_string(),
+	created: row[2].as_string().unwrap_or("").to_string(),
+	updated: row[3].as_string().unwrap_or("").to_string(),
+	body,
+    }
+}
+
+// the secret sealed by Db::seal
+fn credential_row(credential: &Credential, secret: Value) -> Vec<(&'static str, Value)> {
+    vec![
+	(":username", Value::String(credential.username.clone())),
+	(":realm", Value::String(credential.realm.clone())),
+	(":kind", Value::String(credential.kind.as_str().into())),
+	(":secret", secret),
+	(":source", optional_string(credential.source.as_deref())),
+    ]
+}
+
+// id, username, realm, kind, secret, source, status with the opened secret
+fn credential_from_row(row: &[Value], secret: String) -> Result<Credential> {
+    let kind = CredentialKind::parse(row[3].as_string().unwrap_or(""))?;
+    let mut credential = Credential::new(
+	row[1].as_string().unwrap_or(""),
+	row[2].as_string().unwrap_or(""),
+	kind,
+	&secret);
+    credential.id = row[0].as_integer().unwrap_or(0);
+    credential.source = row[5].as_string().map(|source| source.to_string());
+    credential.status = CredentialStatus::from_column(row[6].as_string().unwrap_or(""));
+    Ok(credential)
+}
+
+fn scope_row(rule: &Rule) -> Vec<(&'static str, Value)> {
+    vec![
+	(":action", Value::String(rule.action().into())),
+	(":kind", Value::String(rule.kind().into())),
+	(":value", Value::String(rule.value())),
+    ]
+}
+
+/// Canonical text and binary form (4 or 16 bytes in network order) of an
+/// address: "0:0::1" is "::1" and "::ffff:10.0.0.1" is "10.0.0.1".
+pub fn canonical_ip(ip: &str) -> Option<(String, Vec<u8>)> {
+    let ip = ip.trim().trim_start_matches('[').trim_end_matches(']');
+    // scope ids like fe80::1%eth0 are local to the scanning machine
+    let ip = ip.split('%').next()?;
+    let ip = match ip.parse::<IpAddr>().ok()? {
+	IpAddr::V6(v6) => v6.to_ipv4_mapped().map_or(IpAddr::V6(v6), IpAddr::V4),
+	v4 => v4,
+    };
+    let ip_bin = match ip {
+	IpAddr::V4(v4) => v4.octets().to_vec(),
+	IpAddr::V6(v6) => v6.octets().to_vec(),
+    };
+    Some((ip.to_string(), ip_bin))
+}
+
+// canonical_ip for tables that keep the address as text only
+fn canonical_text(ip: &str) -> String {
+    canonical_ip(ip).map_or_else(|| ip.to_string(), |(ip, _)| ip)
+}
+
+// "com", "co.uk" or "com.au", where names are registered rather than
+// handed out by a zone
+fn is_public_suffix(name: &str) -> bool {
+    match name.split_once('.') {
+	None => true,
+	Some((label, tld)) => tld.len() == 2 && !tld.contains('.') && SECOND_LEVEL_SUFFIXES.contains(&label),
+    }
+}
+
+struct WebPath {
+    url: String,
+    status: u32,
+    length: Option<u64>,
+    words: Option<u64>,
+    redirect: Option<String>,
+}
+
+fn certificate_row(certificate: &Certificate) -> Vec<(&'static str, Value)> {
+    vec![
+	(":fingerprint", optional_string(certificate.fingerprint.as_deref())),
+	(":subject", optional_string(certificate.subject.as_deref())),
+	(":issuer", optional_string(certificate.issuer.as_deref())),
+	(":serial", optional_string(certificate.serial.as_deref())),
+	(":not_before", optional_string(certificate.not_before.as_deref())),
+	(":not_after", optional_string(certificate.not_after.as_deref())),
+	(":san", if certificate.sans.is_empty() { Value::Null } else { Value::String(certificate.sans.join(", ")) }),
+	(":key_type", optional_string(certificate.key_type.as_deref())),
+	(":key_size", certificate.key_size.map_or(Value::Null, |size| Value::Integer(size as i64))),
+	(":signature_algorithm", optional_string(certificate.signature_algorithm.as_deref())),
+    ]
+}
+
+// History keeps every value as text.
+fn history_value(value: &Value) -> Value {
+    match value {
+	Value::Integer(value) => Value::String(value.to_string()),
+	Value::Float(value) => Value::String(value.to_string()),
+	Value::String(value) => Value::String(value.clone()),
+	Value::Binary(_) | Value::Null => Value::Null,
+    }
+}
+
+fn optional_string(value: Option<&str>) -> Value {
+    value.map_or(Value::Null, |value| Value::String(value.into()))
+}
+
+// http://10.10.10.10:8080/admin/login.php -> ("http://10.10.10.10:8080", "/admin/login.php")
+fn split_url(url: &str) -> (&str, &str) {
+    let start = url.find("://").map_or(0, |i| i + 3);
+    match url[start..].find('/') {
+	Some(i) => url.split_at(start + i),
+	None => (url, "/"),
+    }
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+
+    const NMAP_XML: &str = r#"
+<nmaprun scanner="nmap" args="nmap -sTV -iL ips.lst -oA asdf" start="1643060432" startstr="Mon Jan 24 21:40:32 2022" version="7.92" xmloutputversion="1.05">
+
+<host starttime="1643060432" endtime="1643060451">
+<status state="up" reason="syn-ack" reason_ttl="0"/>
+<address addr="104.19.128.108" addrtype="ipv4"/>
+<ports>
+    <port protocol="tcp" portid="80"><state state="open" reason="syn-ack" reason_ttl="52"/><service name="http" product="OpenResty web app server" method="probed" conf="10"><cpe>cpe:/a:openresty:ngx_openresty</cpe></service><script id="http-methods" output="&#xa;  Supported Methods: GET HEAD POST OPTIONS"><table key="Supported Methods">
+<elem>GET</elem>
+<elem>HEAD</elem>
+<elem>POST</elem>
+<elem>OPTIONS</elem>
+</table>
+</script><script id="http-server-header" output="openresty"><elem>openresty</elem>
+</script><script id="http-title" output="Not found."><elem key="title">Not found.</elem>
+</script></port>
+    <port protocol="tcp" portid="443">
+    	<state state="open" reason="syn-ack" reason_ttl="0"/>
+    	<service name="http" product="Cloudflare http proxy" tunnel="ssl" method="probed" conf="10"/>
+    </port>
+    <port protocol="tcp" portid="8080">
+    	<state state="open" reason="syn-ack" reason_ttl="0"/>
+    	<service name="http" product="Cloudflare http proxy" method="probed" conf="10"/>
+    </port>
+    <port protocol="tcp" portid="8443">
+    	<state state="open" reason="syn-ack" reason_ttl="0"/>
+    	<service name="http" product="Cloudflare http proxy" tunnel="ssl" method="probed" conf="10"/>
+    </port>
+</ports>
+</host>
+</nmaprun>
+"#;
+    const NMAP_SSL_CERT_XML: &str = r#"
+<nmaprun scanner="nmap" args="nmap -sTV --script ssl-cert -p 443,8443 104.19.128.108" start="1643060432" startstr="Mon Jan 24 21:40:32 2022" version="7.92" xmloutputversion="1.05">
+<host starttime="1643060432" endtime="1643060451">
+<status state="up" reason="syn-ack" reason_ttl="0"/>
+<address addr="104.19.128.108" addrtype="ipv4"/>
+<ports>
+    <port protocol="tcp" portid="443"><state state="open" reason="syn-ack" reason_ttl="0"/><service name="http" product="Cloudflare http proxy" tunnel="ssl" method="probed" conf="10"/><script id="ssl-cert" output="Subject: commonName=4chan.org&#xa;Subject Alternative Name: DNS:4chan.org, DNS:*.4chan.org, DNS:sys.4chan.org&#xa;Issuer: commonName=Cloudflare Inc ECC CA-3/organizationName=Cloudflare, Inc./countryName=US&#xa;Public Key type: ec&#xa;Public Key bits: 256&#xa;Signature Algorithm: ecdsa-with-SHA256&#xa;Not valid before: 2021-07-08T00:00:00&#xa;Not valid after:  2022-07-07T23:59:59&#xa;MD5:   5d1b 3a32 0e5c 7c3e 3f52 2a0c 4cf5 23ad&#xa;SHA-1: 0a3e 4d1c 7f63 0e3e 2b0c 9e8f 12d4 87a2 5b11 00ce"></script></port>
+    <port protocol="tcp" portid="8443"><state state="open" reason="syn-ack" reason_ttl="0"/><service name="http" product="Cloudflare http proxy" tunnel="ssl" method="probed" conf="10"/><script id="ssl-cert" output="Subject: commonName=4chan.org&#xa;Subject Alternative Name: DNS:4chan.org, DNS:*.4chan.org, DNS:sys.4chan.org&#xa;Issuer: commonName=Cloudflare Inc ECC CA-3/organizationName=Cloudflare, Inc./countryName=US&#xa;Public Key type: ec&#xa;Public Key bits: 256&#xa;Signature Algorithm: ecdsa-with-SHA256&#xa;Not valid before: 2021-07-08T00:00:00&#xa;Not valid after:  2022-07-07T23:59:59&#xa;MD5:   5d1b 3a32 0e5c 7c3e 3f52 2a0c 4cf5 23ad&#xa;SHA-1: 0a3e 4d1c 7f63 0e3e 2b0c 9e8f 12d4 87a2 5b11 00ce"></script></port>
+</ports>
+</host>
+</nmaprun>
+"#;
+    const AMASS_JSON: &str = r#"{"name":"1.thumbs.4chan.org","domain":"4chan.org","addresses":[{"ip":"104.19.129.108","cidr":"104.16.0.0/14","asn":13335,"desc":"CLOUDFLARENET - Cloudflare, Inc."},{"ip":"104.19.128.108","cidr":"104.16.0.0/14","asn":13335,"desc":"CLOUDFLARENET - Cloudflare, Inc."}],"tag":"api","sources":["AlienVault"]}
+{"name":"blog.4chan.org","domain":"4chan.org","addresses":[{"ip":"74.114.154.18","cidr":"74.114.152.0/22","asn":2635,"desc":"AUTOMATTIC - Automattic, Inc"},{"ip":"74.114.154.22","cidr":"74.114.152.0/22","asn":2635,"desc":"AUTOMATTIC - Automattic, Inc"}],"tag":"api","sources":["AlienVault"]}
+{"name":"4chan.org","domain":"4chan.org","addresses":[{"ip":"104.19.128.108","cidr":"104.16.0.0/14","asn":13335,"desc":"CLOUDFLARENET - Cloudflare, Inc."},{"ip":"104.19.129.108","cidr":"104.16.0.0/14","asn":13335,"desc":"CLOUDFLARENET - Cloudflare, Inc."}],"tag":"dns","sources":["DNS","AlienVault","SonarSearch"]}"#;
+
+
+
+    const FFUF_JSON: &str = r#"{"commandline":"ffuf -u http://10.10.10.10:8080/FUZZ -w common.txt -of json -o ffuf.json","time":"2022-01-30T20:06:51+01:00","results":[{"input":{"FUZZ":"admin"},"position":3,"status":301,"length":178,"words":6,"lines":8,"content-type":"text/html","redirectlocation":"http://10.10.10.10:8080/admin/","resultfile":"","url":"http://10.10.10.10:8080/admin","host":"10.10.10.10:8080"},{"input":{"FUZZ":"index.html"},"position":7,"status":200,"length":10918,"words":3499,"lines":376,"content-type":"text/html","redirectlocation":"","resultfile":"","url":"http://10.10.10.10:8080/index.html","host":"10.10.10.10:8080"}],"config":{"url":"http://10.10.10.10:8080/FUZZ","inputproviders":[{"name":"wordlist","keyword":"FUZZ","value":"common.txt"}]}}"#;
+    const GOBUSTER_OUT: &str = r#"[+] Url:                     http://10.10.10.10:8080/
+[+] Wordlist:                common.txt
+/admin                (Status: 301) [Size: 178] [--> http://10.10.10.10:8080/admin/]
+/server-status        (Status: 403) [Size: 277]"#;
+
+    const NESSUS_XML: &str = r#"<?xml version="1.0" ?>
+<NessusClientData_v2>
+<Report name="external" xmlns:cm="http://www.nessus.org/cm">
+<ReportHost name="104.19.128.108"><HostProperties>
+<tag name="operating-system">Linux Kernel 3.10</tag>
+<tag name="host-ip">104.19.128.108</tag>
+<tag name="host-fqdn">4chan.org</tag>
+</HostProperties>
+<ReportItem port="0" svc_name="general" protocol="tcp" severity="0" pluginID="19506" pluginName="Nessus Scan Information" pluginFamily="Settings">
+<plugin_output>Nessus version : 10.1.0</plugin_output>
+</ReportItem>
+<ReportItem port="443" svc_name="www" protocol="tcp" severity="2" pluginID="42873" pluginName="SSL Medium Strength Cipher Suites Supported (SWEET32)" pluginFamily="General">
+<cve>CVE-2016-2183</cve>
+<cvss3_base_score>7.5</cvss3_base_score>
+<synopsis>The remote service supports the use of medium strength SSL ciphers.</synopsis>
+</ReportItem>
+<ReportItem port="8888" svc_name="www" protocol="tcp" severity="0" pluginID="22964" pluginName="Service Detection" pluginFamily="Service detection">
+<plugin_output>A web server is running on this port.</plugin_output>
+</ReportItem>
+</ReportHost>
+</Report>
+</NessusClientData_v2>
+"#;
+
+    const GMP_XML: &str = r#"<report id="7b2a2bd5-9e1c-4c45-8b0e-6a6b5d2b6a11" format_id="a994b278-1f62-11e1-96ac-406186ea4fc5" extension="xml">
+<report id="7b2a2bd5-9e1c-4c45-8b0e-6a6b5d2b6a11">
+<results start="1" max="100">
+<result id="0a6fe6b2-d5e1-4e9a-a1a7-b6a2c1f62e88">
+<name>SSL/TLS: Report Vulnerable Cipher Suites for HTTPS</name>
+<host>104.19.128.108<asset asset_id="5a1d7c55-2f1a-4c1b-a3d5-2d4e1fd0bb0c"/><hostname>www.4chan.org</hostname></host>
+<port>443/tcp</port>
+<nvt oid="1.3.6.1.4.1.25623.1.0.108031">
+<name>SSL/TLS: Report Vulnerable Cipher Suites for HTTPS</name>
+<tags>summary=This routine reports all SSL/TLS cipher suites accepted by a service where attack vectors exists only on HTTPS services.</tags>
+<refs><ref type="cve" id="CVE-2016-2183"/></refs>
+</nvt>
+<threat>High</threat>
+<severity>7.5</severity>
+<qod><value>98</value><type>remote_app</type></qod>
+<description>'Vulnerable' cipher suites accepted by this service via the TLSv1.2 protocol: TLS_RSA_WITH_3DES_EDE_CBC_SHA (SWEET32)</description>
+</result>
+</results>
+</report>
+</report>
+"#;
+
+    const TESTSSL_JSON: &str = r#"[
+  {"id":"service","ip":"example.com/93.184.216.34","port":"443","severity":"INFO","finding":"HTTP"},
+  {"id":"TLS1","ip":"example.com/93.184.216.34","port":"443","severity":"LOW","finding":"offered (deprecated)"},
+  {"id":"TLS1_3","ip":"example.com/93.184.216.34","port":"443","severity":"OK","finding":"offered with final"},
+  {"id":"cipher-tls1_3_x1302","ip":"example.com/93.184.216.34","port":"443","severity":"OK","finding":"TLSv1.3   x1302   TLS_AES_256_GCM_SHA384            ECDH 253   AESGCM      256      TLS_AES_256_GCM_SHA384"},
+  {"id":"cert_commonName","ip":"example.com/93.184.216.34","port":"443","severity":"OK","finding":"www.example.org"},
+  {"id":"cert_serialNumber","ip":"example.com/93.184.216.34","port":"443","severity":"INFO","finding":"0FAA63109307BC3D414892640CCD4D9A"},
+  {"id":"cert_caIssuers","ip":"example.com/93.184.216.34","port":"443","severity":"INFO","finding":"DigiCert TLS RSA SHA256 2020 CA1"},
+  {"id":"cert_notAfter","ip":"example.com/93.184.216.34","port":"443","severity":"OK","finding":"2022-12-09 23:59"},
+  {"id":"heartbleed","ip":"example.com/93.184.216.34","port":"443","severity":"OK","cve":"CVE-2014-0160","cwe":"CWE-119","finding":"not vulnerable, no heartbeat extension"}
+]"#;
+    const SSLSCAN_XML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<document title="SSLScan Results" version="2.0.10-static" web="http://github.com/rbsec/sslscan">
+ <ssltest host="example.com" sniname="example.com" port="443">
+  <protocol type="tls" version="1.0" enabled="0" />
+  <protocol type="tls" version="1.3" enabled="1" />
+  <heartbleed sslversion="TLSv1.3" vulnerable="0" />
+  <cipher status="preferred" sslversion="TLSv1.3" bits="256" cipher="TLS_AES_256_GCM_SHA384" id="0x1302" strength="strong" />
+  <cipher status="accepted" sslversion="TLSv1.3" bits="128" cipher="TLS_AES_128_GCM_SHA256" id="0x1301" strength="strong" />
+  <certificates>
+   <certificate type="short">
+    <subject><![CDATA[www.example.org]]></subject>
+    <issuer><![CDATA[DigiCert TLS RSA SHA256 2020 CA1]]></issuer>
+    <not-valid-after>Dec  9 23:59:00 2022 GMT</not-valid-after>
+    <expired>true</expired>
+   </certificate>
+  </certificates>
+ </ssltest>
+</document>
+"#;
+
+    const SUBFINDER_JSON: &str = r#"{"host":"www.example.com","input":"example.com","source":"crtsh"}
+{"host":"mail.example.com","input":"example.com","sources":["alienvault","dnsdumpster"]}
+{"host":"dev.example.com","ip":"93.184.216.40","input":"example.com","source":"crtsh"}
+"#;
+
+    const DNSX_JSON: &str = r#"{"host":"www.example.com","resolver":["1.1.1.1:53"],"a":["93.184.216.34"],"aaaa":["2606:2800:220:1:248:1893:25c8:1946"],"status_code":"NOERROR"}
+{"host":"mail.example.com","resolver":["1.1.1.1:53"],"status_code":"NXDOMAIN"}
+{"host":"cdn.example.com","resolver":["1.1.1.1:53"],"cname":["example.edgekey.net"],"status_code":"NOERROR"}
+{"host":"old.example.com","resolver":["1.1.1.1:53"],"status_code":"NXDOMAIN"}
+"#;
+
+
+    #[test]
+    fn create_table() {
+	let db = Db::new(":memory:").unwrap();
+	assert!(db.create_table().is_ok());
+    }
+
+    #[test]
+    fn insert_nmap_scan() {
+	let db = Db::new(":memory:").unwrap();
+	db.create_table().unwrap();
+
+	let nmap = Nmap::new(NMAP_XML.as_bytes()).unwrap();
+	let count = db.insert_nmap_scan(nmap);
+	//count.unwrap();
+	assert!(count.is_ok());
+	assert!(count.unwrap() == 4);
+
+	let mut cursor = db.conn.prepare("SELECT ip, port, service_name, state FROM service").unwrap().into_cursor();
+	let mut count = 0;
+	while let Some(row) = cursor.next().unwrap() {
+	    assert!(row[3].as_string() == "open".into());
+	    count += 1;
+	}
+	assert!(count == 4);
+
+	// check if we also have the script tags added to the db
+	let mut cursor = db.conn.prepare(r#"
+SELECT sc.ip, sc.port, sc.script_id, sc.script_output, s.service_name, s.state
+FROM script as sc, service as s 
+WHERE sc.ip = s.ip AND 
+      sc.port = s.port
+"#).unwrap().into_cursor();
+	let script_ids = &["http-methods", "http-server-header", "http-title"];
+	let mut count = 0;
+	while let Some(row) = cursor.next().unwrap() {
+	    assert!(script_ids.contains(&row[2].as_string().unwrap()));
+	    count += 1; 
+	}
+	assert!(count == 3);
+    }
+
+    #[test]
+    fn history() {
+	let db = Db::new(":memory:").unwrap();
+	db.create_table().unwrap();
+	db.insert_nmap_scan(Nmap::new(NMAP_XML.as_bytes()).unwrap()).unwrap();
+	db.insert_amass_scan(Amass::new(AMASS_JSON.as_bytes()).unwrap()).unwrap();
+	let rescan = NMAP_XML.replace("OpenResty web app server", "nginx").replace(r#"output="Not found.""#, r#"output="Welcome""#);
+	db.insert_nmap_scan(Nmap::new(rescan.as_bytes()).unwrap()).unwrap();
+	// testssl does not know the product, it may not blank the one nmap found
+	let testssl = TESTSSL_JSON.replace("93.184.216.34", "104.19.128.108");
+	db.insert_testssl_scan(Testssl::new(testssl.as_bytes()).unwrap()).unwrap();
+	db.add_scope_rule(&Rule::parse(true, "104.19.128.108").unwrap()).unwrap();
+	db.apply_scope("alice").unwrap();
+
+	let entries = db.history("104.19.128.108", Some(80)).unwrap();
+	let changes: Vec<_> = entries.iter()
+	    .map(|entry| (entry.target.as_str(), entry.column.as_str(), entry.new_value.as_deref().unwrap_or(""), entry.cause.as_str()))
+	    .collect();
+	assert!(changes == vec![
+	    ("104.19.128.108:80", "state", "open", "scan 1 (nmap)"),
+	    ("104.19.128.108:80 http-methods", "script_output", "\n  Supported Methods: GET HEAD POST OPTIONS", "scan 1 (nmap)"),
+	    ("104.19.128.108:80 http-server-header", "script_output", "openresty", "scan 1 (nmap)"),
+	    ("104.19.128.108:80 http-title", "script_output", "Not found.", "scan 1 (nmap)"),
+	    ("104.19.128.108:80", "service_product", "nginx", "scan 3 (nmap)"),
+	    ("104.19.128.108:80 http-title", "script_output", "Welcome", "scan 3 (nmap)"),
+	    ("104.19.128.108:80", "scope", "in", "alice"),
+	]);
+	assert!(entries[4].old_value.as_deref() == Some("OpenResty web app server"));
+	assert!(entries[0].old_value.is_none());
+
+	// the whole address has the other ports and the names resolving to it
+	let entries = db.history("104.19.128.108", None).unwrap();
+	assert!(entries.iter().filter(|entry| entry.column == "state").count() == 4);
+	assert!(entries.iter().any(|entry| entry.entity == "domain" && entry.target == "4chan.org" && entry.column == "ip"));
+	let service = db.services().unwrap().into_iter().find(|service| service.port == 443).unwrap();
+	assert!(service.ip == "104.19.128.108" && service.product.as_deref() == Some("Cloudflare http proxy"));
+	assert!(db.history("10.0.0.1", None).unwrap().is_empty());
+    }
+
+    #[test]
+    fn merge() {
+	let service = |product: &str, conf: Value, state: &str, version: Value| -> Vec<(&'static str, Value)> {
+	    vec![
+		(":ip", Value::String("10.0.0.1".into())),
+		(":port", Value::Integer(443)),
+		(":protocol", Value::String("tcp".into())),
+		(":name", Value::String("http".into())),
+		(":product", Value::String(product.into())),
+		(":method", Value::String("probed".into())),
+		(":conf", conf),
+		(":state", Value::String(state.into())),
+		(":reason", Value::Null),
+		(":tunnel", Value::Null),
+		(":version", version),
+	    ]
+	};
+	let old: Vec<Value> = service("nginx", Value::Integer(10), "open", Value::Null).into_iter().map(|(_, value)| value).collect();
+
+	// a tool without a confidence only fills gaps and changes the state
+	let changes = Db::merge(&old, &service("Apache httpd", Value::Null, "filtered", Value::String("1.25.3".into())));
+	assert!(changes == vec![(":state", Value::String("filtered".into())), (":version", Value::String("1.25.3".into()))]);
+	// nmap overrules, but an empty value never blanks anything
+	let changes = Db::merge(&old, &service("", Value::Integer(8), "open", Value::Null));
+	assert!(changes == vec![(":conf", Value::Integer(8))]);
+	let changes = Db::merge(&old, &service("Apache httpd", Value::Integer(10), "open", Value::Null));
+	assert!(changes == vec![(":product", Value::String("Apache httpd".into()))]);
+	assert!(Db::merge(&old, &service("nginx", Value::Integer(10), "open", Value::Null)).is_empty());
+    }
+
+    #[test]
+    fn scan_rows() {
+	let db = Db::new(":memory:").unwrap();
+	db.create_table().unwrap();
+	db.insert_nmap_scan(Nmap::new(NMAP_XML.as_bytes()).unwrap()).unwrap();
+	db.insert_subfinder_scan(Subfinder::new(SUBFINDER_JSON.as_bytes()).unwrap()).unwrap();
+	db.insert_dnsx_scan(Dnsx::new(DNSX_JSON.as_bytes()).unwrap()).unwrap();
+	db.insert_testssl_scan(Testssl::new(TESTSSL_JSON.as_bytes()).unwrap()).unwrap();
+	db.insert_nessus_scan(Nessus::new(NESSUS_XML.as_bytes()).unwrap()).unwrap();
+
+	// every import is a cause history can name, only nmap and amass feed diffs
+	let scans = db.scans().unwrap();
+	let tools: Vec<&str> = scans.iter().map(|scan| scan.tool.as_str()).collect();
+	assert!(tools == vec!["nmap", "subfinder", "dnsx", "testssl", "nessus"]);
+	assert!(scans[0].hosts == 1 && scans[0].services == 4);
+	assert!(scans[1..].iter().all(|scan| scan.hosts == 0 && scan.services == 0 && scan.domains == 0));
+	let entries = db.history("93.184.216.34", None).unwrap();
+	assert!(entries.iter().any(|entry| entry.entity == "domain" && entry.cause == "scan 3 (dnsx)"));
+    }
+
+    #[test]
+    fn history_follows_its_rows() {
+	let db = Db::new(":memory:").unwrap();
+	db.create_table().unwrap();
+	db.insert_nmap_scan(Nmap::new(NMAP_XML.as_bytes()).unwrap()).unwrap();
+	db.insert_assetfinder_scan(Assetfinder::new("ftp.example.com\nmail.example.com\n".as_bytes()).unwrap()).unwrap();
+	db.insert_dnsx_scan(Dnsx::new(r#"{"host":"mail.example.com","a":["93.184.216.36"]}"#.as_bytes()).unwrap()).unwrap();
+	db.conn.execute("DELETE FROM domain WHERE domain = 'ftp.example.com'; DELETE FROM service WHERE port = 80; VACUUM;").unwrap();
+
+	let entries = db.history("104.19.128.108", Some(8080)).unwrap();
+	assert!(entries.len() == 1 && entries[0].target == "104.19.128.108:8080" && entries[0].column == "state");
+	// the name resolved in place, its row and history stay the same
+	let entries = db.history("93.184.216.36", None).unwrap();
+	let changes: Vec<_> = entries.iter()
+	    .map(|entry| (entry.target.as_str(), entry.old_value.as_deref(), entry.new_value.as_deref(), entry.cause.as_str()))
+	    .collect();
+	assert!(changes == vec![
+	    ("mail.example.com", None, Some("mail.example.com"), "scan 2 (assetfinder)"),
+	    ("mail.example.com", None, Some("93.184.216.36"), "scan 3 (dnsx)"),
+	]);
+    }
+
+    #[test]
+    fn insert_amass_scan() {
+	let db = Db::new(":memory:").unwrap();
+	db.create_table().unwrap();
+
+	let amass = Amass::new(AMASS_JSON.as_bytes()).unwrap();
+	let count = db.insert_amass_scan(amass);
+	assert!(&count.is_ok());
+	assert!(count.unwrap() == 6);
+
+	let mut cursor = db.conn.prepare("SELECT ip, domain, cidr, asn, description, amass_tag FROM domain WHERE domain = '4chan.org'")
+	    .unwrap()
+	    .into_cursor();
+	let mut count = 0;
+	while let Some(row) = cursor.next().unwrap() {
+	    assert!(row[1].as_string().unwrap() == "4chan.org");
+	    assert!(row[3].as_integer().unwrap() == 13335);
+	    count += 1;
+	}
+	assert!(count == 2);
+    }
+
+    #[test]
+    fn insert_subdomain_scans() {
+	let db = Db::new(":memory:").unwrap();
+	db.create_table().unwrap();
+
+	let subfinder = Subfinder::new(SUBFINDER_JSON.as_bytes()).unwrap();
+	assert!(db.insert_subfinder_scan(subfinder).unwrap() == 3);
+	// names already known from subfinder are not new
+	let assetfinder = Assetfinder::new("WWW.example.com.\nftp.example.com\n".as_bytes()).unwrap();
+	assert!(db.insert_assetfinder_scan(assetfinder).unwrap() == 1);
+	// both www addresses replace the unresolved www entry
+	let dnsx = Dnsx::new(DNSX_JSON.as_bytes()).unwrap();
+	assert!(db.insert_dnsx_scan(dnsx).unwrap() == 4);
+
+	let mut cursor = db.conn.prepare("SELECT domain, ip, source, tool FROM domain ORDER BY domain, ip")
+	    .unwrap()
+	    .into_cursor();
+	// no address, but the name exists
+	let row = cursor.next().unwrap().unwrap();
+	assert!(row[0].as_string().unwrap() == "cdn.example.com");
+	assert!(row[1] == Value::Null);
+	assert!(row[3].as_string().unwrap() == "dnsx");
+	let row = cursor.next().unwrap().unwrap();
+	assert!(row[0].as_string().unwrap() == "dev.example.com");
+	assert!(row[1].as_string().unwrap() == "93.184.216.40");
+	let row = cursor.next().unwrap().unwrap();
+	assert!(row[0].as_string().unwrap() == "ftp.example.com");
+	assert!(row[1] == Value::Null);
+	assert!(row[3].as_string().unwrap() == "assetfinder");
+	let row = cursor.next().unwrap().unwrap();
+	assert!(row[0].as_string().unwrap() == "mail.example.com");
+	assert!(row[2].as_string().unwrap() == "alienvault, dnsdumpster");
+	assert!(row[3].as_string().unwrap() == "subfinder");
+	let row = cursor.next().unwrap().unwrap();
+	assert!(row[0].as_string().unwrap() == "old.example.com");
+	assert!(row[1] == Value::Null);
+	let row = cursor.next().unwrap().unwrap();
+	assert!(row[1].as_string().unwrap() == "2606:2800:220:1:248:1893:25c8:1946");
+	assert!(row[3].as_string().unwrap() == "dnsx");
+	let row = cursor.next().unwrap().unwrap();
+	assert!(row[0].as_string().unwrap() == "www.example.com");
+	assert!(row[1].as_string().unwrap() == "93.184.216.34");
+	assert!(cursor.next().unwrap().is_none());
+    }
+
+    #[test]
+    fn resolve_cname_chain() {
+	let db = Db::new(":memory:").unwrap();
+	db.create_table().unwrap();
+
+	let json = r#"{"host":"shop.example.com","cname":["shops.myshopify.com."],"ttl":300,"status_code":"NOERROR"}
+{"host":"shops.myshopify.com","cname":["shops.cdn.example.net"],"status_code":"NOERROR"}
+{"host":"shops.cdn.example.net","a":["23.227.38.65"],"aaaa":["2620:127:f00f:5::"],"status_code":"NOERROR"}
+{"host":"a.example.com","cname":["b.example.com"],"status_code":"NOERROR"}
+{"host":"b.example.com","cname":["a.example.com"],"mx":["mail.example.com"],"txt":["v=spf1 -all"],"status_code":"NOERROR"}
+"#;
+	let dnsx = Dnsx::new(json.as_bytes()).unwrap();
+	// shop, shops.myshopify.com, both cdn addresses, a and b
+	assert!(db.insert_dnsx_scan(dnsx).unwrap() == 6);
+	let mut cursor = db.conn.prepare("SELECT count(*) FROM dns_record").unwrap().into_cursor();
+	assert!(cursor.next().unwrap().unwrap()[0].as_integer().unwrap() == 8);
+	// addresses from amass end up in dns_record as well
+	db.insert_amass_scan(Amass::new(AMASS_JSON.as_bytes()).unwrap()).unwrap();
+
+	let mut cursor = db.conn.prepare("SELECT value, ttl FROM dns_record WHERE name = 'shop.example.com' AND type = 'CNAME'")
+	    .unwrap()
+	    .into_cursor();
+	let row = cursor.next().unwrap().unwrap();
+	assert!(row[0].as_string().unwrap() == "shops.myshopify.com");
+	assert!(row[1].as_integer().unwrap() == 300);
+
+	assert!(db.resolve("Shop.Example.com.").unwrap() == vec!["23.227.38.65", "2620:127:f00f:5::"]);
+	assert!(db.resolve("1.thumbs.4chan.org").unwrap() == vec!["104.19.128.108", "104.19.129.108"]);
+	// a loop without addresses on the way resolves to nothing
+	assert!(db.resolve("a.example.com").unwrap().is_empty());
+	assert!(db.resolve("unknown.example.com").unwrap().is_empty());
+    }
+
+    #[test]
+    fn insert_zone_scan() {
+	let db = Db::new(":memory:").unwrap();
+	db.create_table().unwrap();
+
+	let zone = r#"$ORIGIN example.com.
+$TTL 3600
+@	IN	SOA	ns1 hostmaster 2022013101 7200 3600 1209600 3600
+	IN	NS	ns1
+	IN	A	192.0.2.80
+ns1	IN	A	192.0.2.1
+www	IN	CNAME	@
+_sip._tcp	IN	SRV	0 5 5060 ns1
+"#;
+	let zone = Zone::new(zone.as_bytes()).unwrap();
+	assert!(db.insert_zone_scan(zone).unwrap() == 6);
+
+	let mut cursor = db.conn.prepare("SELECT domain, ip, source, tool FROM domain ORDER BY domain")
+	    .unwrap()
+	    .into_cursor();
+	let row = cursor.next().unwrap().unwrap();
+	assert!(row[0].as_string().unwrap() == "example.com");
+	assert!(row[1].as_string().unwrap() == "192.0.2.80");
+	assert!(row[2].as_string().unwrap() == "zone-transfer");
+	let row = cursor.next().unwrap().unwrap();
+	assert!(row[0].as_string().unwrap() == "ns1.example.com");
+	let row = cursor.next().unwrap().unwrap();
+	assert!(row[0].as_string().unwrap() == "www.example.com");
+	assert!(row[1] == Value::Null);
+	assert!(cursor.next().unwrap().is_none());
+
+	assert!(db.resolve("www.example.com").unwrap() == vec!["192.0.2.80"]);
+    }
+
+    #[test]
+    fn insert_crtsh_scan() {
+	let db = Db::new(":memory:").unwrap();
+	db.create_table().unwrap();
+	db.insert_amass_scan(Amass::new(AMASS_JSON.as_bytes()).unwrap()).unwrap();
+
+	let json = r#"[{"issuer_ca_id":183267,"issuer_name":"C=US, O=Let's Encrypt, CN=R3","common_name":"4chan.org","name_value":"4chan.org\n*.4chan.org","id":1,"not_before":"2021-12-10T00:00:00","not_after":"2022-03-10T23:59:59","serial_number":"03A1"},
+{"issuer_ca_id":183267,"issuer_name":"C=US, O=Let's Encrypt, CN=R3","common_name":"4chan.org","name_value":"4chan.org\n*.4chan.org","id":2,"not_before":"2021-12-10T00:00:00","not_after":"2022-03-10T23:59:59","serial_number":"03a1"},
+{"issuer_ca_id":183267,"issuer_name":"C=US, O=Let's Encrypt, CN=R3","common_name":"status.4chan.org","name_value":"status.4chan.org","id":3,"not_before":"2022-03-01T00:00:00","not_after":"2022-05-30T23:59:59","serial_number":"04b2"}]"#;
+	// two certificates, the wildcard and status.4chan.org, 4chan.org is known from amass
+	assert!(db.insert_crtsh_scan(Crtsh::new(json.as_bytes()).unwrap()).unwrap() == 4);
+
+	let mut cursor = db.conn.prepare("SELECT domain, ip, wildcard FROM domain WHERE tool = 'crtsh' ORDER BY domain")
+	    .unwrap()
+	    .into_cursor();
+	let row = cursor.next().unwrap().unwrap();
+	assert!(row[0].as_string().unwrap() == "*.4chan.org");
+	assert!(row[2].as_integer().unwrap() == 1);
+	let row = cursor.next().unwrap().unwrap();
+	assert!(row[0].as_string().unwrap() == "status.4chan.org");
+	assert!(row[1] == Value::Null);
+	assert!(row[2].as_integer().unwrap() == 0);
+	assert!(cursor.next().unwrap().is_none());
+
+	// certificate history of a name
+	let mut cursor = db.conn.prepare("SELECT issuer, not_after FROM certificate WHERE san LIKE '%4chan.org%' ORDER BY not_after")
+	    .unwrap()
+	    .into_cursor();
+	let row = cursor.next().unwrap().unwrap();
+	assert!(row[0].as_string().unwrap() == "R3");
+	assert!(row[1].as_string().unwrap() == "2022-03-10 23:59:59");
+	assert!(cursor.next().unwrap().unwrap()[1].as_string().unwrap() == "2022-05-30 23:59:59");
+	assert!(cursor.next().unwrap().is_none());
+    }
+
+    #[test]
+    fn mark_wildcards() {
+	let db = Db::new(":memory:").unwrap();
+	db.create_table().unwrap();
+
+	// 4chan.org and 1.thumbs.4chan.org share their addresses but are too few
+	db.insert_amass_scan(Amass::new(AMASS_JSON.as_bytes()).unwrap()).unwrap();
+	let mut json = String::new();
+	for i in 0..3 {
+	    json += &format!("{{\"host\":\"random{}.thumbs.4chan.org\",\"a\":[\"104.19.129.108\",\"104.19.128.108\"]}}\n", i);
+	}
+	json += "{\"host\":\"*.dev.example.com\",\"a\":[\"192.0.2.80\"]}\n";
+	json += "{\"host\":\"foo.dev.example.com\",\"a\":[\"192.0.2.80\"]}\n";
+	json += "{\"host\":\"api.dev.example.com\",\"a\":[\"192.0.2.81\"]}\n";
+	// unrelated registrations at one hoster, co.uk is not a zone of its own
+	for name in ["example", "other", "third", "fourth"] {
+	    json += &format!("{{\"host\":\"{}.co.uk\",\"a\":[\"198.51.100.7\"]}}\n", name);
+	}
+	db.insert_dnsx_scan(Dnsx::new(json.as_bytes()).unwrap()).unwrap();
+
+	// two addresses for each of the three random names and 1.thumbs from
+	// amass, the *.dev.example.com record gives foo away
+	assert!(db.mark_wildcards(4, "test").unwrap() == 9);
+	assert!(db.mark_wildcards(WILDCARD_MIN_NAMES, "test").unwrap() == 0);
+
+	let domains = db.domains(false, ScopeFilter::Default, &[]).unwrap();
+	let names: Vec<&str> = domains.iter().map(|domain| domain.domain.as_str()).collect();
+	assert!(names == vec!["4chan.org", "4chan.org", "api.dev.example.com", "blog.4chan.org", "blog.4chan.org",
+			      "example.co.uk", "fourth.co.uk", "other.co.uk", "third.co.uk"]);
+	assert!(db.domains(true, ScopeFilter::Default, &[]).unwrap().iter().filter(|domain| domain.wildcard).count() == 10);
+    }
+
+    #[test]
+    fn public_suffixes() {
+	assert!(is_public_suffix("com"));
+	assert!(is_public_suffix("co.uk"));
+	assert!(is_public_suffix("com.au"));
+	assert!(!is_public_suffix("example.de"));
+	assert!(!is_public_suffix("example.co.uk"));
+	assert!(!is_public_suffix("co.example"));
+    }
+
+    #[test]
+    fn canonical_ips() {
+	assert!(canonical_ip("0:0::1").unwrap().0 == "::1");
+	assert!(canonical_ip("2001:DB8:0:0:0:0:0:1").unwrap().0 == "2001:db8::1");
+	assert!(canonical_ip("[fe80::1%eth0]").unwrap().0 == "fe80::1");
+	let (ip, ip_bin) = canonical_ip("::ffff:192.168.56.10").unwrap();
+	assert!(ip == "192.168.56.10");
+	assert!(ip_bin == vec![192, 168, 56, 10]);
+	assert!(canonical_ip("2001:db8::1").unwrap().1.len() == 16);
+	assert!(canonical_ip("www.example.com").is_none());
+    }
+
+    #[test]
+    fn host_ids() {
+	let db = Db::new(":memory:").unwrap();
+	db.create_table().unwrap();
+
+	db.insert_nessus_scan(Nessus::new(NESSUS_XML.as_bytes()).unwrap()).unwrap();
+	let json = r#"{"host":"www.4chan.org","a":["::ffff:104.19.128.108"],"aaaa":["2001:db8:0:0::1"]}
+{"host":"mail.4chan.org","aaaa":["2001:0DB8::0001"]}
+"#;
+	db.insert_dnsx_scan(Dnsx::new(json.as_bytes()).unwrap()).unwrap();
+
+	let mut cursor = db.conn.prepare("SELECT id, ip, hex(ip_bin), fqdn FROM host ORDER BY length(ip_bin), ip_bin")
+	    .unwrap()
+	    .into_cursor();
+	let row = cursor.next().unwrap().unwrap();
+	let id = row[0].as_integer().unwrap();
+	assert!(row[1].as_string().unwrap() == "104.19.128.108");
+	assert!(row[2].as_string().unwrap() == "6813806C");
+	assert!(row[3].as_string().unwrap() == "4chan.org");
+	let row = cursor.next().unwrap().unwrap();
+	assert!(row[1].as_string().unwrap() == "2001:db8::1");
+	assert!(cursor.next().unwrap().is_none());
+
+	// services, findings and names of the nessus host all point at the same row
+	let mut cursor = db.conn.prepare(r#"
+SELECT count(DISTINCT s.port), count(DISTINCT d.domain)
+FROM service as s, domain as d
+WHERE s.host_id = :id AND d.host_id = :id"#)
+	    .unwrap()
+	    .into_cursor();
+	cursor.bind_by_name(vec![(":id", Value::Integer(id))]).unwrap();
+	let row = cursor.next().unwrap().unwrap();
+	assert!(row[0].as_integer().unwrap() == 2);
+	assert!(row[1].as_integer().unwrap() == 2);
+
+	let mut cursor = db.conn.prepare("SELECT domain, ip FROM domain WHERE ip LIKE '2001:%' ORDER BY domain")
+	    .unwrap()
+	    .into_cursor();
+	assert!(cursor.next().unwrap().unwrap()[1].as_string().unwrap() == "2001:db8::1");
+	assert!(cursor.next().unwrap().unwrap()[1].as_string().unwrap() == "2001:db8::1");
+    }
+
+    #[test]
+    fn apply_scope() {
+	let db = Db::new(":memory:").unwrap();
+	db.create_table().unwrap();
+	db.insert_nmap_scan(Nmap::new(NMAP_XML.as_bytes()).unwrap()).unwrap();
+	db.insert_amass_scan(Amass::new(AMASS_JSON.as_bytes()).unwrap()).unwrap();
+
+	// nothing included yet, everything but the exclusions is listed
+	assert!(db.add_scope_rule(&Rule::parse(false, "blog.4chan.org").unwrap()).unwrap());
+	assert!(!db.add_scope_rule(&Rule::parse(false, "blog.4chan.org").unwrap()).unwrap());
+	db.apply_scope("test").unwrap();
+	let domains = db.domains(true, ScopeFilter::Default, &[]).unwrap();
+	assert!(domains.iter().all(|domain| domain.domain != "blog.4chan.org"));
+	assert!(domains.iter().all(|domain| domain.scope == Scope::Unknown));
+
+	db.add_scope_rule(&Rule::parse(true, "*.4chan.org").unwrap()).unwrap();
+	db.add_scope_rule(&Rule::parse(true, "4chan.org").unwrap()).unwrap();
+	db.add_scope_rule(&Rule::parse(false, "104.19.129.108").unwrap()).unwrap();
+	db.apply_scope("test").unwrap();
+	assert!(db.scope_rules().unwrap().rules.len() == 4);
+
+	// the excluded address takes the names resolving to it out as well
+	let domains = db.domains(true, ScopeFilter::Any, &[]).unwrap();
+	let scopes: Vec<(&str, Option<&str>, Scope)> = domains.iter()
+	    .map(|domain| (domain.domain.as_str(), domain.ip.as_deref(), domain.scope))
+	    .collect();
+	assert!(scopes.contains(&("4chan.org", Some("104.19.128.108"), Scope::In)));
+	assert!(scopes.contains(&("4chan.org", Some("104.19.129.108"), Scope::Out)));
+	assert!(scopes.contains(&("1.thumbs.4chan.org", Some("104.19.129.108"), Scope::Out)));
+	let domains = db.domains(true, ScopeFilter::Default, &[]).unwrap();
+	assert!(domains.len() == 2);
+	assert!(domains.iter().all(|domain| domain.ip.as_deref() == Some("104.19.128.108")));
+
+	// 104.19.128.108 is in scope through the names resolving to it
+	let mut cursor = db.conn.prepare("SELECT DISTINCT h.ip, h.scope, s.scope FROM host AS h, service AS s WHERE s.host_id = h.id ORDER BY h.ip")
+	    .unwrap()
+	    .into_cursor();
+	let row = cursor.next().unwrap().unwrap();
+	assert!(row[0].as_string().unwrap() == "104.19.128.108");
+	assert!(row[1].as_string().unwrap() == "in");
+	assert!(row[2].as_string().unwrap() == "in");
+	assert!(cursor.next().unwrap().is_none());
+
+	assert!(db.remove_scope_rule(&Rule::parse(true, "4chan.org").unwrap()).unwrap());
+	assert!(!db.remove_scope_rule(&Rule::parse(true, "4chan.org").unwrap()).unwrap());
+    }
+
+    #[test]
+    fn apply_scope_rollback() {
+	let db = Db::new(":memory:").unwrap();
+	db.create_table().unwrap();
+	db.insert_amass_scan(Amass::new(AMASS_JSON.as_bytes()).unwrap()).unwrap();
+	db.add_scope_rule(&Rule::parse(true, "*.4chan.org").unwrap()).unwrap();
+	db.add_scope_rule(&Rule::parse(true, "104.19.128.108").unwrap()).unwrap();
+
+	// the host updates succeed, recording the domain changes does not
+	db.conn.execute("DROP TABLE history").unwrap();
+	assert!(db.apply_scope("test").is_err());
+	let mut cursor = db.conn.prepare("SELECT count(*) FROM host WHERE scope != 'unknown'").unwrap().into_cursor();
+	assert!(cursor.next().unwrap().unwrap()[0].as_integer().unwrap() == 0);
+	// and no transaction is left open
+	db.conn.execute("BEGIN; COMMIT;").unwrap();
+    }
+
+    #[test]
+    fn tags() {
+	let db = Db::new(":memory:").unwrap();
+	db.create_table().unwrap();
+	db.insert_nmap_scan(Nmap::new(NMAP_XML.as_bytes()).unwrap()).unwrap();
+	db.insert_amass_scan(Amass::new(AMASS_JSON.as_bytes()).unwrap()).unwrap();
+
+	let http = [Condition::parse("service_name=http").unwrap()];
+	assert!(db.tag("dmz", Entity::Service, &http, true).unwrap() == 4);
+	assert!(db.tag("dmz", Entity::Service, &http, true).unwrap() == 0);
+	// port is NUMERIC, the text value is compared as a number
+	assert!(db.tag("needs-retest", Entity::Service, &[Condition::parse("port=443").unwrap()], true).unwrap() == 1);
+	assert!(db.tag("prod", Entity::Domain, &[Condition::parse("domain=4chan.org").unwrap()], true).unwrap() == 2);
+	assert!(db.tag("prod", Entity::Domain, &[Condition::parse("ip=104.19.128.108").unwrap()], true).unwrap() == 1);
+	assert!(db.tag("prod", Entity::Service, &[Condition::parse("nope=1").unwrap()], true).is_err());
+	assert!(db.tag("two words", Entity::Host, &[], true).is_err());
+
+	let tags: Vec<(String, String, i64)> = db.tags().unwrap()
+	    .into_iter()
+	    .map(|entry| (entry.tag, entry.entity, entry.count))
+	    .collect();
+	assert!(tags == vec![
+	    ("dmz".to_string(), "service".to_string(), 4),
+	    ("needs-retest".to_string(), "service".to_string(), 1),
+	    ("prod".to_string(), "domain".to_string(), 3),
+	]);
+
+	let prod = ["prod".to_string()];
+	assert!(db.domains(true, ScopeFilter::Any, &prod).unwrap().len() == 3);
+	assert!(db.tagged(Entity::Domain, &[]).unwrap().is_none());
+	assert!(db.tagged(Entity::Service, &["dmz".to_string(), "needs-retest".to_string()]).unwrap().unwrap().len() == 1);
+	assert!(db.tagged(Entity::Service, &["dmz".to_string(), "prod".to_string()]).unwrap().unwrap().is_empty());
+
+	// removing the last use of a tag drops it
+	assert!(db.tag("dmz", Entity::Service, &[], false).unwrap() == 4);
+	assert!(db.tags().unwrap().len() == 2);
+    }
+
+    #[test]
+    fn tags_follow_their_rows() {
+	let db = Db::new(":memory:").unwrap();
+	db.create_table().unwrap();
+	db.insert_nmap_scan(Nmap::new(NMAP_XML.as_bytes()).unwrap()).unwrap();
+	db.insert_assetfinder_scan(Assetfinder::new("ftp.example.com\nmail.example.com\n".as_bytes()).unwrap()).unwrap();
+	db.tag("legacy", Entity::Domain, &[Condition::parse("domain=mail.example.com").unwrap()], true).unwrap();
+	db.tag("legacy", Entity::Service, &[Condition::parse("port=8080").unwrap()], true).unwrap();
+
+	// resolving the name keeps its row, VACUUM after a delete keeps ids
+	db.insert_dnsx_scan(Dnsx::new(r#"{"host":"mail.example.com","a":["93.184.216.36"]}"#.as_bytes()).unwrap()).unwrap();
+	db.conn.execute("DELETE FROM domain WHERE domain = 'ftp.example.com'; DELETE FROM service WHERE port = 80; VACUUM;").unwrap();
+
+	let legacy = ["legacy".to_string()];
+	let domains = db.domains(true, ScopeFilter::Any, &legacy).unwrap();
+	assert!(domains.len() == 1);
+	assert!(domains[0].domain == "mail.example.com" && domains[0].ip.as_deref() == Some("93.184.216.36"));
+	let ids = db.tagged(Entity::Service, &legacy).unwrap().unwrap();
+	let services: Vec<u16> = db.services().unwrap().into_iter().filter(|s| ids.contains(&s.id)).map(|s| s.port).collect();
+	assert!(services == vec![8080]);
+    }
+
+    #[test]
+    fn filtered() {
+	let db = Db::new(":memory:").unwrap();
+	db.create_table().unwrap();
+	db.insert_nmap_scan(Nmap::new(NMAP_XML.as_bytes()).unwrap()).unwrap();
+	db.insert_amass_scan(Amass::new(AMASS_JSON.as_bytes()).unwrap()).unwrap();
+	db.tag("prod", Entity::Service, &[Condition::parse("port=8080").unwrap()], true).unwrap();
+
+	let ports = |text: &str| -> Vec<u16> {
+	    let filter = Filter::parse(text).unwrap();
+	    let ids = db.filtered(Table::Service, Some(&filter)).unwrap().unwrap();
+	    db.services().unwrap().into_iter().filter(|s| ids.contains(&s.id)).map(|s| s.port).collect()
+	};
+	assert!(ports("port in (80, 443) and service ~ HTTP") == vec![80, 443]);
+	assert!(ports(r#"product contains "OpenResty""#) == vec![80]);
+	assert!(ports("product contains openresty") == Vec::<u16>::new());
+	assert!(ports("tag:prod or port >= 8443") == vec![8080, 8443]);
+	assert!(ports("not (port < 8000) and seen > 2000-01-01") == vec![8080, 8443]);
+	assert!(db.filtered(Table::Service, None).unwrap().is_none());
+	// services without a product are not openresty either way
+	db.conn.execute("UPDATE service SET service_product = NULL WHERE port = 8080").unwrap();
+	assert!(ports(r#"not product="OpenResty web app server""#) == ports(r#"product!="OpenResty web app server""#));
+	assert!(ports(r#"not product="OpenResty web app server""#).len() == db.services().unwrap().len() - 1);
+	assert!(ports(r#"not product in ("OpenResty web app server")"#) == ports(r#"product!="OpenResty web app server""#));
+
+	// the scripts of the port 80 service, filtered by the service
+	let filter = Filter::parse(r#"product ~ openresty and output ~ "not found""#).unwrap();
+	let ids = db.filtered(Table::Script, Some(&filter)).unwrap().unwrap();
+	let scripts: Vec<String> = db.scripts().unwrap().into_iter().filter(|s| ids.contains(&s.id)).map(|s| s.script_id).collect();
+	assert!(scripts == vec!["http-title"]);
+
+	let filter = Filter::parse("domain ~ thumbs and ip = 104.19.128.108").unwrap();
+	assert!(db.filtered(Table::Domain, Some(&filter)).unwrap().unwrap().len() == 1);
+	let filter = Filter::parse("port = 80").unwrap();
+	assert!(db.filtered(Table::Domain, Some(&filter)).is_err());
+    }
+
+    #[test]
+    fn notes() {
+	let db = Db::new(":memory:").unwrap();
+	db.create_table().unwrap();
+	db.insert_nmap_scan(Nmap::new(NMAP_XML.as_bytes()).unwrap()).unwrap();
+	db.insert_amass_scan(Amass::new(AMASS_JSON.as_bytes()).unwrap()).unwrap();
+
+	let port = [Condition::parse("port=8443").unwrap()];
+	let id = db.add_note("alice", "default creds worked here", Entity::Service, &port).unwrap();
+	let apex = [Condition::parse("domain=4chan.org").unwrap()];
+	let waf = db.add_note("bob", "WAF blocks sqlmap\n\n* try `--tamper`", Entity::Domain, &apex).unwrap();
+	assert!(db.add_note("bob", "nothing", Entity::Domain, &[Condition::parse("domain=example.com").unwrap()]).is_err());
+
+	db.edit_note(id, "bob", "default creds `admin:admin` worked here").unwrap();
+	let notes = db.notes().unwrap();
+	assert!(notes.len() == 2);
+	assert!(notes[0].author == "bob");
+	assert!(notes[0].body.contains("admin:admin"));
+	let history = db.note_history(id).unwrap();
+	assert!(history.len() == 1);
+	assert!(history[0].author == "alice");
+	assert!(history[0].body == "default creds worked here");
+
+	// one note about both addresses of the name
+	let domain_notes = db.entity_notes(Entity::Domain).unwrap();
+	assert!(domain_notes.len() == 2);
+	assert!(domain_notes.values().all(|notes| notes.len() == 1 && notes[0].id == waf));
+	assert!(db.entity_notes(Entity::Service).unwrap().len() == 1);
+
+	db.delete_note(waf, "alice").unwrap();
+	assert!(db.notes().unwrap().len() == 1);
+	assert!(db.entity_notes(Entity::Domain).unwrap().is_empty());
+	assert!(db.note_history(waf).unwrap()[0].body.starts_with("WAF"));
+	assert!(db.edit_note(waf, "alice", "again").is_err());
+	assert!(db.delete_note(42, "alice").is_err());
+    }
+
+    #[test]
+    fn notes_follow_their_rows() {
+	let db = Db::new(":memory:").unwrap();
+	db.create_table().unwrap();
+	db.insert_nmap_scan(Nmap::new(NMAP_XML.as_bytes()).unwrap()).unwrap();
+	db.insert_assetfinder_scan(Assetfinder::new("ftp.example.com\nmail.example.com\n".as_bytes()).unwrap()).unwrap();
+	let mail = db.add_note("alice", "open relay", Entity::Domain, &[Condition::parse("domain=mail.example.com").unwrap()]).unwrap();
+	let proxy = db.add_note("alice", "proxy", Entity::Service, &[Condition::parse("port=8080").unwrap()]).unwrap();
+
+	db.insert_dnsx_scan(Dnsx::new(r#"{"host":"mail.example.com","a":["93.184.216.36"]}"#.as_bytes()).unwrap()).unwrap();
+	db.conn.execute("DELETE FROM domain WHERE domain = 'ftp.example.com'; DELETE FROM service WHERE port = 80; VACUUM;").unwrap();
+
+	let domain_notes = db.entity_notes(Entity::Domain).unwrap();
+	let domains = db.domains(true, ScopeFilter::Any, &[]).unwrap();
+	let noted: Vec<Option<&str>> = domains.iter().filter(|domain| domain_notes.contains_key(&domain.id)).map(|domain| domain.ip.as_deref()).collect();
+	assert!(noted == vec![Some("93.184.216.36")]);
+	assert!(domain_notes.values().all(|notes| notes[0].id == mail));
+	let service_notes = db.entity_notes(Entity::Service).unwrap();
+	let noted: Vec<u16> = db.services().unwrap().iter().filter(|service| service_notes.contains_key(&service.id)).map(|service| service.port).collect();
+	assert!(noted == vec![8080]);
+	assert!(service_notes.values().all(|notes| notes[0].id == proxy));
+    }
+
+    #[test]
+    fn credentials() {
+	let db = Db::new(":memory:").unwrap();
+	db.create_table().unwrap();
+	db.insert_nmap_scan(Nmap::new(NMAP_XML.as_bytes()).unwrap()).unwrap();
+
+	let mut admin = Credential::new("admin", "", CredentialKind::Password, "admin");
+	let (id, new) = db.insert_credential(&admin).unwrap();
+	assert!(new);
+	admin.source = Some("default creds".into());
+	assert!(db.insert_credential(&admin).unwrap() == (id, false));
+
+	db.verify_credential(id, "104.19.128.108", 8443, CredentialStatus::Invalid).unwrap();
+	assert!(db.credentials().unwrap()[0].status == CredentialStatus::Invalid);
+	db.verify_credential(id, "::ffff:104.19.128.108", 80, CredentialStatus::Valid).unwrap();
+	db.verify_credential(id, "104.19.128.108", 8443, CredentialStatus::Valid).unwrap();
+	assert!(db.verify_credential(id + 1, "104.19.128.108", 80, CredentialStatus::Valid).is_err());
+
+	let credentials = db.credentials().unwrap();
+	assert!(credentials.len() == 1);
+	assert!(credentials[0].source.as_deref() == Some("default creds"));
+	assert!(credentials[0].status == CredentialStatus::Valid);
+	let services = &credentials[0].services;
+	assert!(services.len() == 2);
+	assert!(services[0].ip == "104.19.128.108");
+	assert!(services[0].port == 80);
+	assert!(services[0].service_name.as_deref() == Some("http"));
+	assert!(services.iter().all(|service| service.status == CredentialStatus::Valid));
+
+	// a typo creates no host and records nothing
+	assert!(db.verify_credential(id, "104.19.128.109", 80, CredentialStatus::Valid).is_err());
+	assert!(db.verify_credential(id, "104.19.128.108", 8081, CredentialStatus::Valid).is_err());
+	assert!(db.host_id("104.19.128.109").unwrap().is_none());
+	assert!(db.credentials().unwrap()[0].services.len() == 2);
+
+	// an export imports into another db that knows the services
+	let other = Db::new(":memory:").unwrap();
+	other.create_table().unwrap();
+	assert!(other.insert_credential(&credentials[0]).is_err());
+	assert!(other.credentials().unwrap().is_empty());
+	other.insert_nmap_scan(Nmap::new(NMAP_XML.as_bytes()).unwrap()).unwrap();
+	assert!(other.insert_credential(&credentials[0]).unwrap().1);
+	let imported = other.credentials().unwrap();
+	assert!(imported[0].status == CredentialStatus::Valid);
+	assert!(imported[0].services.len() == 2);
+	assert!(imported[0].services[0].service_name.as_deref() == Some("http"));
+    }
+
+    #[test]
+    fn crack_credentials() {
+	let db = Db::new(":memory:").unwrap();
+	db.create_table().unwrap();
+
+	// the potfile may come first
+	let potfile = "64f12cddaa88057e06a81b54e73b949b:Password1\n$DCC2$10240#carol#e4e938d12fe5974dc42a90120bd9c90f:Winter2021\n\
+		       9c2f4e8a1b3d5f7091a2b3c4d5e6f708:Password0\n";
+	assert!(db.insert_potfile(Potfile::new(potfile.as_bytes()).unwrap()).unwrap() == 3);
+	assert!(db.insert_potfile(Potfile::new(potfile.as_bytes()).unwrap()).unwrap() == 0);
+	let secretsdump = r#"Administrator:500:aad3b435b51404eeaad3b435b51404ee:31d6cfe0d16ae931b73c59d7e0c089c0:::
+corp.local\alice:1104:aad3b435b51404eeaad3b435b51404ee:64F12CDDAA88057E06A81B54E73B949B:::
+corp.local\alice_history0:1104:aad3b435b51404eeaad3b435b51404ee:9c2f4e8a1b3d5f7091a2b3c4d5e6f708:::
+CORP.LOCAL/carol:$DCC2$10240#carol#e4e938d12fe5974dc42a90120bd9c90f
+"#;
+	// alice's former password is neither an account nor her password
+	assert!(db.insert_secretsdump_scan(Secretsdump::new(secretsdump.as_bytes()).unwrap()).unwrap() == 3);
+	assert!(db.crack_credentials().unwrap() == 2);
+	assert!(db.crack_credentials().unwrap() == 0);
+	assert!(db.credentials().unwrap().iter().all(|credential| !credential.username.contains("history")));
+
+	let cracked = db.cracked_credentials().unwrap();
+	assert!(cracked.len() == 2);
+	assert!(cracked[0].0.account() == "CORP.LOCAL\\carol");
+	assert!(cracked[0].1 == "Winter2021");
+	assert!(cracked[1].0.account() == "corp.local\\alice");
+	assert!(cracked[1].1 == "Password1");
+
+	// and later potfiles crack what is already there
+	db.insert_potfile(Potfile::new("31d6cfe0d16ae931b73c59d7e0c089c0:\n".as_bytes()).unwrap()).unwrap();
+	assert!(db.crack_credentials().unwrap() == 1);
+	let passwords: Vec<Credential> = db.credentials().unwrap()
+	    .into_iter()
+	    .filter(|credential| credential.kind == CredentialKind::Password)
+	    .collect();
+	assert!(passwords.len() == 3);
+	assert!(passwords.iter().all(|password| password.secret != "Password0"));
+	assert!(passwords[0].account() == "Administrator");
+	assert!(passwords[0].secret.is_empty());
+	assert!(passwords[0].source.as_deref().unwrap().starts_with("cracked ntlm "));
+    }
+
+    #[test]
+    fn encrypt_rollback() {
+	let mut db = Db::new(":memory:").unwrap();
+	db.create_table().unwrap();
+	db.insert_nmap_scan(Nmap::new(NMAP_XML.as_bytes()).unwrap()).unwrap();
+	db.insert_credential(&Credential::new("bob", "corp", CredentialKind::Password, "Summer2022!")).unwrap();
+	db.conn.execute("DROP TABLE note_history").unwrap();
+
+	// the credentials were already encrypted when the history failed
+	assert!(db.encrypt("correct horse").is_err());
+	assert!(!db.is_encrypted().unwrap());
+	assert!(db.credentials().unwrap()[0].secret == "Summer2022!");
+    }
+
+    #[test]
+    fn encrypt_secrets() {
+	let path = std::env::temp_dir().join(format!("pendb-encrypt-{}.db", std::process::id()));
+	let _ = std::fs::remove_file(&path);
+	let secrets = ["Summer2022!", "64f12cddaa88057e06a81b54e73b949b", "Password1", "WAF blocks sqlmap",
+		       "default creds", "Winter2023!", "use --tamper"];
+	{
+	    let mut db = Db::new(&path).unwrap();
+	    db.create_table().unwrap();
+	    db.insert_nmap_scan(Nmap::new(NMAP_XML.as_bytes()).unwrap()).unwrap();
+	    db.insert_credential(&Credential::new("bob", "corp", CredentialKind::Password, "Summer2022!")).unwrap();
+	    db.insert_credential(&Credential::new("alice", "corp", CredentialKind::Ntlm, "64f12cddaa88057e06a81b54e73b949b")).unwrap();
+	    db.insert_potfile(Potfile::new("64f12cddaa88057e06a81b54e73b949b:Password1\n".as_bytes()).unwrap()).unwrap();
+	    let port = [Condition::parse("port=80").unwrap()];
+	    let id = db.add_note("alice", "default creds", Entity::Service, &port).unwrap();
+	    db.edit_note(id, "alice", "WAF blocks sqlmap").unwrap();
+
+	    // 2 credentials, 2 potfile columns, note and history
+	    assert!(db.encrypt("correct horse").unwrap() == 6);
+	    assert!(db.encrypt("correct horse").is_err());
+
+	    // unlocked it works as before, joins included
+	    assert!(db.crack_credentials().unwrap() == 1);
+	    db.insert_credential(&Credential::new("carol", "corp", CredentialKind::Password, "Winter2023!")).unwrap();
+	    db.edit_note(id, "bob", "WAF blocks sqlmap, use --tamper").unwrap();
+	    assert!(db.cracked_credentials().unwrap()[0].1 == "Password1");
+	}
+
+	let bytes = std::fs::read(&path).unwrap();
+	for secret in &secrets {
+	    assert!(!bytes.windows(secret.len()).any(|window| window == secret.as_bytes()), "{} in plaintext", secret);
+	}
+
+	let mut db = Db::new(&path).unwrap();
+	db.create_table().unwrap();
+	assert!(db.is_encrypted().unwrap());
+	assert!(db.credentials().is_err());
+	assert!(db.insert_credential(&Credential::new("dave", "", CredentialKind::Password, "x")).is_err());
+	assert!(db.unlock("battery staple").is_err());
+	db.unlock("correct horse").unwrap();
+	let passwords: Vec<String> = db.credentials().unwrap()
+	    .into_iter()
+	    .filter(|credential| credential.kind == CredentialKind::Password)
+	    .map(|credential| credential.secret)
+	    .collect();
+	assert!(passwords == vec!["Password1", "Summer2022!", "Winter2023!"]);
+	assert!(db.notes().unwrap()[0].body == "WAF blocks sqlmap, use --tamper");
+	let history = db.note_history(1).unwrap();
+	assert!(history[0].body == "default creds");
+	assert!(history[1].body == "WAF blocks sqlmap");
+
+	// a note body copied over a secret does not pass for one
+	db.conn.execute("UPDATE credential SET secret = (SELECT body FROM note) WHERE username = 'bob'").unwrap();
+	assert!(db.credentials().is_err());
+	std::fs::remove_file(&path).unwrap();
+    }
+
+    // cargo test --release bench_host_join -- --ignored --nocapture
+    #[test]
+    #[ignore]
+    fn bench_host_join() {
+	use std::time::Instant;
+
+	let db = Db::new(":memory:").unwrap();
+	db.create_table().unwrap();
+
+	let hosts = 50_000;
+	let start = Instant::now();
+	db.conn.execute("BEGIN").unwrap();
+	{
+	    let scan_id = db.insert_scan("bench", None, None).unwrap();
+	    let mut services = ServiceCursors::new(&db.conn, scan_id).unwrap();
+	    let mut domains = DomainCursors::new(&db.conn, scan_id).unwrap();
+	    for i in 0..hosts {
+		// half of the hosts are IPv6, written the long way
+		let ip = if i % 2 == 0 {
+		    format!("10.{}.{}.{}", i >> 16, (i >> 8) & 0xff, i & 0xff)
+		} else {
+		    format!("2001:db8:0:0:0:0:{:x}:{:x}", i >> 16, i & 0xffff)
+		};
+		for port in [22, 80, 443, 8080] {
+		    services.insert(vec![
+			(":ip", Value::String(ip.clone())),
+			(":port", Value::Integer(port)),
+			(":protocol", Value::String("tcp".into())),
+			(":name", Value::Null),
+			(":product", Value::Null),
+			(":method", Value::Null),
+			(":conf", Value::Null),
+			(":state", Value::String("open".into())),
+			(":reason", Value::Null),
+			(":tunnel", Value::Null),
+			(":version", Value::Null),
+		    ]).unwrap();
+		}
+		domains.insert(DomainRow {
+		    ip: Some(&ip),
+		    domain: &format!("host{}.example.com", i),
+		    tool: "bench",
+		    ..Default::default()
+		}).unwrap();
+	    }
+	}
+	db.conn.execute("COMMIT").unwrap();
+	println!("insert {} hosts: {:?}", hosts, start.elapsed());
+	// an import of a log directory ends with this as well
+	db.analyze().unwrap();
+
+	let count = |sql: &str| {
+	    let start = Instant::now();
+	    let mut cursor = db.conn.prepare(sql).unwrap().into_cursor();
+	    let count = cursor.next().unwrap().unwrap()[0].as_integer().unwrap();
+	    (count, start.elapsed())
+	};
+	let (text, text_time) = count("SELECT count(*) FROM domain as d, service as s WHERE d.ip = s.ip");
+	let (id, id_time) = count("SELECT count(*) FROM domain as d, service as s WHERE d.host_id = s.host_id");
+	println!("join on ip TEXT: {:?}, on host_id: {:?}", text_time, id_time);
+	assert!(text == id);
+	assert!(id == hosts * 4);
+    }
+
+    #[test]
+    fn insert_web_path_scans() {
+	let db = Db::new(":memory:").unwrap();
+	db.create_table().unwrap();
+
+	let ffuf = Ffuf::new(FFUF_JSON.as_bytes()).unwrap();
+	let count = db.insert_ffuf_scan(ffuf);
+	assert!(count.is_ok());
+	assert!(count.unwrap() == 2);
+
+	// /admin was already found by ffuf and must not show up twice
+	let gobuster = Gobuster::new(GOBUSTER_OUT.as_bytes()).unwrap();
+	let count = db.insert_gobuster_scan(gobuster);
+	assert!(count.is_ok());
+	assert!(count.unwrap() == 1);
+
+	let mut cursor = db.conn.prepare("SELECT url, base_url, path, status, tool FROM web_path WHERE status IN (200, 403) ORDER BY path")
+	    .unwrap()
+	    .into_cursor();
+	let row = cursor.next().unwrap().unwrap();
+	assert!(row[1].as_string().unwrap() == "http://10.10.10.10:8080");
+	assert!(row[2].as_string().unwrap() == "/index.html");
+	assert!(row[4].as_string().unwrap() == "ffuf");
+	let row = cursor.next().unwrap().unwrap();
+	assert!(row[2].as_string().unwrap() == "/server-status");
+	assert!(row[3].as_integer().unwrap() == 403);
+	assert!(row[4].as_string().unwrap() == "gobuster");
+	assert!(cursor.next().unwrap().is_none());
+
+	// without banner and expanded urls there is no way to know the target
+	let gobuster = Gobuster::new("/admin (Status: 301)".as_bytes()).unwrap();
+	assert!(db.insert_gobuster_scan(gobuster).is_err());
+    }
+
+    #[test]
+    fn insert_nessus_scan() {
+	let db = Db::new(":memory:").unwrap();
+	db.create_table().unwrap();
+	db.insert_nmap_scan(Nmap::new(NMAP_XML.as_bytes()).unwrap()).unwrap();
+
+	// 3 findings and the new service on 8888, 443 is already known from nmap
+	let nessus = Nessus::new(NESSUS_XML.as_bytes()).unwrap();
+	let count = db.insert_nessus_scan(nessus);
+	assert!(count.is_ok());
+	assert!(count.unwrap() == 4);
+
+	let mut cursor = db.conn.prepare("SELECT port, service_name, service_method FROM service WHERE port IN (443, 8888) ORDER BY port")
+	    .unwrap()
+	    .into_cursor();
+	let row = cursor.next().unwrap().unwrap();
+	assert!(row[2].as_string().unwrap() == "probed");
+	let row = cursor.next().unwrap().unwrap();
+	assert!(row[1].as_string().unwrap() == "http");
+	assert!(row[2].as_string().unwrap() == "nessus");
+
+	let mut cursor = db.conn.prepare(r#"
+SELECT f.severity, f.cvss, f.cve, s.service_product
+FROM finding as f, service as s
+WHERE f.ip = s.ip AND f.port = s.port AND f.plugin_id = '42873'"#)
+	    .unwrap()
+	    .into_cursor();
+	let row = cursor.next().unwrap().unwrap();
+	assert!(row[0].as_string().unwrap() == "medium");
+	assert!(row[1].as_float().unwrap() == 7.5);
+	assert!(row[2].as_string().unwrap() == "CVE-2016-2183");
+	assert!(row[3].as_string().unwrap() == "Cloudflare http proxy");
+
+	let mut cursor = db.conn.prepare("SELECT h.os, d.domain FROM host as h, domain as d WHERE h.ip = d.ip")
+	    .unwrap()
+	    .into_cursor();
+	let row = cursor.next().unwrap().unwrap();
+	assert!(row[0].as_string().unwrap() == "Linux Kernel 3.10");
+	assert!(row[1].as_string().unwrap() == "4chan.org");
+
+	// importing the same report twice adds nothing
+	let nessus = Nessus::new(NESSUS_XML.as_bytes()).unwrap();
+	assert!(db.insert_nessus_scan(nessus).unwrap() == 0);
+
+	// report hosts named after the target, without a host-ip tag
+	db.insert_dnsx_scan(Dnsx::new(DNSX_JSON.as_bytes()).unwrap()).unwrap();
+	let named = NESSUS_XML.replace(r#"<tag name="host-ip">104.19.128.108</tag>"#, "")
+	    .replace(r#"<tag name="host-fqdn">4chan.org</tag>"#, "");
+	let resolved = named.replace(r#"ReportHost name="104.19.128.108""#, r#"ReportHost name="www.example.com""#);
+	assert!(db.insert_nessus_scan(Nessus::new(resolved.as_bytes()).unwrap()).unwrap() == 5);
+	let unresolved = named.replace(r#"ReportHost name="104.19.128.108""#, r#"ReportHost name="intranet.example.com""#);
+	assert!(db.insert_nessus_scan(Nessus::new(unresolved.as_bytes()).unwrap()).unwrap() == 3);
+	assert!(db.insert_nessus_scan(Nessus::new(unresolved.as_bytes()).unwrap()).unwrap() == 0);
+
+	let ips: Vec<String> = db.services().unwrap().into_iter().map(|service| service.ip).collect();
+	assert!(ips.iter().all(|ip| canonical_ip(ip).is_some()));
+	let addresses = db.resolve("www.example.com").unwrap();
+	assert!(ips.iter().filter(|ip| addresses.contains(ip)).count() == 2);
+	let mut cursor = db.conn.prepare("SELECT count(*) FROM finding WHERE ip IS NULL AND domain = 'intranet.example.com'").unwrap().into_cursor();
+	assert!(cursor.next().unwrap().unwrap()[0].as_integer() == Some(3));
+	assert!(db.hosts().unwrap().iter().all(|host| host.ip != "intranet.example.com"));
+    }
+
+    #[test]
+    fn insert_openvas_scan() {
+	let db = Db::new(":memory:").unwrap();
+	db.create_table().unwrap();
+	db.insert_nessus_scan(Nessus::new(NESSUS_XML.as_bytes()).unwrap()).unwrap();
+
+	let openvas = OpenVas::new(GMP_XML.as_bytes()).unwrap();
+	let count = db.insert_openvas_scan(openvas);
+	assert!(count.is_ok());
+	assert!(count.unwrap() == 1);
+
+	// both scanners report SWEET32 on the same port
+	let mut cursor = db.conn.prepare("SELECT scanner, severity, qod FROM finding WHERE ip = '104.19.128.108' AND port = 443 AND cve LIKE '%CVE-2016-2183%' ORDER BY scanner")
+	    .unwrap()
+	    .into_cursor();
+	let row = cursor.next().unwrap().unwrap();
+	assert!(row[0].as_string().unwrap() == "nessus");
+	assert!(row[2] == Value::Null);
+	let row = cursor.next().unwrap().unwrap();
+	assert!(row[0].as_string().unwrap() == "openvas");
+	assert!(row[1].as_string().unwrap() == "high");
+	assert!(row[2].as_integer().unwrap() == 98);
+	assert!(cursor.next().unwrap().is_none());
+
+	let mut cursor = db.conn.prepare("SELECT domain FROM domain WHERE ip = '104.19.128.108' ORDER BY domain")
+	    .unwrap()
+	    .into_cursor();
+	assert!(cursor.next().unwrap().unwrap()[0].as_string().unwrap() == "4chan.org");
+	assert!(cursor.next().unwrap().unwrap()[0].as_string().unwrap() == "www.4chan.org");
+    }
+
+    #[test]
+    fn insert_tls_scans() {
+	let db = Db::new(":memory:").unwrap();
+	db.create_table().unwrap();
+
+	// cipher, certificate link and the TLS1 finding
+	let testssl = Testssl::new(TESTSSL_JSON.as_bytes()).unwrap();
+	let count = db.insert_testssl_scan(testssl);
+	assert!(count.is_ok());
+	assert!(count.unwrap() == 3);
+
+	// sslscan only knows the name it was pointed at
+	db.conn.execute("INSERT INTO domain (ip, domain) VALUES ('93.184.216.34', 'example.com')").unwrap();
+	let sslscan = Sslscan::new(SSLSCAN_XML.as_bytes()).unwrap();
+	let count = db.insert_sslscan_scan(sslscan);
+	assert!(count.is_ok());
+	// TLS_AES_128_GCM_SHA256 and the expired certificate
+	assert!(count.unwrap() == 2);
+
+	let mut cursor = db.conn.prepare("SELECT protocol, enabled, tool FROM tls_protocol WHERE ip = '93.184.216.34' AND port = 443 ORDER BY protocol")
+	    .unwrap()
+	    .into_cursor();
+	let row = cursor.next().unwrap().unwrap();
+	assert!(row[0].as_string().unwrap() == "TLSv1.0");
+	assert!(row[1].as_integer().unwrap() == 0);
+	assert!(row[2].as_string().unwrap() == "sslscan");
+
+	let mut cursor = db.conn.prepare(r#"
+SELECT count(*), s.service_name
+FROM service_certificate as sc, certificate as c, service as s
+WHERE sc.certificate_id = c.id AND sc.ip = s.ip AND sc.port = s.port AND c.subject = 'www.example.org'"#)
+	    .unwrap()
+	    .into_cursor();
+	let row = cursor.next().unwrap().unwrap();
+	assert!(row[0].as_integer().unwrap() == 1);
+	assert!(row[1].as_string().unwrap() == "http");
+
+	let mut cursor = db.conn.prepare("SELECT scanner, plugin_id, severity FROM finding ORDER BY scanner")
+	    .unwrap()
+	    .into_cursor();
+	let row = cursor.next().unwrap().unwrap();
+	assert!(row[0].as_string().unwrap() == "sslscan");
+	assert!(row[1].as_string().unwrap() == "cert_expired");
+	let row = cursor.next().unwrap().unwrap();
+	assert!(row[0].as_string().unwrap() == "testssl");
+	assert!(row[1].as_string().unwrap() == "TLS1");
+	assert!(cursor.next().unwrap().is_none());
+    }
+
+    #[test]
+    fn insert_sslscan_unresolved() {
+	let db = Db::new(":memory:").unwrap();
+	db.create_table().unwrap();
+
+	// example.com is not in domain, so there is no address to store it under
+	let sslscan = Sslscan::new(SSLSCAN_XML.as_bytes()).unwrap();
+	assert!(db.insert_sslscan_scan(sslscan).unwrap() == 0);
+	let mut cursor = db.conn.prepare("SELECT count(*) FROM service").unwrap().into_cursor();
+	assert!(cursor.next().unwrap().unwrap()[0].as_integer().unwrap() == 0);
+	let mut cursor = db.conn.prepare("SELECT count(*) FROM tls_protocol").unwrap().into_cursor();
+	assert!(cursor.next().unwrap().unwrap()[0].as_integer().unwrap() == 0);
+    }
+
+    #[test]
+    fn insert_nmap_ssl_cert() {
+	let db = Db::new(":memory:").unwrap();
+	db.create_table().unwrap();
+	db.insert_amass_scan(Amass::new(AMASS_JSON.as_bytes()).unwrap()).unwrap();
+
+	let nmap = Nmap::new(NMAP_SSL_CERT_XML.as_bytes()).unwrap();
+	assert!(db.insert_nmap_scan(nmap).unwrap() == 2);
+
+	// one certificate served on two ports
+	let mut cursor = db.conn.prepare(r#"
+SELECT c.subject, c.not_after, group_concat(sc.port)
+FROM certificate as c, service_certificate as sc
+WHERE c.id = sc.certificate_id
+GROUP BY c.id"#)
+	    .unwrap()
+	    .into_cursor();
+	let row = cursor.next().unwrap().unwrap();
+	assert!(row[0].as_string().unwrap() == "4chan.org");
+	assert!(row[1].as_string().unwrap() == "2022-07-07 23:59:59");
+	assert!(row[2].as_string().unwrap() == "443,8443");
+	assert!(cursor.next().unwrap().is_none());
+
+	// 4chan.org is known from amass, the other names were only in the certificate
+	let mut cursor = db.conn.prepare("SELECT domain, source FROM domain WHERE ip = '104.19.128.108' ORDER BY domain")
+	    .unwrap()
+	    .into_cursor();
+	let row = cursor.next().unwrap().unwrap();
+	assert!(row[0].as_string().unwrap() == "1.thumbs.4chan.org");
+	let row = cursor.next().unwrap().unwrap();
+	assert!(row[0].as_string().unwrap() == "4chan.org");
+	assert!(row[1].as_string().unwrap() == "DNS, AlienVault, SonarSearch");
+	let row = cursor.next().unwrap().unwrap();
+	assert!(row[0].as_string().unwrap() == "sys.4chan.org");
+	assert!(row[1].as_string().unwrap() == "certificate");
+	assert!(cursor.next().unwrap().is_none());
+    }
 }
@@ -0,0 +1,98 @@
+use std::fmt;
+
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+
+// What a tag can be attached to, each kind is a table and the tag points at
+// its id.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Entity {
+    Host,
+    Service,
+    Domain,
+    Finding,
+}
+
+impl Entity {
+    pub fn parse(value: &str) -> Result<Self> {
+	match value {
+	    "host" => Ok(Entity::Host),
+	    "service" => Ok(Entity::Service),
+	    "domain" => Ok(Entity::Domain),
+	    "finding" => Ok(Entity::Finding),
+	    _ => Err(format!("unknown entity {}, use host, service, domain or finding", value).into()),
+	}
+    }
+
+    /// Name of the table, also stored in entity_tag.entity.
+    pub fn as_str(&self) -> &'static str {
+	match self {
+	    Entity::Host => "host",
+	    Entity::Service => "service",
+	    Entity::Domain => "domain",
+	    Entity::Finding => "finding",
+	}
+    }
+}
+
+impl fmt::Display for Entity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+	write!(f, "{}", self.as_str())
+    }
+}
+
+// column=value, selects the rows a tag is added to or removed from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Condition {
+    pub column: String,
+    pub value: String,
+}
+
+impl Condition {
+    pub fn parse(value: &str) -> Result<Self> {
+	match value.split_once('=') {
+	    Some((column, value)) if !column.trim().is_empty() => Ok(Self {
+		column: column.trim().to_string(),
+		value: value.trim().to_string(),
+	    }),
+	    _ => Err(format!("invalid condition {:?}, use column=value", value).into()),
+	}
+    }
+}
+
+/// Tags are free-form but a single word, so they can be passed around on
+/// the command line.
+pub fn check_tag(tag: &str) -> Result<&str> {
+    let tag = tag.trim();
+    if tag.is_empty() || tag.contains(|c: char| c.is_whitespace() || c == ',') {
+	return Err(format!("invalid tag {:?}", tag).into());
+    }
+    Ok(tag)
+}
+
+#[derive(Debug)]
+pub struct TagEntry {
+    pub tag: String,
+    pub entity: String,
+    pub count: i64,
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+
+    #[test]
+    fn parse_condition() {
+	assert!(Condition::parse("service_name=http").unwrap() == Condition { column: "service_name".into(), value: "http".into() });
+	assert!(Condition::parse("output=a=b").unwrap().value == "a=b");
+	assert!(Condition::parse("port").is_err());
+	assert!(Condition::parse("=80").is_err());
+	assert!(Entity::parse("service").unwrap() == Entity::Service);
+	assert!(Entity::parse("script").is_err());
+	assert!(check_tag(" needs-retest ").unwrap() == "needs-retest");
+	assert!(check_tag("two words").is_err());
+    }
+}
@@ -0,0 +1,4 @@
+pub mod parser;
+pub mod db;
+pub mod report;
+pub mod output;
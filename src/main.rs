@@ -1,42 +1,518 @@
-mod parser;
-mod db;
-use crate::parser::nmap::NmapRun;
-use crate::db::sqlite;
+use std::fs::File;
+use std::io::Read;
+use std::net::IpAddr;
+use std::path::Path;
 
-use serde_xml_rs::from_str;
+use pendb::parser::nmap::Nmap;
+use pendb::parser::amass::Amass;
+use pendb::parser::ffuf::Ffuf;
+use pendb::parser::feroxbuster::Feroxbuster;
+use pendb::parser::gobuster::Gobuster;
+use pendb::parser::nessus::Nessus;
+use pendb::parser::openvas::OpenVas;
+use pendb::parser::testssl::Testssl;
+use pendb::parser::sslscan::Sslscan;
+use pendb::parser::subfinder::Subfinder;
+use pendb::parser::assetfinder::Assetfinder;
+use pendb::parser::dnsx::Dnsx;
+use pendb::parser::zone::Zone;
+use pendb::parser::crtsh::Crtsh;
+use pendb::parser::secretsdump::Secretsdump;
+use pendb::parser::potfile::Potfile;
+use pendb::db::sqlite::{Db, Note, WILDCARD_MIN_NAMES};
+use pendb::db::scope::{Rule, ScopeFilter};
+use pendb::db::tag::{Condition, Entity};
+use pendb::db::filter::{parse_time, Filter, Table};
+use pendb::db::credential::{Credential, CredentialKind, CredentialService, CredentialStatus};
+use pendb::report::takeover::takeover;
+use pendb::report::shared::shared_infrastructure;
+use pendb::report::targets::{targets, Format as TargetFormat, TargetFilter};
+use pendb::report::diff::{diff, Point};
+use pendb::output::{notes_cell, Cell, Format, Listing};
+
+use serde_json::{from_reader, to_string_pretty};
 use walkdir::WalkDir;
 
 type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
 
 
+// Guess which tool wrote a log file and import it. Returns the tool name and
+// the number of new rows, or None if the file is not something we know.
+fn import_file(db: &Db, path: &Path) -> Result<Option<(&'static str, u32)>> {
+    let name = path.file_name().and_then(|name| name.to_str()).unwrap_or("");
+    let content = match std::fs::read_to_string(path) {
+	Ok(content) => content,
+	// binary files like nmap's .gnmap siblings are fine, random blobs are not ours
+	Err(_) => return Ok(None),
+    };
 
+    let imported = if name.ends_with(".xml") && content.contains("<nmaprun") {
+	("nmap", db.insert_nmap_scan(Nmap::new(content.as_bytes())?)?)
+    } else if content.contains("<NessusClientData_v2") {
+	("nessus", db.insert_nessus_scan(Nessus::new(content.as_bytes())?)?)
+    } else if name.ends_with(".xml") && content.contains("<report") && content.contains("<nvt oid=") {
+	("openvas", db.insert_openvas_scan(OpenVas::new(content.as_bytes())?)?)
+    } else if name.ends_with(".xml") && content.contains("<ssltest") {
+	("sslscan", db.insert_sslscan_scan(Sslscan::new(content.as_bytes())?)?)
+    } else if content.starts_with('[') && content.contains("\"name_value\"") {
+	("crtsh", db.insert_crtsh_scan(Crtsh::new(content.as_bytes())?)?)
+    } else if content.starts_with('[') && content.contains("\"finding\"") {
+	("testssl", db.insert_testssl_scan(Testssl::new(content.as_bytes())?)?)
+    } else if content.starts_with('{') && content.contains("\"commandline\"") && content.contains("\"results\"") {
+	("ffuf", db.insert_ffuf_scan(Ffuf::new(content.as_bytes())?)?)
+    } else if content.starts_with('{') && content.contains("\"type\":\"response\"") {
+	("feroxbuster", db.insert_feroxbuster_scan(Feroxbuster::new(content.as_bytes())?)?)
+    } else if content.starts_with('{') && content.contains("\"addresses\"") {
+	("amass", db.insert_amass_scan(Amass::new(content.as_bytes())?)?)
+    } else if name.ends_with(".zone") || content.starts_with("$ORIGIN") || content.starts_with("$TTL")
+	|| (content.contains("<<>> DiG") && content.contains("axfr")) {
+	("zone", db.insert_zone_scan(Zone::new(content.as_bytes())?)?)
+    } else if content.starts_with('{') && content.contains("\"host\"") && content.contains("\"input\"") {
+	("subfinder", db.insert_subfinder_scan(Subfinder::new(content.as_bytes())?)?)
+    } else if content.starts_with('{') && content.contains("\"host\"") && content.contains("\"status_code\"") {
+	("dnsx", db.insert_dnsx_scan(Dnsx::new(content.as_bytes())?)?)
+    } else if name.contains("assetfinder") {
+	// plain list of names, nothing in the content to recognize it by
+	("assetfinder", db.insert_assetfinder_scan(Assetfinder::new(content.as_bytes())?)?)
+    } else if [".sam", ".ntds", ".secrets", ".cached", ".cleartext", ".kerberos"].iter().any(|ext| name.ends_with(ext))
+	|| (content.contains("Impacket") && content.contains(":::")) {
+	("secretsdump", db.insert_secretsdump_scan(Secretsdump::new(content.as_bytes())?)?)
+    } else if name.ends_with(".pot") || name.ends_with(".potfile") {
+	// hash:plaintext lines, only the name says it is a potfile
+	("potfile", db.insert_potfile(Potfile::new(content.as_bytes())?)?)
+    } else if content.contains("(Status: ") {
+	("gobuster", db.insert_gobuster_scan(Gobuster::new(content.as_bytes())?)?)
+    } else {
+	return Ok(None);
+    };
+    Ok(Some(imported))
+}
 
-fn main() -> Result<()> {
-    
-
-    let args: Vec<String> = std::env::args().collect();
-    if args.len() != 2 {
-    	println!("Usage: {} <log-dir>", &args[0]);
-    	return Ok(());
-    }
 
-    for entry in WalkDir::new(&args[1])
+fn import_dir(db: &Db, dir: &str) -> Result<()> {
+    for entry in WalkDir::new(dir)
     {
 	let entry = entry?;
-	if entry.file_name().to_str().unwrap().ends_with(".xml") {
-	    let path = entry.into_path();
-	    let nmap_xml = std::fs::read_to_string(&path)?;
-	    let nmap = from_str::<NmapRun>(&nmap_xml)?;
-	    println!("{}", &nmap.args);
-	    //if let Ok(nmap) = from_str::<NmapRun>(&nmap_xml) {
-	    //	println!("{}", &nmap.args);
-	    //	//dbg!(&nmap);
-	    //} else {
-	    //	println!("fail");
-	    //}
+	if !entry.file_type().is_file() {
+	    continue;
+	}
+	let path = entry.into_path();
+	match import_file(db, &path) {
+	    Ok(Some((tool, count))) => println!("{}: {} new {} entries", path.display(), count, tool),
+	    Ok(None) => continue,
+	    Err(e) => println!("{}: {}", path.display(), e),
+	}
+    }
+    db.analyze()
+}
+
+fn print_takeover(db: &Db, options: &Options, format: Format) -> Result<()> {
+    let notes = db.entity_notes(Entity::Domain)?;
+    let domains = db.domains(true, ScopeFilter::Any, &[])?;
+    let candidates = takeover(db, options.scope, &options.tags)?;
+    // a note about any address of the name
+    let candidate_notes = candidates.iter().map(|candidate| {
+	let mut candidate_notes: Vec<Note> = domains.iter()
+	    .filter(|domain| domain.domain == candidate.domain)
+	    .flat_map(|domain| notes.get(&domain.id).into_iter().flatten().cloned())
+	    .collect();
+	candidate_notes.sort_by_key(|note| note.id);
+	candidate_notes.dedup_by_key(|note| note.id);
+	notes_cell(Some(&candidate_notes))
+    }).collect();
+    Listing::of(&candidates).column("notes", candidate_notes).print(format)
+}
+
+fn print_domains(db: &Db, options: &Options, format: Format) -> Result<()> {
+    let notes = db.entity_notes(Entity::Domain)?;
+    let domains = db.domains(options.all, options.scope, &options.tags)?;
+    let domain_notes = domains.iter().map(|domain| notes_cell(notes.get(&domain.id))).collect();
+    Listing::of(&domains).column("notes", domain_notes).print(format)
+}
+
+fn scope(db: &Db, args: &[String], format: Format) -> Result<()> {
+    match args {
+	[] => (),
+	[command, action, rules @ ..] if !rules.is_empty() => {
+	    let include = match action.as_str() {
+		"include" => true,
+		"exclude" => false,
+		_ => return Err(format!("unknown scope action {}, use include or exclude", action).into()),
+	    };
+	    if command != "add" && command != "remove" {
+		return Err(format!("unknown scope command {}, use add or remove", command).into());
+	    }
+	    let saved = rules.iter().try_for_each(|rule| -> Result<()> {
+		let rule = Rule::parse(include, rule)?;
+		match command.as_str() {
+		    "add" => db.add_scope_rule(&rule)?,
+		    _ => db.remove_scope_rule(&rule)?,
+		};
+		Ok(())
+	    });
+	    // the rules saved before a failing one are in effect as well
+	    db.apply_scope(&author())?;
+	    saved?;
+	},
+	_ => return Err("usage: scope [add|remove include|exclude <rule>...]".into()),
+    }
+    Listing::of(&db.scope_rules()?.rules).print(format)
+}
 
+fn shared(db: &Db, args: &[String], format: Format) -> Result<()> {
+    match args {
+	[command, ips @ ..] if command == "approve" || command == "revoke" => {
+	    for ip in ips {
+		db.approve_shared(ip, command == "approve")?;
+	    }
+	    Ok(())
+	},
+	options => {
+	    let options = Options::parse(options)?;
+	    let notes = db.entity_notes(Entity::Domain)?;
+	    let alerts = shared_infrastructure(db, options.scope, &options.tags)?;
+	    let alert_notes = alerts.iter().map(|alert| notes_cell(notes.get(&alert.id))).collect();
+	    Listing::of(&alerts).column("notes", alert_notes).print(format)
+	},
+    }
+}
+
+// One target per line on stdout, for piping into the next tool. What was
+// left out goes to stderr.
+fn print_targets(db: &Db, args: &[String], format: Format) -> Result<()> {
+    let (target_format, args) = args.split_first().ok_or("usage: targets ip|ipport|url|hostname [options]")?;
+    let target_format = TargetFormat::parse(target_format)?;
+    let mut filter = TargetFilter::default();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+	let mut value = || args.next().ok_or(format!("{} needs a value", arg));
+	match arg.as_str() {
+	    "--service" => filter.services.extend(value()?.split(',').map(|name| name.trim().to_string())),
+	    "--port" => {
+		for port in value()?.split(',') {
+		    filter.ports.push(port.trim().parse().map_err(|_| format!("invalid port {}", port))?);
+		}
+	    },
+	    "--tag" => filter.tags.push(value()?.clone()),
+	    "--scope" => filter.scope = ScopeFilter::parse(value()?)?,
+	    "--since" => filter.since = Some(parse_time(value()?)?),
+	    "--until" => filter.until = Some(parse_time(value()?)?),
+	    "--where" => filter.filter = Some(Filter::parse(value()?)?),
+	    _ => return Err(format!("unknown option {}", arg).into()),
 	}
     }
+    let targets = targets(db, target_format, &filter)?;
+    let mut listing = Listing::new(&["target"]);
+    for target in &targets.targets {
+	listing.push(vec![target.as_str().into()]);
+    }
+    listing.print(format)?;
+    if !targets.excluded.is_empty() {
+	eprintln!("left out {} unapproved addresses on shared infrastructure: {}", targets.excluded.len(), targets.excluded.join(" "));
+    }
+    Ok(())
+}
+
+// Rows of a table matching a filter expression, the rest of the arguments
+// are the expression so it can be given with or without quotes.
+fn list(db: &Db, args: &[String], format: Format) -> Result<()> {
+    let (table, expression) = args.split_first().ok_or("usage: list services|domains|scripts [<filter>]")?;
+    let table = Table::parse(table)?;
+    let filter = match expression.join(" ").trim() {
+	"" => None,
+	expression => Some(Filter::parse(expression)?),
+    };
+    let selected = db.filtered(table, filter.as_ref())?;
+    let selected = |id: &i64| selected.as_ref().is_none_or(|selected| selected.contains(id));
+    match table {
+	Table::Service => {
+	    let notes = db.entity_notes(Entity::Service)?;
+	    let services: Vec<_> = db.services()?.into_iter().filter(|service| selected(&service.id)).collect();
+	    let service_notes = services.iter().map(|service| notes_cell(notes.get(&service.id))).collect();
+	    Listing::of(&services).column("notes", service_notes).print(format)
+	},
+	Table::Domain => {
+	    let notes = db.entity_notes(Entity::Domain)?;
+	    let domains: Vec<_> = db.domains(true, ScopeFilter::Any, &[])?.into_iter().filter(|domain| selected(&domain.id)).collect();
+	    let domain_notes = domains.iter().map(|domain| notes_cell(notes.get(&domain.id))).collect();
+	    Listing::of(&domains).column("notes", domain_notes).print(format)
+	},
+	Table::Script => {
+	    let scripts: Vec<_> = db.scripts()?.into_iter().filter(|script| selected(&script.id)).collect();
+	    Listing::of(&scripts).print(format)
+	},
+    }
+}
+
+// history <ip>, <ip>:<port> or [<ipv6>]:<port>
+fn history(db: &Db, target: &str, format: Format) -> Result<()> {
+    let (ip, port) = match target.parse::<IpAddr>() {
+	Ok(_) => (target, None),
+	Err(_) => {
+	    let (ip, port) = target.rsplit_once(':').ok_or(format!("invalid target {}, use <ip> or <ip>:<port>", target))?;
+	    let port = port.parse().map_err(|_| format!("invalid port {}", port))?;
+	    (ip.trim_start_matches('[').trim_end_matches(']'), Some(port))
+	},
+    };
+    Listing::of(&db.history(ip, port)?).print(format)
+}
+
+fn tag(db: &Db, args: &[String], format: Format) -> Result<()> {
+    match args {
+	[] => Listing::of(&db.tags()?).print(format),
+	[command, tag, entity, conditions @ ..] if command == "add" || command == "remove" => {
+	    let conditions = conditions.iter()
+		.map(|condition| Condition::parse(condition))
+		.collect::<Result<Vec<_>>>()?;
+	    let count = db.tag(tag, Entity::parse(entity)?, &conditions, command == "add")?;
+	    println!("{} {} rows changed", count, entity);
+	    Ok(())
+	},
+	_ => Err("usage: tag [add|remove <tag> host|service|domain|finding [column=value...]]".into()),
+    }
+}
 
+// Who writes notes, PENDB_AUTHOR or the login name.
+fn author() -> String {
+    std::env::var("PENDB_AUTHOR")
+	.or_else(|_| std::env::var("USER"))
+	.unwrap_or_else(|_| "unknown".to_string())
+}
+
+// A note body from the command line, "-" reads it from stdin.
+fn note_body(body: &str) -> Result<String> {
+    if body == "-" {
+	let mut body = String::new();
+	std::io::stdin().read_to_string(&mut body)?;
+	Ok(body.trim_end().to_string())
+    } else {
+	Ok(body.to_string())
+    }
+}
+
+fn note(db: &Db, args: &[String], format: Format) -> Result<()> {
+    match args {
+	[] => Listing::of(&db.notes()?).print(format)?,
+	[command, entity, body, conditions @ ..] if command == "add" => {
+	    let conditions = conditions.iter()
+		.map(|condition| Condition::parse(condition))
+		.collect::<Result<Vec<_>>>()?;
+	    let id = db.add_note(&author(), &note_body(body)?, Entity::parse(entity)?, &conditions)?;
+	    println!("note {}", id);
+	},
+	[command, id, body] if command == "edit" => db.edit_note(id.parse()?, &author(), &note_body(body)?)?,
+	[command, id] if command == "delete" => db.delete_note(id.parse()?, &author())?,
+	[command, id] if command == "history" => Listing::of(&db.note_history(id.parse()?)?).print(format)?,
+	_ => return Err("usage: note [add host|service|domain|finding <body> [column=value...]|edit <id> <body>|delete <id>|history <id>]".into()),
+    }
     Ok(())
 }
+
+fn creds(db: &Db, args: &[String], format: Format) -> Result<()> {
+    match args {
+	[] => Listing::of(&db.credentials()?).print(format)?,
+	[command, kind, username, secret, options @ ..] if command == "add" => {
+	    let (realm, username) = username.split_once('\\').unwrap_or(("", username));
+	    let mut credential = Credential::new(username, realm, CredentialKind::parse(kind)?, secret);
+	    match options {
+		[] => (),
+		[option, source] if option == "--source" => credential.source = Some(source.clone()),
+		_ => return Err("usage: creds add <kind> [<realm>\\]<username> <secret> [--source <source>]".into()),
+	    }
+	    let (id, new) = db.insert_credential(&credential)?;
+	    db.crack_credentials()?;
+	    println!("credential {}{}", id, if new { "" } else { " (known)" });
+	},
+	[command, id, target, status] if command == "verify" => {
+	    let (ip, port) = CredentialService::parse_target(target)?;
+	    db.verify_credential(id.parse()?, &ip, port, CredentialStatus::parse(status)?)?;
+	},
+	[command, id] if command == "services" => {
+	    let services: Vec<_> = db.credential_services(id.parse()?)?
+		.into_iter()
+		.filter(|service| service.status == CredentialStatus::Valid)
+		.collect();
+	    Listing::of(&services).print(format)?;
+	},
+	[command] if command == "cracked" => {
+	    let mut listing = Listing::new(&["id", "account", "kind", "secret", "plaintext"]);
+	    for (credential, plaintext) in db.cracked_credentials()? {
+		listing.push(vec![
+		    credential.id.into(),
+		    credential.account().into(),
+		    credential.kind.as_str().into(),
+		    credential.secret.into(),
+		    Cell::Text(plaintext),
+		]);
+	    }
+	    listing.print(format)?;
+	},
+	[command] if command == "export" => println!("{}", to_string_pretty(&db.credentials()?)?),
+	[command, path] if command == "import" => {
+	    let credentials: Vec<Credential> = from_reader(File::open(path)?)?;
+	    let new = credentials.iter()
+		.map(|credential| db.insert_credential(credential))
+		.collect::<Result<Vec<_>>>()?
+		.iter()
+		.filter(|(_, new)| *new)
+		.count();
+	    db.crack_credentials()?;
+	    println!("{}: {} new credentials", path, new);
+	},
+	_ => return Err("usage: creds [add <kind> <username> <secret>|verify <id> <ip:port> valid|invalid|services <id>|cracked|export|import <file>]".into()),
+    }
+    Ok(())
+}
+
+// PENDB_PASSPHRASE for scripts, a prompt without echo otherwise.
+fn passphrase(prompt: &str) -> Result<String> {
+    match std::env::var("PENDB_PASSPHRASE") {
+	Ok(passphrase) => Ok(passphrase),
+	Err(_) => rpassword::prompt_password(prompt)
+	    .map_err(|e| format!("the database is encrypted, set PENDB_PASSPHRASE without a terminal ({})", e).into()),
+    }
+}
+
+// Options of the listing commands.
+struct Options {
+    // include wildcard rows
+    all: bool,
+    scope: ScopeFilter,
+    // only rows carrying all of these
+    tags: Vec<String>,
+}
+
+impl Options {
+    fn parse(args: &[String]) -> Result<Self> {
+	let mut options = Options {
+	    all: false,
+	    scope: ScopeFilter::Default,
+	    tags: vec![],
+	};
+	let mut args = args.iter();
+	while let Some(arg) = args.next() {
+	    match arg.as_str() {
+		"--all" => options.all = true,
+		"--scope" => options.scope = ScopeFilter::parse(args.next().ok_or("--scope needs a value")?)?,
+		"--tag" => options.tags.push(args.next().ok_or("--tag needs a value")?.clone()),
+		_ => return Err(format!("unknown option {}", arg).into()),
+	    }
+	}
+	Ok(options)
+    }
+}
+
+fn usage(program: &str) {
+    println!("Usage: {} <db> <log-dir>", program);
+    println!("       {} <db> domains [--all] [--scope in|out|unknown|any] [--tag <tag>]...", program);
+    println!("       {} <db> takeover [--scope in|out|unknown|any] [--tag <tag>]...", program);
+    println!("       {} <db> scope [add|remove include|exclude <rule>...]", program);
+    println!("       {} <db> shared [--scope in|out|unknown|any] [--tag <tag>]...", program);
+    println!("       {} <db> shared approve|revoke <ip>...", program);
+    println!("       {} <db> targets ip|ipport|url|hostname [--service <name,...>] [--port <port,...>]", program);
+    println!("           [--tag <tag>]... [--scope in|out|unknown|any] [--since <time>] [--until <time>] [--where <filter>]");
+    println!("       {} <db> list services|domains|scripts [<filter>]", program);
+    println!("       {} <db> tag [add|remove <tag> host|service|domain|finding [column=value...]]", program);
+    println!("       {} <db> note [add host|service|domain|finding <body> [column=value...]]", program);
+    println!("       {} <db> note edit <id> <body> | delete <id> | history <id>", program);
+    println!("       {} <db> creds [add password|ntlm|hash|key|token [<realm>\\]<username> <secret> [--source <source>]]", program);
+    println!("       {} <db> creds verify <id> <ip:port> valid|invalid | services <id> | cracked | export | import <file>", program);
+    println!("       {} <db> scans", program);
+    println!("       {} <db> diff <scan|time> [<scan|time>|latest]", program);
+    println!("       {} <db> history <ip>[:<port>]", program);
+    println!("       {} <db> encrypt", program);
+    println!();
+    println!("Listings take --format table|csv|json|jsonl|markdown, table by default.");
+    println!("Column names are the same in every format.");
+    println!();
+    println!("Scope rules are CIDRs (10.0.0.0/8), ranges (10.0.0.1-10.0.0.50),");
+    println!("domain suffixes (*.example.com) or exact hosts (example.com, 10.0.0.1).");
+    println!("Addresses on CDNs and shared hosting stay out of target lists until approved.");
+    println!("Target times are YYYY-MM-DD[ HH:MM:SS] in UTC and compared with when a target was");
+    println!("first imported, --tag matches hosts, services or names, whatever is listed.");
+    println!("Filters look like: port in (80,443) and service ~ http and product contains \"Apache\"");
+    println!("and tag:prod and seen>2026-10-01. ~ is a case-insensitive substring match, an");
+    println!("unknown field shows the fields of the table. --where selects services, or names");
+    println!("for hostname lists.");
+    println!("Notes are Markdown, a body of - is read from stdin, PENDB_AUTHOR names the author.");
+    println!("diff compares two nmap or amass scans, or everything scanned up to a time, with");
+    println!("the latest state by default. A port missing from a scan that probed it is closed.");
+    println!("history shows every change to the services, scripts and names of an address");
+    println!("and which scan or user made it.");
+    println!("takeover checks pages by name, point nmap's http scripts at the names themselves.");
+    println!("encrypt protects credential secrets and notes with a passphrase, asked for on");
+    println!("every later start or taken from PENDB_PASSPHRASE.");
+}
+
+
+fn main() -> Result<()> {
+    let mut args: Vec<String> = std::env::args().collect();
+    // --format goes with every listing, wherever it is given
+    let format = match args.iter().position(|arg| arg == "--format") {
+	Some(i) if i + 1 < args.len() => {
+	    let format = Format::parse(&args[i + 1])?;
+	    args.drain(i..i + 2);
+	    format
+	},
+	Some(_) => return Err("--format needs a value".into()),
+	None => Format::Table,
+    };
+    if args.len() < 3 {
+	usage(&args[0]);
+	return Ok(());
+    }
+
+    let mut db = Db::new(&args[1])?;
+    db.create_table()?;
+    if db.is_encrypted()? {
+	db.unlock(&passphrase("passphrase: ")?)?;
+    }
+
+    match (args[2].as_str(), &args[3..]) {
+	("encrypt", []) => {
+	    let passphrase = passphrase("new passphrase: ")?;
+	    if std::env::var("PENDB_PASSPHRASE").is_err() && passphrase != rpassword::prompt_password("again: ")? {
+		return Err("the passphrases differ".into());
+	    }
+	    println!("{} values encrypted", db.encrypt(&passphrase)?);
+	    Ok(())
+	},
+	("takeover", options) => print_takeover(&db, &Options::parse(options)?, format),
+	("domains", options) => print_domains(&db, &Options::parse(options)?, format),
+	("scope", args) => scope(&db, args, format),
+	("shared", args) => shared(&db, args, format),
+	("targets", args) => print_targets(&db, args, format),
+	("list", args) => list(&db, args, format),
+	("tag", args) => tag(&db, args, format),
+	("note", args) => note(&db, args, format),
+	("creds", args) => creds(&db, args, format),
+	("history", [target]) => history(&db, target, format),
+	("scans", []) => Listing::of(&db.scans()?).print(format),
+	("diff", [from]) => Listing::of(&diff(&db, &Point::parse(from)?, &Point::Latest)?).print(format),
+	("diff", [from, to]) => Listing::of(&diff(&db, &Point::parse(from)?, &Point::parse(to)?)?).print(format),
+	(dir, []) => {
+	    import_dir(&db, dir)?;
+	    let wildcards = db.mark_wildcards(WILDCARD_MIN_NAMES, &author())?;
+	    if wildcards > 0 {
+		println!("{} new wildcard domain entries", wildcards);
+	    }
+	    let cracked = db.crack_credentials()?;
+	    if cracked > 0 {
+		println!("{} new cracked passwords, see creds cracked", cracked);
+	    }
+	    db.apply_scope(&author())?;
+	    let shared = shared_infrastructure(&db, ScopeFilter::Default, &[])?
+		.iter()
+		.filter(|alert| !alert.approved)
+		.count();
+	    if shared > 0 {
+		println!("warning: {} in scope domain entries resolve to shared infrastructure, see shared", shared);
+	    }
+	    Ok(())
+	},
+	_ => {
+	    usage(&args[0]);
+	    Ok(())
+	},
+    }
+}
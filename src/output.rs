@@ -0,0 +1,573 @@
+use std::io::{self, Write};
+
+use crate::db::sqlite::{DomainEntry, ServiceEntry, ScriptEntry, ScanEntry, HistoryEntry, Note, NoteVersion};
+use crate::db::scope::Rule;
+use crate::db::tag::TagEntry;
+use crate::db::credential::{Credential, CredentialService};
+use crate::report::takeover::{Candidate, Reason};
+use crate::report::shared::Alert;
+use crate::report::diff::DiffEntry;
+
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+
+// How listings are written:
+//   table     aligned columns for the terminal, multi-line values below the row
+//   csv       RFC 4180, with a header row
+//   json      one array of objects
+//   jsonl     one object per line
+//   markdown  a table for reports
+//
+// Column names are the same in every format and only ever get added to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Table,
+    Csv,
+    Json,
+    Jsonl,
+    Markdown,
+}
+
+impl Format {
+    pub fn parse(value: &str) -> Result<Self> {
+	match value {
+	    "table" => Ok(Format::Table),
+	    "csv" => Ok(Format::Csv),
+	    "json" => Ok(Format::Json),
+	    "jsonl" => Ok(Format::Jsonl),
+	    "markdown" | "md" => Ok(Format::Markdown),
+	    _ => Err(format!("unknown format {}, use table, csv, json, jsonl or markdown", value).into()),
+	}
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Cell {
+    Null,
+    Text(String),
+    Integer(i64),
+    Bool(bool),
+    // notes, script output, ... a JSON array of strings
+    Lines(Vec<String>),
+}
+
+impl Cell {
+    fn text(&self, separator: &str) -> String {
+	match self {
+	    Cell::Null => String::new(),
+	    Cell::Text(text) => text.clone(),
+	    Cell::Integer(integer) => integer.to_string(),
+	    Cell::Bool(value) => value.to_string(),
+	    Cell::Lines(lines) => lines.join(separator),
+	}
+    }
+
+    fn json(&self) -> String {
+	let value = match self {
+	    Cell::Null => serde_json::Value::Null,
+	    Cell::Text(text) => serde_json::Value::from(text.as_str()),
+	    Cell::Integer(integer) => serde_json::Value::from(*integer),
+	    Cell::Bool(value) => serde_json::Value::from(*value),
+	    Cell::Lines(lines) => serde_json::Value::from(lines.clone()),
+	};
+	value.to_string()
+    }
+}
+
+impl From<String> for Cell {
+    fn from(value: String) -> Self {
+	Cell::Text(value)
+    }
+}
+
+impl From<&str> for Cell {
+    fn from(value: &str) -> Self {
+	Cell::Text(value.to_string())
+    }
+}
+
+impl<T: Into<Cell>> From<Option<T>> for Cell {
+    fn from(value: Option<T>) -> Self {
+	value.map_or(Cell::Null, |value| value.into())
+    }
+}
+
+impl From<i64> for Cell {
+    fn from(value: i64) -> Self {
+	Cell::Integer(value)
+    }
+}
+
+impl From<u16> for Cell {
+    fn from(value: u16) -> Self {
+	Cell::Integer(value as i64)
+    }
+}
+
+impl From<bool> for Cell {
+    fn from(value: bool) -> Self {
+	Cell::Bool(value)
+    }
+}
+
+impl From<Vec<String>> for Cell {
+    fn from(value: Vec<String>) -> Self {
+	Cell::Lines(value)
+    }
+}
+
+/// A row type of a listing and its columns.
+pub trait Record {
+    fn columns() -> Vec<&'static str>;
+    fn cells(&self) -> Vec<Cell>;
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Listing {
+    pub columns: Vec<&'static str>,
+    pub rows: Vec<Vec<Cell>>,
+}
+
+impl Listing {
+    pub fn new(columns: &[&'static str]) -> Self {
+	Self {
+	    columns: columns.to_vec(),
+	    rows: vec![],
+	}
+    }
+
+    pub fn of<R: Record>(records: &[R]) -> Self {
+	Self {
+	    columns: R::columns(),
+	    rows: records.iter().map(|record| record.cells()).collect(),
+	}
+    }
+
+    pub fn push(&mut self, row: Vec<Cell>) {
+	assert!(row.len() == self.columns.len());
+	self.rows.push(row);
+    }
+
+    /// Adds a column, one cell per row.
+    pub fn column(mut self, name: &'static str, cells: Vec<Cell>) -> Self {
+	assert!(cells.len() == self.rows.len());
+	self.columns.push(name);
+	for (row, cell) in self.rows.iter_mut().zip(cells) {
+	    row.push(cell);
+	}
+	self
+    }
+
+    pub fn write<W: Write>(&self, format: Format, out: &mut W) -> io::Result<()> {
+	match format {
+	    Format::Table => self.write_table(out),
+	    Format::Csv => self.write_csv(out),
+	    Format::Json => {
+		writeln!(out, "[")?;
+		for (i, row) in self.rows.iter().enumerate() {
+		    let comma = if i + 1 < self.rows.len() { "," } else { "" };
+		    writeln!(out, "  {}{}", self.json_object(row), comma)?;
+		}
+		writeln!(out, "]")
+	    },
+	    Format::Jsonl => {
+		for row in &self.rows {
+		    writeln!(out, "{}", self.json_object(row))?;
+		}
+		Ok(())
+	    },
+	    Format::Markdown => self.write_markdown(out),
+	}
+    }
+
+    pub fn print(&self, format: Format) -> Result<()> {
+	let stdout = io::stdout();
+	let mut out = stdout.lock();
+	self.write(format, &mut out)?;
+	Ok(())
+    }
+
+    // A single column, like a target list, is printed as a plain list so it
+    // can be piped into other tools.
+    fn write_table<W: Write>(&self, out: &mut W) -> io::Result<()> {
+	let is_lines = |i: usize| self.rows.iter().any(|row| matches!(row[i], Cell::Lines(_)));
+	let columns: Vec<usize> = (0..self.columns.len()).filter(|i| !is_lines(*i)).collect();
+	if columns.len() == 1 && self.columns.len() == 1 {
+	    for row in &self.rows {
+		writeln!(out, "{}", row[0].text(" "))?;
+	    }
+	    return Ok(());
+	}
+	let mut widths: Vec<usize> = columns.iter().map(|i| self.columns[*i].len()).collect();
+	for row in &self.rows {
+	    for (width, i) in widths.iter_mut().zip(&columns) {
+		*width = (*width).max(row[*i].text(" ").chars().count());
+	    }
+	}
+	let line = |cells: Vec<String>| -> String {
+	    let padded: Vec<String> = cells.iter().zip(&widths)
+		.map(|(cell, width)| format!("{:width$}", cell, width = width))
+		.collect();
+	    padded.join("  ").trim_end().to_string()
+	};
+	writeln!(out, "{}", line(columns.iter().map(|i| self.columns[*i].to_string()).collect()))?;
+	for row in &self.rows {
+	    writeln!(out, "{}", line(columns.iter().map(|i| row[*i].text(" ")).collect()))?;
+	    for cell in row {
+		if let Cell::Lines(lines) = cell {
+		    for text in lines {
+			// the rest of a multi-line entry hangs below its first line
+			for (j, line) in text.lines().enumerate() {
+			    writeln!(out, "{}{}", if j == 0 { "    " } else { "      " }, line)?;
+			}
+		    }
+		}
+	    }
+	}
+	Ok(())
+    }
+
+    fn write_csv<W: Write>(&self, out: &mut W) -> io::Result<()> {
+	let line = |cells: Vec<String>| -> String {
+	    cells.iter().map(|cell| csv_field(cell)).collect::<Vec<_>>().join(",")
+	};
+	write!(out, "{}\r\n", line(self.columns.iter().map(|name| name.to_string()).collect()))?;
+	for row in &self.rows {
+	    write!(out, "{}\r\n", line(row.iter().map(|cell| cell.text("\n")).collect()))?;
+	}
+	Ok(())
+    }
+
+    fn write_markdown<W: Write>(&self, out: &mut W) -> io::Result<()> {
+	let line = |cells: Vec<String>| -> String {
+	    format!("| {} |", cells.join(" | "))
+	};
+	writeln!(out, "{}", line(self.columns.iter().map(|name| name.to_string()).collect()))?;
+	writeln!(out, "{}", line(self.columns.iter().map(|_| "---".to_string()).collect()))?;
+	for row in &self.rows {
+	    writeln!(out, "{}", line(row.iter().map(|cell| markdown_cell(&cell.text("\n"))).collect()))?;
+	}
+	Ok(())
+    }
+
+    // keys in column order, serde_json would sort them
+    fn json_object(&self, row: &[Cell]) -> String {
+	let fields: Vec<String> = self.columns.iter().zip(row)
+	    .map(|(name, cell)| format!("{}:{}", serde_json::Value::from(*name), cell.json()))
+	    .collect();
+	format!("{{{}}}", fields.join(","))
+    }
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+	format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+	value.to_string()
+    }
+}
+
+fn markdown_cell(value: &str) -> String {
+    value.replace('\\', "\\\\")
+	.replace('|', "\\|")
+	.replace("\r\n", "<br>")
+	.replace('\n', "<br>")
+}
+
+/// "note 3 by alice at 2026-10-01 12:00:00: body", one per note for the
+/// notes column of a listing.
+pub fn notes_cell(notes: Option<&Vec<Note>>) -> Cell {
+    let notes: Vec<String> = notes.into_iter()
+	.flatten()
+	.map(|note| format!("note {} by {} at {}: {}", note.id, note.author, note.updated, note.body))
+	.collect();
+    Cell::Lines(notes)
+}
+
+impl Record for DomainEntry {
+    fn columns() -> Vec<&'static str> {
+	vec!["domain", "ip", "scope", "tool", "source", "wildcard", "first_seen"]
+    }
+
+    fn cells(&self) -> Vec<Cell> {
+	vec![
+	    self.domain.as_str().into(),
+	    self.ip.clone().into(),
+	    self.scope.as_str().into(),
+	    self.tool.clone().into(),
+	    self.source.clone().into(),
+	    self.wildcard.into(),
+	    self.first_seen.clone().into(),
+	]
+    }
+}
+
+impl Record for ServiceEntry {
+    fn columns() -> Vec<&'static str> {
+	vec!["ip", "port", "protocol", "state", "service", "product", "ssl", "scope", "first_seen", "last_seen"]
+    }
+
+    fn cells(&self) -> Vec<Cell> {
+	vec![
+	    self.ip.as_str().into(),
+	    self.port.into(),
+	    self.protocol.clone().into(),
+	    self.state.clone().into(),
+	    self.service_name.clone().into(),
+	    self.product.clone().into(),
+	    self.ssl.into(),
+	    self.scope.as_str().into(),
+	    self.first_seen.clone().into(),
+	    self.last_seen.clone().into(),
+	]
+    }
+}
+
+impl Record for ScriptEntry {
+    fn columns() -> Vec<&'static str> {
+	vec!["ip", "port", "vhost", "script", "output"]
+    }
+
+    fn cells(&self) -> Vec<Cell> {
+	let output = self.output.lines()
+	    .filter(|line| !line.trim().is_empty())
+	    .map(|line| line.trim_end().to_string())
+	    .collect::<Vec<_>>();
+	vec![
+	    self.ip.as_str().into(),
+	    self.port.into(),
+	    self.vhost.clone().into(),
+	    self.script_id.as_str().into(),
+	    output.into(),
+	]
+    }
+}
+
+impl Record for Rule {
+    fn columns() -> Vec<&'static str> {
+	vec!["action", "kind", "rule"]
+    }
+
+    fn cells(&self) -> Vec<Cell> {
+	vec![self.action().into(), self.kind().into(), self.value().into()]
+    }
+}
+
+impl Record for TagEntry {
+    fn columns() -> Vec<&'static str> {
+	vec!["tag", "entity", "count"]
+    }
+
+    fn cells(&self) -> Vec<Cell> {
+	vec![self.tag.as_str().into(), self.entity.as_str().into(), self.count.into()]
+    }
+}
+
+impl Record for Note {
+    fn columns() -> Vec<&'static str> {
+	vec!["id", "author", "created", "updated", "body"]
+    }
+
+    fn cells(&self) -> Vec<Cell> {
+	vec![
+	    self.id.into(),
+	    self.author.as_str().into(),
+	    self.created.as_str().into(),
+	    self.updated.as_str().into(),
+	    Cell::Lines(self.body.lines().map(|line| line.to_string()).collect()),
+	]
+    }
+}
+
+impl Record for NoteVersion {
+    fn columns() -> Vec<&'static str> {
+	vec!["author", "time", "body"]
+    }
+
+    fn cells(&self) -> Vec<Cell> {
+	vec![
+	    self.author.as_str().into(),
+	    self.time.as_str().into(),
+	    Cell::Lines(self.body.lines().map(|line| line.to_string()).collect()),
+	]
+    }
+}
+
+impl Record for Credential {
+    fn columns() -> Vec<&'static str> {
+	vec!["id", "account", "kind", "secret", "status", "source"]
+    }
+
+    fn cells(&self) -> Vec<Cell> {
+	vec![
+	    self.id.into(),
+	    self.account().into(),
+	    self.kind.as_str().into(),
+	    self.secret.as_str().into(),
+	    self.status.as_str().into(),
+	    self.source.clone().into(),
+	]
+    }
+}
+
+impl Record for CredentialService {
+    fn columns() -> Vec<&'static str> {
+	vec!["ip", "port", "service", "status", "verified"]
+    }
+
+    fn cells(&self) -> Vec<Cell> {
+	vec![
+	    self.ip.as_str().into(),
+	    self.port.into(),
+	    self.service_name.clone().into(),
+	    self.status.as_str().into(),
+	    self.verified.as_str().into(),
+	]
+    }
+}
+
+impl Record for Alert {
+    fn columns() -> Vec<&'static str> {
+	vec!["domain", "ip", "provider", "asn", "cidr", "approved"]
+    }
+
+    fn cells(&self) -> Vec<Cell> {
+	vec![
+	    self.domain.as_str().into(),
+	    self.ip.as_str().into(),
+	    self.provider.as_str().into(),
+	    self.asn.into(),
+	    self.cidr.clone().into(),
+	    self.approved.into(),
+	]
+    }
+}
+
+impl Record for Candidate {
+    fn columns() -> Vec<&'static str> {
+	vec!["domain", "cname", "service", "reason"]
+    }
+
+    fn cells(&self) -> Vec<Cell> {
+	let reason = match &self.reason {
+	    Reason::Unresolved => "does not resolve".to_string(),
+	    Reason::Fingerprint(fingerprint) => format!("serves \"{}\"", fingerprint),
+	};
+	vec![
+	    self.domain.as_str().into(),
+	    self.cname.as_str().into(),
+	    self.service.as_str().into(),
+	    reason.into(),
+	]
+    }
+}
+
+impl Record for ScanEntry {
+    fn columns() -> Vec<&'static str> {
+	vec!["id", "tool", "started", "imported", "hosts", "services", "domains", "args"]
+    }
+
+    fn cells(&self) -> Vec<Cell> {
+	vec![
+	    self.id.into(),
+	    self.tool.as_str().into(),
+	    self.started.clone().into(),
+	    self.imported.as_str().into(),
+	    self.hosts.into(),
+	    self.services.into(),
+	    self.domains.into(),
+	    self.args.clone().into(),
+	]
+    }
+}
+
+impl Record for HistoryEntry {
+    fn columns() -> Vec<&'static str> {
+	vec!["time", "entity", "target", "column", "old", "new", "cause"]
+    }
+
+    fn cells(&self) -> Vec<Cell> {
+	vec![
+	    self.time.as_str().into(),
+	    self.entity.as_str().into(),
+	    self.target.as_str().into(),
+	    self.column.as_str().into(),
+	    self.old_value.clone().into(),
+	    self.new_value.clone().into(),
+	    self.cause.as_str().into(),
+	]
+    }
+}
+
+impl Record for DiffEntry {
+    fn columns() -> Vec<&'static str> {
+	vec!["change", "ip", "port", "protocol", "domain", "old", "new"]
+    }
+
+    fn cells(&self) -> Vec<Cell> {
+	vec![
+	    self.change.as_str().into(),
+	    self.ip.clone().into(),
+	    self.port.into(),
+	    self.protocol.clone().into(),
+	    self.domain.clone().into(),
+	    self.old.clone().into(),
+	    self.new.clone().into(),
+	]
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+
+    fn listing() -> Listing {
+	let mut listing = Listing::new(&["ip", "port", "product", "ssl", "notes"]);
+	listing.push(vec!["10.0.0.1".into(), 80u16.into(), "Apache, \"httpd\"".into(), false.into(), vec!["note 1 by alice at 2026-10-01 12:00:00: a | b".to_string()].into()]);
+	listing.push(vec!["2001:db8::1".into(), 443u16.into(), Cell::Null, true.into(), Cell::Lines(vec![])]);
+	listing
+    }
+
+    fn written(listing: &Listing, format: Format) -> String {
+	let mut out = vec![];
+	listing.write(format, &mut out).unwrap();
+	String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn write_formats() {
+	let listing = listing();
+	assert!(written(&listing, Format::Table) == "\
+ip           port  product          ssl
+10.0.0.1     80    Apache, \"httpd\"  false
+    note 1 by alice at 2026-10-01 12:00:00: a | b
+2001:db8::1  443                    true
+");
+	assert!(written(&listing, Format::Csv) == "\
+ip,port,product,ssl,notes\r
+10.0.0.1,80,\"Apache, \"\"httpd\"\"\",false,note 1 by alice at 2026-10-01 12:00:00: a | b\r
+2001:db8::1,443,,true,\r
+");
+	assert!(written(&listing, Format::Jsonl) == r#"{"ip":"10.0.0.1","port":80,"product":"Apache, \"httpd\"","ssl":false,"notes":["note 1 by alice at 2026-10-01 12:00:00: a | b"]}
+{"ip":"2001:db8::1","port":443,"product":null,"ssl":true,"notes":[]}
+"#);
+	let json: serde_json::Value = serde_json::from_str(&written(&listing, Format::Json)).unwrap();
+	assert!(json[1]["port"] == 443);
+	assert!(written(&listing, Format::Markdown) == "\
+| ip | port | product | ssl | notes |
+| --- | --- | --- | --- | --- |
+| 10.0.0.1 | 80 | Apache, \"httpd\" | false | note 1 by alice at 2026-10-01 12:00:00: a \\| b |
+| 2001:db8::1 | 443 |  | true |  |
+");
+
+	let mut targets = Listing::new(&["target"]);
+	targets.push(vec!["10.0.0.1".into()]);
+	assert!(written(&targets, Format::Table) == "10.0.0.1\n");
+	assert!(written(&targets, Format::Csv) == "target\r\n10.0.0.1\r\n");
+	assert!(written(&Listing::new(&["target"]), Format::Json) == "[\n]\n");
+	assert!(Format::parse("xml").is_err());
+    }
+}
@@ -2,6 +2,8 @@ use serde::{Deserialize};
 use serde_json::from_str;
 use std::io::{Read, BufReader, BufRead};
 
+use super::dns::*;
+
 
 type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
 
@@ -19,8 +21,7 @@ impl Amass {
 	// does not work for some reason
 	let reader = BufReader::new(reader);
 	let entries: Vec<AmassEntry> = reader.lines()
-	    .into_iter()
-	    .flatten()
+	    .map_while(std::result::Result::ok)
 	    .flat_map(|line| from_str(&line))
 	    .collect();
 	Ok(Self {
@@ -40,6 +41,15 @@ pub struct AmassEntry {
     pub sources: Vec<String>,
 }
 
+impl AmassEntry {
+    /// amass only reports the addresses a name resolved to, as A and AAAA records.
+    pub fn records(&self) -> Vec<DnsRecord> {
+	self.addresses.iter()
+	    .map(|addr| DnsRecord::address(&self.name, &addr.ip, None))
+	    .collect()
+    }
+}
+
 #[derive(Debug, Deserialize)]
 pub struct Address {
     pub ip: String,
@@ -69,7 +79,7 @@ mod tests {
      #[test]
     fn parse_amass_address() {
 	let addr_json = r#"{"ip":"74.114.154.18","cidr":"74.114.152.0/22","asn":2635,"desc":"AUTOMATTIC - Automattic, Inc"}"#;
-	let addr: Address = from_str(&addr_json).unwrap();
+	let addr: Address = from_str(addr_json).unwrap();
 
 	assert!(addr.ip == "74.114.154.18");
     }
@@ -80,8 +90,9 @@ mod tests {
 	let entry_json = r#"{"name":"1.thumbs.4chan.org","domain":"4chan.org","addresses":[{"ip":"104.19.129.108","cidr":"104.16.0.0/14","asn":13335,"desc":"CLOUDFLARENET - Cloudflare, Inc."},{"ip":"104.19.128.108","cidr":"104.16.0.0/14","asn":13335,"desc":"CLOUDFLARENET - Cloudflare, Inc."}],"tag":"api","sources":["AlienVault"]}"#;
 
 
-	let entry: AmassEntry = from_str(&entry_json).unwrap();
+	let entry: AmassEntry = from_str(entry_json).unwrap();
 	assert!(entry.name == "1.thumbs.4chan.org");
+	assert!(entry.records()[0] == DnsRecord::new("1.thumbs.4chan.org", "A", "104.19.129.108", None));
 
     }
 
@@ -89,7 +100,7 @@ mod tests {
     fn parse_amass_entries() {
 
 	let entries: Vec<AmassEntry> = AMASS_JSON.lines()
-	    .map(|line| from_str(&line).unwrap())
+	    .map(|line| from_str(line).unwrap())
 	    .collect(); 
 	assert!(entries.len() == 3);
 	assert!(entries[2].name == "4chan.org");
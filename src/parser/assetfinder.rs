@@ -0,0 +1,45 @@
+use std::io::{Read, BufReader, BufRead};
+
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+
+// assetfinder --subs-only example.com > assetfinder.txt
+//
+// Just the names, one per line, without addresses or sources.
+#[derive(Debug)]
+pub struct Assetfinder {
+    pub names: Vec<String>,
+}
+
+impl Assetfinder {
+    pub fn new<R: Read>(reader: R) -> Result<Self> {
+	let reader = BufReader::new(reader);
+	let mut names = vec![];
+	for line in reader.lines() {
+	    let line = line?;
+	    let name = line.trim();
+	    if !name.is_empty() {
+		names.push(name.to_string());
+	    }
+	}
+	Ok(Self {
+	    names,
+	})
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+
+    #[test]
+    fn parse_assetfinder_new() {
+	let assetfinder = Assetfinder::new("www.example.com\n\nmail.example.com\r\n".as_bytes());
+	assert!(assetfinder.is_ok());
+	let assetfinder = assetfinder.unwrap();
+	assert!(assetfinder.names == vec!["www.example.com", "mail.example.com"]);
+    }
+}
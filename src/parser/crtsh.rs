@@ -0,0 +1,114 @@
+use std::io::Read;
+
+use serde::{Deserialize};
+use serde_json::from_reader;
+
+use super::tls::*;
+
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+
+// curl 'https://crt.sh/?q=%25.example.com&output=json' > crtsh.json
+//
+// One entry per logged certificate (precertificates included), all names of
+// the certificate newline separated in name_value.
+#[derive(Debug)]
+pub struct Crtsh {
+    pub entries: Vec<CrtshEntry>,
+}
+
+impl Crtsh {
+    pub fn new<R: Read>(reader: R) -> Result<Self> {
+	Ok(Self {
+	    entries: from_reader(reader)?,
+	})
+    }
+}
+
+// {"issuer_ca_id":183267,"issuer_name":"C=US, O=Let's Encrypt, CN=R3","common_name":"www.example.com",
+//  "name_value":"example.com\nwww.example.com","id":5815180186,"entry_timestamp":"2021-12-10T01:23:45.678",
+//  "not_before":"2021-12-10T00:00:00","not_after":"2022-03-10T23:59:59","serial_number":"03a1f2..."}
+#[derive(Debug, Deserialize)]
+pub struct CrtshEntry {
+    pub id: u64,
+    pub issuer_name: String,
+    pub common_name: Option<String>,
+    pub name_value: String,
+    pub not_before: Option<String>,
+    pub not_after: Option<String>,
+    pub serial_number: Option<String>,
+}
+
+impl CrtshEntry {
+    /// Lowercase names the certificate is valid for, wildcards included.
+    pub fn names(&self) -> Vec<String> {
+	let mut names: Vec<String> = self.name_value.lines()
+	    .chain(self.common_name.as_deref())
+	    .map(|name| name.trim().to_lowercase())
+	    // crt.sh lists email addresses of S/MIME certificates too
+	    .filter(|name| !name.is_empty() && !name.contains('@') && !name.contains(' '))
+	    .collect();
+	names.sort();
+	names.dedup();
+	names
+    }
+
+    pub fn certificate(&self) -> Certificate {
+	Certificate {
+	    subject: self.common_name.clone(),
+	    issuer: Some(issuer_common_name(&self.issuer_name).to_string()),
+	    serial: self.serial_number.as_ref().map(|serial| serial.to_lowercase()),
+	    not_before: self.not_before.as_deref().map(crtsh_date),
+	    not_after: self.not_after.as_deref().map(crtsh_date),
+	    sans: self.names(),
+	    ..Default::default()
+	}
+    }
+}
+
+/// "C=US, O=Let's Encrypt, CN=R3" -> "R3", the whole DN if it has no CN.
+fn issuer_common_name(dn: &str) -> &str {
+    dn.split(", ")
+	.find_map(|part| part.strip_prefix("CN="))
+	.unwrap_or(dn)
+}
+
+// "2021-12-10T00:00:00" -> "2021-12-10 00:00:00"
+fn crtsh_date(date: &str) -> String {
+    date.replacen('T', " ", 1)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::from_str;
+
+
+    const CRTSH_JSON: &str = r#"[{"issuer_ca_id":183267,"issuer_name":"C=US, O=Let's Encrypt, CN=R3","common_name":"www.example.com","name_value":"example.com\nwww.example.com","id":5815180186,"entry_timestamp":"2021-12-10T01:23:45.678","not_before":"2021-12-10T00:00:00","not_after":"2022-03-10T23:59:59","serial_number":"03a1f2b4c5d6e7f8091a2b3c4d5e6f708192"},
+{"issuer_ca_id":183267,"issuer_name":"C=US, O=Let's Encrypt, CN=R3","common_name":"*.dev.example.com","name_value":"*.dev.example.com\ndev.example.com","id":5815180190,"entry_timestamp":"2021-12-11T08:00:00.000","not_before":"2021-12-11T00:00:00","not_after":"2022-03-11T23:59:59","serial_number":"04b2"}]"#;
+
+
+    #[test]
+    fn parse_crtsh_entry() {
+	let json = r#"{"issuer_ca_id":1,"issuer_name":"C=US, O=DigiCert Inc, CN=DigiCert TLS RSA SHA256 2020 CA1","common_name":"WWW.example.com","name_value":"www.example.com\nhostmaster@example.com\nexample.com","id":1,"entry_timestamp":null,"not_before":"2021-12-10T00:00:00","not_after":"2022-12-09T23:59:59","serial_number":"0FAA63"}"#;
+	let entry: CrtshEntry = from_str(json).unwrap();
+	assert!(entry.names() == vec!["example.com", "www.example.com"]);
+
+	let certificate = entry.certificate();
+	assert!(certificate.issuer.as_deref() == Some("DigiCert TLS RSA SHA256 2020 CA1"));
+	assert!(certificate.serial.as_deref() == Some("0faa63"));
+	assert!(certificate.not_after.as_deref() == Some("2022-12-09 23:59:59"));
+    }
+
+    #[test]
+    fn parse_crtsh_new() {
+	let crtsh = Crtsh::new(CRTSH_JSON.as_bytes());
+	assert!(crtsh.is_ok());
+	let crtsh = crtsh.unwrap();
+	assert!(crtsh.entries.len() == 2);
+	assert!(crtsh.entries[1].names() == vec!["*.dev.example.com", "dev.example.com"]);
+	assert!(crtsh.entries[1].certificate().issuer.as_deref() == Some("R3"));
+    }
+}
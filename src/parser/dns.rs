@@ -0,0 +1,60 @@
+// One DNS answer as a row of the dns_record table: the queried name, the
+// record type in upper case, the answer and its TTL if the tool had one.
+//
+// Names and host valued answers (CNAME, MX, NS, SRV targets) are lowercase
+// without the trailing dot, TXT values are kept as they were.
+#[derive(Debug, PartialEq, Eq)]
+pub struct DnsRecord {
+    pub name: String,
+    pub kind: String,
+    pub value: String,
+    pub ttl: Option<u32>,
+}
+
+impl DnsRecord {
+    pub fn new(name: &str, kind: &str, value: &str, ttl: Option<u32>) -> Self {
+	let kind = kind.to_uppercase();
+	let value = match kind.as_str() {
+	    "TXT" => value.to_string(),
+	    _ => normalize_name(value),
+	};
+	Self {
+	    name: normalize_name(name),
+	    kind,
+	    value,
+	    ttl,
+	}
+    }
+
+    /// A or AAAA record, depending on the address family.
+    pub fn address(name: &str, ip: &str, ttl: Option<u32>) -> Self {
+	let kind = if ip.contains(':') { "AAAA" } else { "A" };
+	Self::new(name, kind, ip, ttl)
+    }
+}
+
+/// "WWW.Example.com." -> "www.example.com"
+pub fn normalize_name(name: &str) -> String {
+    name.trim().trim_end_matches('.').to_lowercase()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+
+    #[test]
+    fn dns_record_new() {
+	let record = DnsRecord::new("WWW.example.com.", "cname", "Example.COM.", Some(300));
+	assert!(record.name == "www.example.com");
+	assert!(record.kind == "CNAME");
+	assert!(record.value == "example.com");
+
+	let record = DnsRecord::new("example.com", "TXT", "v=spf1 include:_spf.Example.com -all", None);
+	assert!(record.value == "v=spf1 include:_spf.Example.com -all");
+
+	assert!(DnsRecord::address("example.com", "2606:2800:220:1:248:1893:25c8:1946", None).kind == "AAAA");
+	assert!(DnsRecord::address("example.com", "93.184.216.34", None).kind == "A");
+    }
+}
@@ -0,0 +1,119 @@
+use std::io::{Read, BufReader, BufRead};
+
+use serde::{Deserialize};
+use serde_json::from_str;
+
+use super::dns::*;
+
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+
+// dnsx -l names.txt -a -aaaa -cname -mx -txt -ns -resp -json -o dnsx.json
+//
+// One JSON object per queried name, only the record types that were
+// asked for and answered are present.
+#[derive(Debug)]
+pub struct Dnsx {
+    pub entries: Vec<DnsxEntry>,
+}
+
+impl Dnsx {
+    pub fn new<R: Read>(reader: R) -> Result<Self> {
+	let reader = BufReader::new(reader);
+	let mut entries = vec![];
+	for line in reader.lines() {
+	    let line = line?;
+	    if !line.trim().is_empty() {
+		entries.push(from_str(&line)?);
+	    }
+	}
+	Ok(Self {
+	    entries,
+	})
+    }
+}
+
+// {"host":"www.example.com","resolver":["1.1.1.1:53"],"a":["93.184.216.34"],
+//  "aaaa":["2606:2800:220:1:248:1893:25c8:1946"],"cname":["example.com"],
+//  "ttl":3600,"status_code":"NOERROR","timestamp":"2022-01-31T10:00:00.000Z"}
+#[derive(Debug, Deserialize)]
+pub struct DnsxEntry {
+    pub host: String,
+    #[serde(default)]
+    pub a: Vec<String>,
+    #[serde(default)]
+    pub aaaa: Vec<String>,
+    #[serde(default)]
+    pub cname: Vec<String>,
+    #[serde(default)]
+    pub mx: Vec<String>,
+    #[serde(default)]
+    pub txt: Vec<String>,
+    #[serde(default)]
+    pub ns: Vec<String>,
+    #[serde(default)]
+    pub srv: Vec<String>,
+    pub ttl: Option<u32>,
+    pub status_code: Option<String>,
+}
+
+impl DnsxEntry {
+    /// A and AAAA answers
+    pub fn addresses(&self) -> impl Iterator<Item = &String> {
+	self.a.iter().chain(self.aaaa.iter())
+    }
+
+    pub fn records(&self) -> Vec<DnsRecord> {
+	let answers = [
+	    ("A", &self.a),
+	    ("AAAA", &self.aaaa),
+	    ("CNAME", &self.cname),
+	    ("MX", &self.mx),
+	    ("TXT", &self.txt),
+	    ("NS", &self.ns),
+	    ("SRV", &self.srv),
+	];
+	answers.iter()
+	    .flat_map(|(kind, values)| values.iter().map(move |value| DnsRecord::new(&self.host, kind, value, self.ttl)))
+	    .collect()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+
+    const DNSX_JSON: &str = r#"{"host":"www.example.com","resolver":["1.1.1.1:53"],"a":["93.184.216.34"],"aaaa":["2606:2800:220:1:248:1893:25c8:1946"],"cname":["example.com"],"ttl":3600,"status_code":"NOERROR","timestamp":"2022-01-31T10:00:00.000Z"}
+{"host":"example.com","resolver":["1.1.1.1:53"],"a":["93.184.216.34"],"mx":["mail.example.com"],"txt":["v=spf1 -all"],"ns":["a.iana-servers.net","b.iana-servers.net"],"ttl":86400,"status_code":"NOERROR","timestamp":"2022-01-31T10:00:00.000Z"}
+{"host":"old.example.com","resolver":["1.1.1.1:53"],"status_code":"NXDOMAIN","timestamp":"2022-01-31T10:00:00.000Z"}
+"#;
+
+
+    #[test]
+    fn parse_dnsx_entry() {
+	let entry: DnsxEntry = from_str(r#"{"host":"www.example.com","a":["93.184.216.34"],"aaaa":["2606:2800:220:1:248:1893:25c8:1946"],"cname":["example.com"]}"#).unwrap();
+	assert!(entry.addresses().count() == 2);
+	assert!(entry.cname == vec!["example.com"]);
+	assert!(entry.mx.is_empty());
+    }
+
+    #[test]
+    fn parse_dnsx_new() {
+	let dnsx = Dnsx::new(DNSX_JSON.as_bytes());
+	assert!(dnsx.is_ok());
+	let dnsx = dnsx.unwrap();
+	assert!(dnsx.entries.len() == 3);
+	assert!(dnsx.entries[1].ns.len() == 2);
+	assert!(dnsx.entries[1].txt[0] == "v=spf1 -all");
+	assert!(dnsx.entries[2].addresses().next().is_none());
+	assert!(dnsx.entries[2].status_code.as_deref() == Some("NXDOMAIN"));
+
+	let records = dnsx.entries[1].records();
+	assert!(records.len() == 5);
+	assert!(records[1] == DnsRecord::new("example.com", "MX", "mail.example.com", Some(86400)));
+	assert!(records[2].kind == "TXT");
+    }
+}
@@ -0,0 +1,115 @@
+use std::collections::HashMap;
+use std::io::{Read, BufReader, BufRead};
+
+use serde::{Deserialize};
+use serde_json::from_str;
+
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+
+// feroxbuster -u http://10.10.10.10 --json -o ferox.json
+//
+// One JSON object per line, tagged by "type". Only "configuration" and
+// "response" lines carry data we keep, "statistics" and friends are skipped.
+#[derive(Debug)]
+pub struct Feroxbuster {
+    pub wordlist: Option<String>,
+    pub entries: Vec<FeroxResponse>,
+}
+
+impl Feroxbuster {
+    pub fn new<R: Read>(reader: R) -> Result<Self> {
+	let reader = BufReader::new(reader);
+	let mut wordlist = None;
+	let mut entries = vec![];
+	for line in reader.lines() {
+	    let line = line?;
+	    if line.trim().is_empty() {
+		continue;
+	    }
+	    match from_str(&line)? {
+		FeroxLine::Configuration(config) => wordlist = Some(config.wordlist),
+		FeroxLine::Response(response) => entries.push(response),
+		FeroxLine::Other => continue,
+	    }
+	}
+	Ok(Self {
+	    wordlist,
+	    entries,
+	})
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum FeroxLine {
+    Configuration(Configuration),
+    Response(FeroxResponse),
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Configuration {
+    pub wordlist: String,
+}
+
+// {"type":"response","url":"http://10.10.10.10/admin","original_url":"http://10.10.10.10",
+//  "path":"/admin","wildcard":false,"status":301,"method":"GET","content_length":178,
+//  "line_count":7,"word_count":11,"headers":{"location":"http://10.10.10.10/admin/"},
+//  "extension":""}
+#[derive(Debug, Deserialize)]
+pub struct FeroxResponse {
+    pub url: String,
+    pub path: String,
+    pub status: u32,
+    pub content_length: u64,
+    pub line_count: u64,
+    pub word_count: u64,
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+}
+
+impl FeroxResponse {
+    pub fn location(&self) -> Option<&str> {
+	self.headers.get("location").map(|location| location.as_str())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+
+    const FEROX_JSON: &str = r#"{"type":"configuration","wrapped_type":"Configuration","wordlist":"/usr/share/seclists/Discovery/Web-Content/raft-medium-directories.txt","target_url":"http://10.10.10.10","threads":50}
+{"type":"response","url":"http://10.10.10.10/admin","original_url":"http://10.10.10.10","path":"/admin","wildcard":false,"status":301,"method":"GET","content_length":178,"line_count":7,"word_count":11,"headers":{"location":"http://10.10.10.10/admin/","content-type":"text/html"},"extension":""}
+{"type":"response","url":"http://10.10.10.10/admin/login.php","original_url":"http://10.10.10.10","path":"/admin/login.php","wildcard":false,"status":200,"method":"GET","content_length":1532,"line_count":42,"word_count":120,"headers":{"content-type":"text/html"},"extension":""}
+{"type":"statistics","timeouts":0,"requests":4500,"successes":2}"#;
+
+
+    #[test]
+    fn parse_ferox_response() {
+	let json = r#"{"type":"response","url":"http://10.10.10.10/admin","original_url":"http://10.10.10.10","path":"/admin","wildcard":false,"status":301,"method":"GET","content_length":178,"line_count":7,"word_count":11,"headers":{"location":"http://10.10.10.10/admin/"},"extension":""}"#;
+	match from_str(json).unwrap() {
+	    FeroxLine::Response(response) => {
+		assert!(response.path == "/admin");
+		assert!(response.status == 301);
+		assert!(response.location() == Some("http://10.10.10.10/admin/"));
+	    },
+	    _ => panic!("expected a response line"),
+	}
+    }
+
+    #[test]
+    fn parse_ferox_new() {
+	let ferox = Feroxbuster::new(FEROX_JSON.as_bytes());
+	assert!(ferox.is_ok());
+	let ferox = ferox.unwrap();
+	assert!(ferox.entries.len() == 2);
+	assert!(ferox.entries[1].url == "http://10.10.10.10/admin/login.php");
+	assert!(ferox.entries[1].location().is_none());
+	assert!(ferox.wordlist.unwrap().ends_with("raft-medium-directories.txt"));
+    }
+}
@@ -0,0 +1,98 @@
+use std::io::Read;
+
+use serde::{Deserialize};
+use serde_json::from_reader;
+
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+// ffuf -u http://10.10.10.10/FUZZ -w words.txt -of json -o ffuf.json
+//
+// {"commandline":"ffuf -u http://10.10.10.10/FUZZ ...",
+//  "time":"2022-01-30T20:06:51+01:00",
+//  "results":[...],
+//  "config":{...}}
+#[derive(Debug, Deserialize)]
+pub struct Ffuf {
+    pub commandline: String,
+    pub time: String,
+    pub results: Vec<FfufResult>,
+    pub config: Option<Config>,
+}
+
+impl Ffuf {
+    pub fn new<R: Read>(reader: R) -> Result<Self> {
+	Ok(from_reader(reader)?)
+    }
+
+    /// Wordlist path of the first input provider, if ffuf recorded one.
+    pub fn wordlist(&self) -> Option<&str> {
+	self.config.as_ref()?
+	    .inputproviders
+	    .iter()
+	    .find(|input| input.name == "wordlist")
+	    .map(|input| input.value.as_str())
+    }
+}
+
+// {"input":{"FUZZ":"admin"},"position":3,"status":301,"length":178,"words":6,
+//  "lines":8,"content-type":"text/html","redirectlocation":"http://10.10.10.10/admin/",
+//  "resultfile":"","url":"http://10.10.10.10/admin","host":"10.10.10.10"}
+#[derive(Debug, Deserialize)]
+pub struct FfufResult {
+    pub url: String,
+    pub status: u32,
+    pub length: u64,
+    pub words: u64,
+    pub lines: u64,
+    #[serde(rename = "content-type", default)]
+    pub content_type: String,
+    #[serde(rename = "redirectlocation", default)]
+    pub redirect_location: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Config {
+    pub url: String,
+    #[serde(default)]
+    pub inputproviders: Vec<InputProvider>,
+}
+
+// {"name":"wordlist","keyword":"FUZZ","value":"/usr/share/wordlists/dirb/common.txt"}
+#[derive(Debug, Deserialize)]
+pub struct InputProvider {
+    pub name: String,
+    pub keyword: String,
+    pub value: String,
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::from_str;
+
+
+    const FFUF_JSON: &str = r#"{"commandline":"ffuf -u http://10.10.10.10/FUZZ -w /usr/share/wordlists/dirb/common.txt -of json -o ffuf.json","time":"2022-01-30T20:06:51+01:00","results":[{"input":{"FUZZ":"admin"},"position":3,"status":301,"length":178,"words":6,"lines":8,"content-type":"text/html","redirectlocation":"http://10.10.10.10/admin/","resultfile":"","url":"http://10.10.10.10/admin","host":"10.10.10.10"},{"input":{"FUZZ":"index.html"},"position":7,"status":200,"length":10918,"words":3499,"lines":376,"content-type":"text/html","redirectlocation":"","resultfile":"","url":"http://10.10.10.10/index.html","host":"10.10.10.10"},{"input":{"FUZZ":"server-status"},"position":9,"status":403,"length":277,"words":20,"lines":10,"content-type":"text/html; charset=iso-8859-1","redirectlocation":"","resultfile":"","url":"http://10.10.10.10/server-status","host":"10.10.10.10"}],"config":{"autocalibration":false,"url":"http://10.10.10.10/FUZZ","method":"GET","inputproviders":[{"name":"wordlist","keyword":"FUZZ","value":"/usr/share/wordlists/dirb/common.txt"}],"outputfile":"ffuf.json","outputformat":"json","threads":40}}"#;
+
+
+    #[test]
+    fn parse_ffuf_result() {
+	let json = r#"{"input":{"FUZZ":"admin"},"position":3,"status":301,"length":178,"words":6,"lines":8,"content-type":"text/html","redirectlocation":"http://10.10.10.10/admin/","resultfile":"","url":"http://10.10.10.10/admin","host":"10.10.10.10"}"#;
+	let result: FfufResult = from_str(json).unwrap();
+	assert!(result.url == "http://10.10.10.10/admin");
+	assert!(result.status == 301);
+	assert!(result.redirect_location == "http://10.10.10.10/admin/");
+    }
+
+    #[test]
+    fn parse_ffuf_new() {
+	let ffuf = Ffuf::new(FFUF_JSON.as_bytes());
+	assert!(ffuf.is_ok());
+	let ffuf = ffuf.unwrap();
+	assert!(ffuf.results.len() == 3);
+	assert!(ffuf.results[2].status == 403);
+	assert!(ffuf.results[2].words == 20);
+	assert!(ffuf.wordlist() == Some("/usr/share/wordlists/dirb/common.txt"));
+    }
+}
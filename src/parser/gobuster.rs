@@ -0,0 +1,137 @@
+use std::io::{Read, BufReader, BufRead};
+
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+
+// gobuster dir -u http://10.10.10.10 -w common.txt -o gobuster.txt
+//
+// gobuster has no structured output format, the -o file only contains the
+// result lines. If the tool output was saved with tee the banner is there as
+// well and tells us the target url and wordlist:
+//
+// [+] Url:                     http://10.10.10.10
+// [+] Wordlist:                /usr/share/wordlists/dirb/common.txt
+// /admin                (Status: 301) [Size: 178] [--> http://10.10.10.10/admin/]
+// /index.html           (Status: 200) [Size: 10918]
+#[derive(Debug)]
+pub struct Gobuster {
+    pub url: Option<String>,
+    pub wordlist: Option<String>,
+    pub entries: Vec<GobusterEntry>,
+}
+
+impl Gobuster {
+    pub fn new<R: Read>(reader: R) -> Result<Self> {
+	let reader = BufReader::new(reader);
+	let mut url = None;
+	let mut wordlist = None;
+	let mut entries = vec![];
+	for line in reader.lines() {
+	    let line = line?;
+	    // progress updates are written over with \r, only the last part is a real line
+	    let line = line.rsplit('\r').next().unwrap_or("").trim();
+	    if let Some(banner) = line.strip_prefix("[+] ") {
+		if let Some(value) = banner.strip_prefix("Url:") {
+		    url = Some(value.trim().to_string());
+		} else if let Some(value) = banner.strip_prefix("Wordlist:") {
+		    wordlist = Some(value.trim().to_string());
+		}
+	    } else if let Some(entry) = GobusterEntry::parse(line) {
+		entries.push(entry);
+	    }
+	}
+	Ok(Self {
+	    url,
+	    wordlist,
+	    entries,
+	})
+    }
+}
+
+// /admin                (Status: 301) [Size: 178] [--> http://10.10.10.10/admin/]
+//
+// path is an absolute url instead when gobuster runs with -e (expanded mode).
+#[derive(Debug)]
+pub struct GobusterEntry {
+    pub path: String,
+    pub status: u32,
+    pub size: Option<u64>,
+    pub redirect: Option<String>,
+}
+
+impl GobusterEntry {
+    pub fn parse(line: &str) -> Option<Self> {
+	let (path, rest) = line.split_once("(Status: ")?;
+	let (status, rest) = rest.split_once(')')?;
+	let size = Self::field(rest, "[Size: ").and_then(|size| size.parse().ok());
+	let redirect = Self::field(rest, "[--> ").map(|redirect| redirect.to_string());
+	Some(Self {
+	    path: path.trim().to_string(),
+	    status: status.trim().parse().ok()?,
+	    size,
+	    redirect,
+	})
+    }
+
+    fn field<'a>(line: &'a str, prefix: &str) -> Option<&'a str> {
+	let (_, value) = line.split_once(prefix)?;
+	let (value, _) = value.split_once(']')?;
+	Some(value.trim())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+
+    const GOBUSTER_OUT: &str = r#"===============================================================
+Gobuster v3.1.0
+by OJ Reeves (@TheColonial) & Christian Mehlmauer (@firefart)
+===============================================================
+[+] Url:                     http://10.10.10.10
+[+] Method:                  GET
+[+] Threads:                 10
+[+] Wordlist:                /usr/share/wordlists/dirb/common.txt
+[+] Negative Status codes:   404
+===============================================================
+2022/01/30 20:06:51 Starting gobuster in directory enumeration mode
+===============================================================
+/admin                (Status: 301) [Size: 178] [--> http://10.10.10.10/admin/]
+/index.html           (Status: 200) [Size: 10918]
+/server-status        (Status: 403) [Size: 277]
+===============================================================
+2022/01/30 20:07:12 Finished
+==============================================================="#;
+
+
+    #[test]
+    fn parse_gobuster_entry() {
+	let entry = GobusterEntry::parse("/admin                (Status: 301) [Size: 178] [--> http://10.10.10.10/admin/]").unwrap();
+	assert!(entry.path == "/admin");
+	assert!(entry.status == 301);
+	assert!(entry.size == Some(178));
+	assert!(entry.redirect.unwrap() == "http://10.10.10.10/admin/");
+
+	// gobuster v2 and expanded mode
+	let entry = GobusterEntry::parse("http://10.10.10.10/icons (Status: 403)").unwrap();
+	assert!(entry.path == "http://10.10.10.10/icons");
+	assert!(entry.size.is_none());
+
+	assert!(GobusterEntry::parse("[+] Threads:                 10").is_none());
+    }
+
+    #[test]
+    fn parse_gobuster_new() {
+	let gobuster = Gobuster::new(GOBUSTER_OUT.as_bytes());
+	assert!(gobuster.is_ok());
+	let gobuster = gobuster.unwrap();
+	assert!(gobuster.url.unwrap() == "http://10.10.10.10");
+	assert!(gobuster.wordlist.unwrap() == "/usr/share/wordlists/dirb/common.txt");
+	assert!(gobuster.entries.len() == 3);
+	assert!(gobuster.entries[2].path == "/server-status");
+	assert!(gobuster.entries[2].status == 403);
+    }
+}
@@ -1,2 +1,18 @@
 pub mod nmap;
 pub mod amass;
+pub mod ffuf;
+pub mod feroxbuster;
+pub mod gobuster;
+pub mod nessus;
+pub mod openvas;
+pub mod tls;
+pub mod testssl;
+pub mod sslscan;
+pub mod dns;
+pub mod subfinder;
+pub mod assetfinder;
+pub mod dnsx;
+pub mod zone;
+pub mod crtsh;
+pub mod secretsdump;
+pub mod potfile;
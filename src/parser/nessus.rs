@@ -0,0 +1,226 @@
+use std::io::Read;
+
+use serde::{Deserialize};
+use serde_xml_rs::from_reader;
+
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+// <NessusClientData_v2>
+//   <Policy>...</Policy>
+//   <Report name="external" xmlns:cm="http://www.nessus.org/cm">
+//     <ReportHost name="192.168.56.10">...</ReportHost>
+//   </Report>
+// </NessusClientData_v2>
+#[derive(Debug, Deserialize)]
+pub struct NessusClientData {
+    #[serde(rename = "Report")]
+    pub report: Report,
+}
+
+pub type Nessus = NessusClientData;
+impl Nessus {
+    pub fn new<R: Read>(reader: R) -> Result<Self> {
+	Ok(from_reader(reader)?)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Report {
+    pub name: String,
+    #[serde(rename = "ReportHost", default)]
+    pub hosts: Vec<ReportHost>,
+}
+
+// <ReportHost name="192.168.56.10">
+//   <HostProperties>
+//     <tag name="host-ip">192.168.56.10</tag>
+//     ...
+//   </HostProperties>
+//   <ReportItem ...>...</ReportItem>
+// </ReportHost>
+#[derive(Debug, Deserialize)]
+pub struct ReportHost {
+    pub name: String,
+    #[serde(rename = "HostProperties")]
+    pub properties: HostProperties,
+    #[serde(rename = "ReportItem", default)]
+    pub items: Vec<ReportItem>,
+}
+
+impl ReportHost {
+    /// The scanned address. Nessus names the host after the target as given
+    /// by the user, which can be a hostname, so prefer the host-ip tag.
+    pub fn ip(&self) -> &str {
+	self.properties.get("host-ip").unwrap_or(&self.name)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct HostProperties {
+    #[serde(rename = "tag", default)]
+    pub tags: Vec<Tag>,
+}
+
+impl HostProperties {
+    pub fn get(&self, name: &str) -> Option<&str> {
+	self.tags.iter()
+	    .find(|tag| tag.name == name)
+	    .map(|tag| tag.value.as_str())
+    }
+}
+
+// <tag name="operating-system">Linux Kernel 3.10 on CentOS Linux release 7</tag>
+#[derive(Debug, Deserialize)]
+pub struct Tag {
+    pub name: String,
+    #[serde(rename = "$value", default)]
+    pub value: String,
+}
+
+// <ReportItem port="443" svc_name="www" protocol="tcp" severity="2" pluginID="42873"
+//     pluginName="SSL Medium Strength Cipher Suites Supported (SWEET32)" pluginFamily="General">
+//   <cve>CVE-2016-2183</cve>
+//   <cvss3_base_score>7.5</cvss3_base_score>
+//   <cvss_base_score>5.0</cvss_base_score>
+//   <plugin_output>...</plugin_output>
+//   <risk_factor>Medium</risk_factor>
+//   <synopsis>The remote service supports the use of medium strength SSL ciphers.</synopsis>
+// </ReportItem>
+#[derive(Debug, Deserialize)]
+pub struct ReportItem {
+    pub port: u32,
+    pub svc_name: String,
+    pub protocol: String,
+    pub severity: u32,
+    #[serde(rename = "pluginID")]
+    pub plugin_id: String,
+    #[serde(rename = "pluginName")]
+    pub plugin_name: String,
+    #[serde(rename = "cve", default)]
+    pub cves: Vec<String>,
+    pub cvss3_base_score: Option<f64>,
+    pub cvss_base_score: Option<f64>,
+    pub synopsis: Option<String>,
+    pub plugin_output: Option<String>,
+}
+
+impl ReportItem {
+    /// Nessus severity 0-4 as the words used in the report.
+    pub fn severity_name(&self) -> &'static str {
+	match self.severity {
+	    0 => "info",
+	    1 => "low",
+	    2 => "medium",
+	    3 => "high",
+	    _ => "critical",
+	}
+    }
+
+    /// nmap style service name, Nessus has its own names for a few services.
+    pub fn service_name(&self) -> &str {
+	match self.svc_name.trim_end_matches('?') {
+	    "www" => "http",
+	    "cifs" => "microsoft-ds",
+	    "dns" => "domain",
+	    name => name,
+	}
+    }
+
+    pub fn cvss(&self) -> Option<f64> {
+	self.cvss3_base_score.or(self.cvss_base_score)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_xml_rs::from_str;
+
+
+    const NESSUS_XML: &str = r#"<?xml version="1.0" ?>
+<NessusClientData_v2>
+<Policy><policyName>external</policyName></Policy>
+<Report name="external" xmlns:cm="http://www.nessus.org/cm">
+<ReportHost name="192.168.56.10"><HostProperties>
+<tag name="HOST_END">Mon Jan 31 10:12:45 2022</tag>
+<tag name="operating-system">Linux Kernel 3.10 on CentOS Linux release 7</tag>
+<tag name="host-ip">192.168.56.10</tag>
+<tag name="host-fqdn">web01.example.com</tag>
+<tag name="netbios-name">WEB01</tag>
+<tag name="HOST_START">Mon Jan 31 10:01:02 2022</tag>
+</HostProperties>
+<ReportItem port="0" svc_name="general" protocol="tcp" severity="0" pluginID="19506" pluginName="Nessus Scan Information" pluginFamily="Settings">
+<plugin_output>Nessus version : 10.1.0</plugin_output>
+<risk_factor>None</risk_factor>
+<synopsis>This plugin displays information about the Nessus scan.</synopsis>
+</ReportItem>
+<ReportItem port="22" svc_name="ssh" protocol="tcp" severity="0" pluginID="22964" pluginName="Service Detection" pluginFamily="Service detection">
+<plugin_output>An SSH server is running on this port.</plugin_output>
+<risk_factor>None</risk_factor>
+<synopsis>The remote service could be identified.</synopsis>
+</ReportItem>
+<ReportItem port="443" svc_name="www" protocol="tcp" severity="2" pluginID="42873" pluginName="SSL Medium Strength Cipher Suites Supported (SWEET32)" pluginFamily="General">
+<cve>CVE-2016-2183</cve>
+<cvss3_base_score>7.5</cvss3_base_score>
+<cvss_base_score>5.0</cvss_base_score>
+<plugin_output>
+  Medium Strength Ciphers (&gt; 64-bit and &lt; 112-bit key, or 3DES)
+    DES-CBC3-SHA
+</plugin_output>
+<risk_factor>Medium</risk_factor>
+<synopsis>The remote service supports the use of medium strength SSL ciphers.</synopsis>
+</ReportItem>
+</ReportHost>
+</Report>
+</NessusClientData_v2>
+"#;
+
+
+    #[test]
+    fn parse_host_properties() {
+	let xml = r#"
+<HostProperties>
+<tag name="operating-system">Microsoft Windows Server 2016</tag>
+<tag name="netbios-name">DC01</tag>
+</HostProperties>
+"#;
+	let properties: HostProperties = from_str(xml).unwrap();
+	assert!(properties.get("netbios-name") == Some("DC01"));
+	assert!(properties.get("host-fqdn").is_none());
+    }
+
+    #[test]
+    fn parse_report_item() {
+	let xml = r#"
+<ReportItem port="443" svc_name="www" protocol="tcp" severity="2" pluginID="42873" pluginName="SSL Medium Strength Cipher Suites Supported (SWEET32)" pluginFamily="General">
+<cve>CVE-2016-2183</cve>
+<cve>CVE-2016-6329</cve>
+<cvss_base_score>5.0</cvss_base_score>
+<risk_factor>Medium</risk_factor>
+</ReportItem>
+"#;
+	let item: ReportItem = from_str(xml).unwrap();
+	assert!(item.port == 443);
+	assert!(item.service_name() == "http");
+	assert!(item.severity_name() == "medium");
+	assert!(item.cves == vec!["CVE-2016-2183", "CVE-2016-6329"]);
+	assert!(item.cvss() == Some(5.0));
+	assert!(item.synopsis.is_none());
+    }
+
+    #[test]
+    fn parse_nessus_obj() {
+	let nessus = Nessus::new(NESSUS_XML.as_bytes());
+	assert!(nessus.is_ok());
+	let nessus = nessus.unwrap();
+	assert!(nessus.report.hosts.len() == 1);
+	let host = &nessus.report.hosts[0];
+	assert!(host.ip() == "192.168.56.10");
+	assert!(host.properties.get("host-fqdn") == Some("web01.example.com"));
+	assert!(host.items.len() == 3);
+	assert!(host.items[2].cvss() == Some(7.5));
+	assert!(host.items[2].plugin_output.as_ref().unwrap().contains("DES-CBC3-SHA"));
+    }
+}
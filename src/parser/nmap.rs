@@ -3,6 +3,8 @@ use std::io::Read;
 use serde::{Deserialize};
 use serde_xml_rs::from_reader;
 
+use super::tls::Certificate;
+
 
 type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
 
@@ -29,6 +31,18 @@ impl Nmap {
     pub fn new<R: Read>(reader: R) -> Result<Self> {
 	Ok(from_reader(reader)?)
     }
+
+    /// Ports the scan probed, from its scaninfo elements. Empty for scans
+    /// without any, like host discovery with -sn.
+    pub fn port_ranges(&self) -> Result<Vec<PortRange>> {
+	let mut ranges = vec![];
+	for element in &self.hosts {
+	    if let RunElement::ScanInfo(info) = element {
+		ranges.extend(info.port_ranges()?);
+	    }
+	}
+	Ok(ranges)
+    }
 }
 
 /*
@@ -57,8 +71,38 @@ pub enum RunElement {
     RunStats(RunStats),
 }
 
+// <scaninfo type="syn" protocol="tcp" numservices="1000" services="1,3-4,6-7,9"/>
 #[derive(Debug, Deserialize)]
-pub struct StructInfo {}
+pub struct StructInfo {
+    pub protocol: String,
+    #[serde(default)]
+    pub services: String,
+}
+
+// First and last port of a range a scan probed, both included.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PortRange {
+    pub protocol: String,
+    pub first: u16,
+    pub last: u16,
+}
+
+impl StructInfo {
+    fn port_ranges(&self) -> Result<Vec<PortRange>> {
+	let port = |value: &str| -> Result<u16> {
+	    value.parse().map_err(|_| format!("invalid port {:?} in the scaninfo services", value).into())
+	};
+	let mut ranges = vec![];
+	for part in self.services.split(',').filter(|part| !part.is_empty()) {
+	    let (first, last) = match part.split_once('-') {
+		Some((first, last)) => (port(first)?, port(last)?),
+		None => (port(part)?, port(part)?),
+	    };
+	    ranges.push(PortRange { protocol: self.protocol.clone(), first, last });
+	}
+	Ok(ranges)
+    }
+}
 
 #[derive(Debug, Deserialize)]
 pub struct Verbose {}
@@ -93,8 +137,34 @@ pub struct Host {
     pub starttime: u32,
     pub endtime: u32,
     pub address: Address,
+    #[serde(default)]
+    pub hostnames: Hostnames,
     pub ports: Ports,
 }
+
+impl Host {
+    /// The name nmap was given for the host, its scripts reached the
+    /// virtual host of that name. None for hosts scanned by address.
+    pub fn user_hostname(&self) -> Option<&str> {
+	self.hostnames.hostnames.iter()
+	    .find(|hostname| hostname.kind == "user")
+	    .map(|hostname| hostname.name.as_str())
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct Hostnames {
+    #[serde(rename = "hostname", default)]
+    pub hostnames: Vec<Hostname>,
+}
+
+// <hostname name="docs.example.com" type="user"/>, PTR for reverse lookups
+#[derive(Debug, Deserialize)]
+pub struct Hostname {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+}
 #[derive(Debug, Deserialize)]
 pub struct Output {}
 
@@ -124,11 +194,14 @@ pub struct Status {
     pub reason_ttl: u32,
 }
 
-// <service name="http" product="Cloudflare http proxy" method="probed" conf="10"/>
+// <service name="http" product="Cloudflare http proxy" tunnel="ssl" method="probed" conf="10"/>
 #[derive(Debug, Deserialize)]
 pub struct Service {
     pub name: String,
     pub product: Option<String>,
+    pub version: Option<String>,
+    // "ssl" when nmap spoke TLS before detecting the service
+    pub tunnel: Option<String>,
     pub method: String,
     pub conf: u32,
 }
@@ -166,6 +239,101 @@ pub struct Port {
 pub struct Script {
     pub id: String,
     pub output: String,
+    #[serde(rename = "$value", default)]
+    pub elements: Vec<ScriptElement>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScriptElement {
+    Table(Table),
+    Elem(Elem),
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Table {}
+
+// <elem key="serial">0FAA6338A6B5C1B2A3DDE1E9A4D8E9C1</elem>
+#[derive(Debug, Deserialize)]
+pub struct Elem {
+    pub key: Option<String>,
+    #[serde(rename = "$value")]
+    pub value: Option<String>,
+}
+
+impl Script {
+    // A top level <elem key="..."> of the structured output.
+    fn elem(&self, key: &str) -> Option<&str> {
+	self.elements.iter().find_map(|element| match element {
+	    ScriptElement::Elem(elem) if elem.key.as_deref() == Some(key) => elem.value.as_deref(),
+	    _ => None,
+	})
+    }
+
+    /// Certificate details from the ssl-cert script output:
+    ///
+    /// Subject: commonName=www.example.org/organizationName=Internet Corporation for Assigned Names and Numbers
+    /// Subject Alternative Name: DNS:www.example.org, DNS:example.com
+    /// Issuer: commonName=DigiCert TLS RSA SHA256 2020 CA1/organizationName=DigiCert Inc/countryName=US
+    /// Public Key type: rsa
+    /// Public Key bits: 2048
+    /// Signature Algorithm: sha256WithRSAEncryption
+    /// Not valid before: 2021-12-10T00:00:00
+    /// Not valid after:  2022-12-09T23:59:59
+    /// MD5:   ab23 ...
+    /// SHA-1: 9f60 ...
+    ///
+    /// The serial is only in the structured output, <elem key="serial">.
+    pub fn certificate(&self) -> Option<Certificate> {
+	if self.id != "ssl-cert" {
+	    return None;
+	}
+	let mut certificate = Certificate::default();
+	let mut sha1 = None;
+	for line in self.output.lines() {
+	    let (key, value) = match line.split_once(':') {
+		Some((key, value)) => (key.trim(), value.trim()),
+		None => continue,
+	    };
+	    match key {
+		"Subject" => certificate.subject = common_name(value),
+		"Issuer" => certificate.issuer = common_name(value),
+		"Subject Alternative Name" => certificate.sans = value.split(',')
+		    .filter_map(|name| name.trim().strip_prefix("DNS:"))
+		    .map(|name| name.to_string())
+		    .collect(),
+		"Public Key type" => certificate.key_type = Some(value.to_uppercase()),
+		"Public Key bits" => certificate.key_size = value.parse().ok(),
+		"Signature Algorithm" => certificate.signature_algorithm = Some(value.into()),
+		"Not valid before" => certificate.not_before = Some(nmap_date(value)),
+		"Not valid after" => certificate.not_after = Some(nmap_date(value)),
+		"SHA-256" => certificate.fingerprint = Some(value.replace(' ', "").to_lowercase()),
+		"SHA-1" => sha1 = Some(value.replace(' ', "").to_lowercase()),
+		_ => continue,
+	    }
+	}
+	// older nmap versions only print MD5 and SHA-1
+	if certificate.fingerprint.is_none() {
+	    certificate.fingerprint = sha1;
+	}
+	certificate.serial = self.elem("serial").map(|serial| serial.trim().to_lowercase());
+	Some(certificate)
+    }
+}
+
+// "commonName=www.example.org/organizationName=..." -> "www.example.org"
+fn common_name(dn: &str) -> Option<String> {
+    dn.split('/')
+	.find_map(|part| part.strip_prefix("commonName="))
+	.map(|name| name.to_string())
+}
+
+// "2022-12-09T23:59:59" -> "2022-12-09 23:59:59"
+fn nmap_date(date: &str) -> String {
+    date.chars()
+	.take(19)
+	.map(|c| if c == 'T' { ' ' } else { c })
+	.collect()
 }
 
 #[cfg(test)]
@@ -206,7 +374,7 @@ mod tests {
     #[test]
     fn parse_address() {
 	let addr_str = r#"<address addr="104.19.128.108" addrtype="ipv4"/>"#;
-	let addr: Address = from_str(&addr_str).unwrap();
+	let addr: Address = from_str(addr_str).unwrap();
 	assert!(addr.addr == "104.19.128.108");
 	assert!(addr.addrtype == "ipv4");
     }
@@ -216,7 +384,7 @@ mod tests {
 	let xml = r#"
 <status state="up" reason="syn-ack" reason_ttl="0"/>
 "#;
-	let status: Status = from_str(&xml).unwrap();
+	let status: Status = from_str(xml).unwrap();
 	assert!(status.state == "up");
 
     }
@@ -236,7 +404,7 @@ mod tests {
 </script></port>
 "#;
 
-	let port: Port = from_str(&xml).unwrap();
+	let port: Port = from_str(xml).unwrap();
 	assert!(port.protocol == "tcp");
 	assert!(port.portid == 80);
 	assert!(port.state.state == "open");
@@ -248,6 +416,24 @@ mod tests {
 	//assert!(scripts[1].output == "openresty");
 
     }
+
+    #[test]
+    fn parse_ssl_cert_script() {
+	let xml = r#"<script id="ssl-cert" output="Subject: commonName=www.example.org/organizationName=Internet&#xa0;Corporation&#xa0;for&#xa0;Assigned&#xa0;Names&#xa0;and&#xa0;Numbers/countryName=US&#xa;Subject Alternative Name: DNS:www.example.org, DNS:example.com, DNS:*.example.net&#xa;Issuer: commonName=DigiCert TLS RSA SHA256 2020 CA1/organizationName=DigiCert Inc/countryName=US&#xa;Public Key type: rsa&#xa;Public Key bits: 2048&#xa;Signature Algorithm: sha256WithRSAEncryption&#xa;Not valid before: 2021-12-10T00:00:00&#xa;Not valid after:  2022-12-09T23:59:59&#xa;MD5:   ab23 0e3c 9d1f 4f77 c1b2 d4e6 8a90 12cd&#xa;SHA-1: 9f60 0ad3 4b3e 2b17 c8b3 6f10 2ef5 e0c3 a8b5 1d6e"><table key="subject"><elem key="commonName">www.example.org</elem></table><elem key="serial">0FAA6338A6B5C1B2A3DDE1E9A4D8E9C1</elem><elem key="sig_algo">sha256WithRSAEncryption</elem></script>"#;
+	let script: Script = from_str(xml).unwrap();
+	let certificate = script.certificate().unwrap();
+	assert!(certificate.subject.unwrap() == "www.example.org");
+	assert!(certificate.issuer.unwrap() == "DigiCert TLS RSA SHA256 2020 CA1");
+	assert!(certificate.sans == vec!["www.example.org", "example.com", "*.example.net"]);
+	assert!(certificate.key_type.unwrap() == "RSA");
+	assert!(certificate.key_size == Some(2048));
+	assert!(certificate.not_after.unwrap() == "2022-12-09 23:59:59");
+	assert!(certificate.fingerprint.unwrap() == "9f600ad34b3e2b17c8b36f102ef5e0c3a8b51d6e");
+	assert!(certificate.serial.unwrap() == "0faa6338a6b5c1b2a3dde1e9a4d8e9c1");
+
+	let script = Script { id: "http-title".into(), output: "Not found.".into(), elements: vec![] };
+	assert!(script.certificate().is_none());
+    }
     
 
     #[test]
@@ -256,6 +442,8 @@ mod tests {
 <host starttime="1643060432" endtime="1643060451">
 <status state="up" reason="syn-ack" reason_ttl="0"/>
 <address addr="104.19.128.108" addrtype="ipv4"/>
+<hostnames>
+</hostnames>
 <ports>
     <!-- <port protocol="tcp" portid="80">
     	<state state="open" reason="syn-ack" reason_ttl="0"/>
@@ -276,7 +464,7 @@ mod tests {
 </ports>
 </host>
 "#;
-	let host: Host = from_str(&xml).unwrap();
+	let host: Host = from_str(xml).unwrap();
 	assert!(host.address.addr == "104.19.128.108");
 	assert!(host.address.addrtype == "ipv4");
 	assert!(host.starttime == 1643060432);
@@ -285,11 +473,42 @@ mod tests {
 	    assert!(port.protocol == "tcp");
 	    assert!(port.service.name == "http");
 	}
+	assert!(host.user_hostname().is_none());
+    }
+
+    #[test]
+    fn parse_hostnames() {
+	let xml = r#"
+<host starttime="1643060432" endtime="1643060451">
+<status state="up" reason="syn-ack" reason_ttl="0"/>
+<address addr="185.199.108.153" addrtype="ipv4"/>
+<hostnames>
+<hostname name="docs.example.com" type="user"/>
+<hostname name="cdn-185-199-108-153.github.com" type="PTR"/>
+</hostnames>
+<ports></ports>
+</host>
+"#;
+	let host: Host = from_str(xml).unwrap();
+	assert!(host.hostnames.hostnames.len() == 2);
+	assert!(host.user_hostname() == Some("docs.example.com"));
+
+	let xml = r#"
+<host starttime="1643060432" endtime="1643060451">
+<address addr="185.199.108.153" addrtype="ipv4"/>
+<hostnames>
+<hostname name="cdn-185-199-108-153.github.com" type="PTR"/>
+</hostnames>
+<ports></ports>
+</host>
+"#;
+	let host: Host = from_str(xml).unwrap();
+	assert!(host.user_hostname().is_none());
     }
 
     #[test]
     fn parse_nmap_run() {
-	let nmap_run: NmapRun= from_str(&NMAP_XML).unwrap();
+	let nmap_run: NmapRun= from_str(NMAP_XML).unwrap();
 	assert!(nmap_run.hosts.len() == 1);
 	for host in &nmap_run.hosts {
 	    match host {
@@ -297,6 +516,7 @@ mod tests {
 		    for port in &host.ports.ports {
 			assert!(port.protocol == "tcp");
 			assert!(port.service.name == "http");
+			assert!(port.service.tunnel.is_some() == (port.portid % 1000 == 443));
 		    }
 		},
 		_ => continue,
@@ -313,5 +533,22 @@ mod tests {
 	assert!(nmap.hosts.len() == 1);
     }
 
-
+    #[test]
+    fn parse_scaninfo() {
+	let xml = r#"<nmaprun scanner="nmap" args="nmap -sSU -p T:22,80-81,U:53 10.0.0.1" start="1760000000" startstr="Thu Oct  9 08:53:20 2025" version="7.94" xmloutputversion="1.05">
+<scaninfo type="syn" protocol="tcp" numservices="3" services="22,80-81"/>
+<scaninfo type="udp" protocol="udp" numservices="1" services="53"/>
+</nmaprun>"#;
+	let nmap = Nmap::new(xml.as_bytes()).unwrap();
+	let range = |protocol: &str, first, last| PortRange { protocol: protocol.into(), first, last };
+	assert!(nmap.port_ranges().unwrap() == vec![range("tcp", 22, 22), range("tcp", 80, 81), range("udp", 53, 53)]);
+
+	let xml = r#"<nmaprun scanner="nmap" args="nmap -sn 10.0.0.1" start="1760000000" startstr="Thu Oct  9 08:53:20 2025" version="7.94" xmloutputversion="1.05">
+<verbose level="0"/>
+<runstats><finished time="1760000001"/></runstats>
+</nmaprun>"#;
+	assert!(Nmap::new(xml.as_bytes()).unwrap().port_ranges().unwrap().is_empty());
+	let info = StructInfo { protocol: "tcp".into(), services: "80,http".into() };
+	assert!(info.port_ranges().is_err());
+    }
 }
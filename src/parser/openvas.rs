@@ -0,0 +1,314 @@
+use std::fmt;
+use std::io::Read;
+
+use serde::{Deserialize, Deserializer};
+use serde::de::{self, EnumAccess, VariantAccess, Visitor};
+use serde_xml_rs::from_reader;
+
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+// Greenbone report in the GMP XML format, as exported from GSA or returned by
+// get_reports. The results live in a report element nested into the outer
+// report (and into get_reports_response when taken from gvm-cli):
+//
+// <report id="..." format_id="a994b278-1f62-11e1-96ac-406186ea4fc5" extension="xml">
+//   <name>2022-01-31T10:00:00Z</name>
+//   <report id="...">
+//     <results start="1" max="100">
+//       <result id="...">...</result>
+//     </results>
+//   </report>
+// </report>
+#[derive(Debug, Deserialize)]
+pub struct GmpReport {
+    pub report: Option<Box<GmpReport>>,
+    pub results: Option<Results>,
+}
+
+pub type OpenVas = GmpReport;
+impl OpenVas {
+    pub fn new<R: Read>(reader: R) -> Result<Self> {
+	Ok(from_reader(reader)?)
+    }
+
+    pub fn results(&self) -> &[GmpResult] {
+	match (&self.results, &self.report) {
+	    (Some(results), _) => &results.results,
+	    (None, Some(report)) => report.results(),
+	    (None, None) => &[],
+	}
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Results {
+    #[serde(rename = "result", default)]
+    pub results: Vec<GmpResult>,
+}
+
+// <result id="0a6fe6b2-d5e1-4e9a-a1a7-b6a2c1f62e88">
+//   <name>SSL/TLS: Report Weak Cipher Suites</name>
+//   <host>192.168.56.10<asset asset_id="..."/><hostname>web01.example.com</hostname></host>
+//   <port>443/tcp</port>
+//   <nvt oid="1.3.6.1.4.1.25623.1.0.103440">...</nvt>
+//   <threat>Medium</threat>
+//   <severity>5.0</severity>
+//   <qod><value>98</value><type>remote_banner</type></qod>
+//   <description>'Weak' cipher suites accepted by this service via the TLSv1.0 protocol: ...</description>
+// </result>
+#[derive(Debug, Deserialize)]
+pub struct GmpResult {
+    pub name: String,
+    pub host: Host,
+    pub port: String,
+    pub nvt: Nvt,
+    pub threat: String,
+    pub severity: f64,
+    pub qod: Option<Qod>,
+    pub description: Option<String>,
+}
+
+impl GmpResult {
+    /// Port number and protocol from "443/tcp". Host level results use
+    /// "general/tcp" or "general/icmp" and are returned as port 0.
+    pub fn port(&self) -> (u32, &str) {
+	let (port, protocol) = self.port.split_once('/').unwrap_or((&self.port, ""));
+	(port.parse().unwrap_or(0), protocol)
+    }
+
+    /// Threat level in the words used for Nessus findings.
+    pub fn severity_name(&self) -> String {
+	match self.threat.to_lowercase().as_str() {
+	    "log" | "debug" | "false positive" => "info".into(),
+	    threat => threat.into(),
+	}
+    }
+}
+
+// <nvt oid="1.3.6.1.4.1.25623.1.0.103440">
+//   <name>SSL/TLS: Report Weak Cipher Suites</name>
+//   <cvss_base>5.0</cvss_base>
+//   <tags>cvss_base_vector=AV:N/AC:L/Au:N/C:P/I:N/A:N|summary=...|insight=...</tags>
+//   <refs>
+//     <ref type="cve" id="CVE-2013-2566"/>
+//     <ref type="url" id="https://www.bsi.bund.de/..."/>
+//   </refs>
+// </nvt>
+#[derive(Debug, Deserialize)]
+pub struct Nvt {
+    pub oid: String,
+    pub tags: Option<String>,
+    pub refs: Option<Refs>,
+}
+
+impl Nvt {
+    /// Value of one key in the | separated tags.
+    pub fn tag(&self, key: &str) -> Option<&str> {
+	self.tags.as_ref()?
+	    .split('|')
+	    .filter_map(|tag| tag.split_once('='))
+	    .find(|(name, _)| *name == key)
+	    .map(|(_, value)| value)
+    }
+
+    pub fn cves(&self) -> Vec<&str> {
+	self.refs.iter()
+	    .flat_map(|refs| &refs.refs)
+	    .filter(|r| r.kind == "cve")
+	    .map(|r| r.id.as_str())
+	    .collect()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Refs {
+    #[serde(rename = "ref", default)]
+    pub refs: Vec<Ref>,
+}
+
+// <ref type="cve" id="CVE-2013-2566"/>
+#[derive(Debug, Deserialize)]
+pub struct Ref {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub id: String,
+}
+
+// <qod><value>98</value><type>remote_banner</type></qod>
+#[derive(Debug, Deserialize)]
+pub struct Qod {
+    pub value: u32,
+}
+
+// <host>192.168.56.10<asset asset_id="..."/><hostname>web01.example.com</hostname></host>
+//
+// serde-xml-rs hands mixed content over as enum variants named after the
+// text nodes, so the address has to be picked out by hand.
+#[derive(Debug)]
+pub struct Host {
+    pub ip: String,
+    pub hostname: Option<String>,
+}
+
+impl<'de> Deserialize<'de> for Host {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+	#[derive(Deserialize)]
+	struct MixedHost {
+	    #[serde(rename = "$value")]
+	    content: Vec<HostContent>,
+	}
+
+	let mut host = Host {
+	    ip: String::new(),
+	    hostname: None,
+	};
+	for content in MixedHost::deserialize(deserializer)?.content {
+	    match content {
+		HostContent::Text(ip) => host.ip = ip.trim().to_string(),
+		HostContent::Hostname(hostname) if !hostname.is_empty() => host.hostname = Some(hostname),
+		_ => continue,
+	    }
+	}
+	Ok(host)
+    }
+}
+
+enum HostContent {
+    Text(String),
+    Hostname(String),
+    Other,
+}
+
+impl<'de> Deserialize<'de> for HostContent {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+	struct ContentVisitor;
+
+	impl<'de> Visitor<'de> for ContentVisitor {
+	    type Value = HostContent;
+
+	    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+		formatter.write_str("host address or element")
+	    }
+
+	    fn visit_enum<A: EnumAccess<'de>>(self, data: A) -> std::result::Result<Self::Value, A::Error> {
+		let (name, variant): (String, _) = data.variant()?;
+		match name.as_str() {
+		    "hostname" => Ok(HostContent::Hostname(variant.newtype_variant()?)),
+		    "asset" => {
+			variant.newtype_variant::<de::IgnoredAny>()?;
+			Ok(HostContent::Other)
+		    },
+		    _ => {
+			variant.unit_variant()?;
+			Ok(HostContent::Text(name))
+		    },
+		}
+	    }
+	}
+
+	deserializer.deserialize_enum("HostContent", &[], ContentVisitor)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_xml_rs::from_str;
+
+
+    const GMP_XML: &str = r#"<report id="7b2a2bd5-9e1c-4c45-8b0e-6a6b5d2b6a11" format_id="a994b278-1f62-11e1-96ac-406186ea4fc5" extension="xml" content_type="text/xml">
+<owner><name>admin</name></owner>
+<name>2022-01-31T10:00:00Z</name>
+<report id="7b2a2bd5-9e1c-4c45-8b0e-6a6b5d2b6a11">
+<scan_run_status>Done</scan_run_status>
+<ports start="1" max="-1"><count>2</count><port>443/tcp<host>192.168.56.10</host><severity>5.0</severity><threat>Medium</threat></port></ports>
+<results start="1" max="100">
+<result id="0a6fe6b2-d5e1-4e9a-a1a7-b6a2c1f62e88">
+<name>SSL/TLS: Report Weak Cipher Suites</name>
+<owner><name>admin</name></owner>
+<host>192.168.56.10<asset asset_id="5a1d7c55-2f1a-4c1b-a3d5-2d4e1fd0bb0c"/><hostname>web01.example.com</hostname></host>
+<port>443/tcp</port>
+<nvt oid="1.3.6.1.4.1.25623.1.0.103440">
+<type>nvt</type>
+<name>SSL/TLS: Report Weak Cipher Suites</name>
+<family>SSL and TLS</family>
+<cvss_base>5.0</cvss_base>
+<tags>cvss_base_vector=AV:N/AC:L/Au:N/C:P/I:N/A:N|summary=This routine reports all Weak SSL/TLS cipher suites accepted by a service.|solution_type=Mitigation</tags>
+<refs>
+<ref type="cve" id="CVE-2013-2566"/>
+<ref type="cve" id="CVE-2015-2808"/>
+<ref type="url" id="https://www.bsi.bund.de/SharedDocs/Downloads/DE/BSI/Publikationen/TechnischeRichtlinien/TR02102/BSI-TR-02102-2.pdf"/>
+</refs>
+</nvt>
+<threat>Medium</threat>
+<severity>5.0</severity>
+<qod><value>98</value><type>remote_app</type></qod>
+<description>'Weak' cipher suites accepted by this service via the TLSv1.0 protocol:
+
+TLS_RSA_WITH_RC4_128_SHA</description>
+</result>
+<result id="9c4b8e7a-1e52-4d35-9a4f-0c6e2b1f8d33">
+<name>OS Detection Consolidation and Reporting</name>
+<host>192.168.56.10<asset asset_id="5a1d7c55-2f1a-4c1b-a3d5-2d4e1fd0bb0c"/><hostname></hostname></host>
+<port>general/tcp</port>
+<nvt oid="1.3.6.1.4.1.25623.1.0.105937">
+<name>OS Detection Consolidation and Reporting</name>
+<cvss_base>0.0</cvss_base>
+<tags>summary=This script consolidates the OS information detected by several NVTs.</tags>
+<refs></refs>
+</nvt>
+<threat>Log</threat>
+<severity>0.0</severity>
+<qod><value>80</value><type>remote_banner</type></qod>
+<description>Best matching OS: Linux Kernel</description>
+</result>
+</results>
+</report>
+</report>
+"#;
+
+
+    #[test]
+    fn parse_host() {
+	let xml = r#"<host>192.168.56.10<asset asset_id="5a1d7c55"/><hostname>web01.example.com</hostname></host>"#;
+	let host: Host = from_str(xml).unwrap();
+	assert!(host.ip == "192.168.56.10");
+	assert!(host.hostname.unwrap() == "web01.example.com");
+
+	let host: Host = from_str("<host>192.168.56.11</host>").unwrap();
+	assert!(host.ip == "192.168.56.11");
+	assert!(host.hostname.is_none());
+    }
+
+    #[test]
+    fn parse_nvt() {
+	let xml = r#"<nvt oid="1.3.6.1.4.1.25623.1.0.108031">
+<name>SSL/TLS: Report Vulnerable Cipher Suites for HTTPS</name>
+<tags>cvss_base_vector=AV:N/AC:L/Au:N/C:P/I:N/A:N|summary=Vulnerable cipher suites.</tags>
+<refs><ref type="cve" id="CVE-2016-2183"/><ref type="cert-bund" id="CB-K17/1750"/></refs>
+</nvt>"#;
+	let nvt: Nvt = from_str(xml).unwrap();
+	assert!(nvt.oid == "1.3.6.1.4.1.25623.1.0.108031");
+	assert!(nvt.tag("summary") == Some("Vulnerable cipher suites."));
+	assert!(nvt.tag("insight").is_none());
+	assert!(nvt.cves() == vec!["CVE-2016-2183"]);
+    }
+
+    #[test]
+    fn parse_openvas_obj() {
+	let openvas = OpenVas::new(GMP_XML.as_bytes());
+	assert!(openvas.is_ok());
+	let openvas = openvas.unwrap();
+	let results = openvas.results();
+	assert!(results.len() == 2);
+	assert!(results[0].port() == (443, "tcp"));
+	assert!(results[0].severity_name() == "medium");
+	assert!(results[0].qod.as_ref().unwrap().value == 98);
+	assert!(results[0].nvt.cves().len() == 2);
+	assert!(results[1].port() == (0, "tcp"));
+	assert!(results[1].severity_name() == "info");
+	assert!(results[1].host.hostname.is_none());
+    }
+}
@@ -0,0 +1,110 @@
+use std::io::{Read, BufReader, BufRead};
+
+use super::secretsdump::is_hash;
+
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+
+// ~/.local/share/hashcat/hashcat.potfile or ~/.john/john.pot
+//
+// One cracked hash per line as hash:plaintext. hashcat writes plaintexts
+// with odd bytes as $HEX[...], john prefixes NT hashes with $NT$:
+//
+// 64f12cddaa88057e06a81b54e73b949b:Password1
+// $NT$64f12cddaa88057e06a81b54e73b949b:Password1
+// alice::CORP:1122334455667788:0123...:0101...:Password1
+#[derive(Debug)]
+pub struct Potfile {
+    pub entries: Vec<PotfileEntry>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct PotfileEntry {
+    // NT hashes are lowercase without the $NT$ prefix, to match the creds db
+    pub hash: String,
+    pub plaintext: String,
+}
+
+impl Potfile {
+    pub fn new<R: Read>(reader: R) -> Result<Self> {
+	let reader = BufReader::new(reader);
+	let mut entries = vec![];
+	for line in reader.lines() {
+	    if let Some(entry) = PotfileEntry::parse(&line?) {
+		entries.push(entry);
+	    }
+	}
+	Ok(Self {
+	    entries,
+	})
+    }
+}
+
+impl PotfileEntry {
+    pub fn parse(line: &str) -> Option<Self> {
+	let line = line.strip_prefix("$NT$").unwrap_or(line);
+	// a plain hash ends at the first colon, the plaintext may have more;
+	// hashes with colons of their own (NetNTLMv2, ...) end at the last one
+	let (hash, plaintext) = match line.split_once(':') {
+	    Some((hash, plaintext)) if is_hash(hash) => (hash.to_lowercase(), plaintext),
+	    _ => {
+		let (hash, plaintext) = line.rsplit_once(':')?;
+		(hash.to_string(), plaintext)
+	    },
+	};
+	if hash.is_empty() {
+	    return None;
+	}
+	Some(Self {
+	    hash,
+	    plaintext: decode_hex(plaintext),
+	})
+    }
+}
+
+// "$HEX[50617373776f7264]" -> "Password", kept as it is unless it decodes
+// to UTF-8.
+fn decode_hex(plaintext: &str) -> String {
+    let hex = match plaintext.strip_prefix("$HEX[").and_then(|hex| hex.strip_suffix(']')) {
+	Some(hex) if hex.len() % 2 == 0 => hex,
+	_ => return plaintext.to_string(),
+    };
+    let bytes: Option<Vec<u8>> = (0..hex.len())
+	.step_by(2)
+	.map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+	.collect();
+    bytes.and_then(|bytes| String::from_utf8(bytes).ok())
+	.unwrap_or_else(|| plaintext.to_string())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+
+    const POTFILE: &str = r#"64F12CDDAA88057E06A81B54E73B949B:Password1
+$NT$5b4c6335673a75f13ed948e848f00840:a:b:c
+31d6cfe0d16ae931b73c59d7e0c089c0:
+bob::CORP:1122334455667788:0123456789abcdef0123456789abcdef:0101000000000000:Summer2022
+e4e938d12fe5974dc42a90120bd9c90f:$HEX[50617373776f7264]
+"#;
+
+
+    #[test]
+    fn parse_potfile_new() {
+	let potfile = Potfile::new(POTFILE.as_bytes());
+	assert!(potfile.is_ok());
+	let entries = potfile.unwrap().entries;
+	assert!(entries.len() == 5);
+	assert!(entries[0] == PotfileEntry { hash: "64f12cddaa88057e06a81b54e73b949b".into(), plaintext: "Password1".into() });
+	assert!(entries[1].plaintext == "a:b:c");
+	// the empty password
+	assert!(entries[2].plaintext.is_empty());
+	assert!(entries[3].hash == "bob::CORP:1122334455667788:0123456789abcdef0123456789abcdef:0101000000000000");
+	assert!(entries[3].plaintext == "Summer2022");
+	assert!(entries[4].plaintext == "Password");
+	assert!(decode_hex("$HEX[ff]") == "$HEX[ff]");
+    }
+}
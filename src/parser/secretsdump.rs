@@ -0,0 +1,168 @@
+use std::io::{Read, BufReader, BufRead};
+
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+
+// impacket-secretsdump -just-dc -outputfile dc01 corp.local/admin@10.0.0.5
+//
+// Console output or any of the files written with -outputfile (.sam, .ntds,
+// .ntds.cleartext, .ntds.kerberos, .cached), one secret per line:
+//
+// Administrator:500:aad3b435b51404eeaad3b435b51404ee:31d6cfe0d16ae931b73c59d7e0c089c0:::
+// corp.local\alice:1104:aad3b435b51404eeaad3b435b51404ee:64f12cddaa88057e06a81b54e73b949b::: (status=Enabled)
+// corp.local\alice_history0:1104:aad3b435b51404eeaad3b435b51404ee:9c2f4e8a1b3d5f7091a2b3c4d5e6f708:::
+// corp.local\alice:aes256-cts-hmac-sha1-96:4b0a4d1b...
+// corp.local\bob:CLEARTEXT:Summer2022!
+// CORP.LOCAL/carol:$DCC2$10240#carol#e4e938d12fe5974dc42a90120bd9c90f
+//
+// With -history the former NT hashes of an account follow it as
+// <account>_history<n>, newest first.
+//
+// Everything else, like the [*] progress lines and LSA secrets, is skipped.
+#[derive(Debug)]
+pub struct Secretsdump {
+    pub entries: Vec<SecretsdumpEntry>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Secret {
+    // NT hash, lowercase, the LM half is empty on anything recent
+    Ntlm(String),
+    Cleartext(String),
+    // encryption type and key
+    Kerberos(String, String),
+    // domain cached credentials, in hashcat format
+    Dcc2(String),
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct SecretsdumpEntry {
+    // domain of the account, empty for local SAM accounts
+    pub realm: String,
+    pub username: String,
+    pub rid: Option<u32>,
+    pub secret: Secret,
+    // n of a former password from the _history<n> lines, None for the
+    // current one
+    pub history: Option<u32>,
+}
+
+const KERBEROS_TYPES: [&str; 4] = ["aes256-cts-hmac-sha1-96", "aes128-cts-hmac-sha1-96", "des-cbc-md5", "rc4_hmac"];
+
+impl Secretsdump {
+    pub fn new<R: Read>(reader: R) -> Result<Self> {
+	let reader = BufReader::new(reader);
+	let mut entries = vec![];
+	for line in reader.lines() {
+	    if let Some(entry) = SecretsdumpEntry::parse(&line?) {
+		entries.push(entry);
+	    }
+	}
+	Ok(Self {
+	    entries,
+	})
+    }
+}
+
+impl SecretsdumpEntry {
+    pub fn parse(line: &str) -> Option<Self> {
+	let line = line.trim();
+	if line.starts_with('[') || line.starts_with('$') {
+	    return None;
+	}
+	let (account, rest) = line.split_once(':')?;
+	let (rid, secret) = if let Some(password) = rest.strip_prefix("CLEARTEXT:") {
+	    (None, Secret::Cleartext(password.to_string()))
+	} else if let Some((kind, value)) = rest.split_once(':').filter(|(kind, _)| KERBEROS_TYPES.contains(kind)) {
+	    (None, Secret::Kerberos(kind.to_string(), value.trim().to_string()))
+	} else if rest.starts_with("$DCC2$") {
+	    (None, Secret::Dcc2(rest.trim().to_string()))
+	} else {
+	    // rid:lm:nt::: with "(status=...)" and "(pwdLastSet=...)" behind it
+	    let hashes = rest.split_whitespace().next()?;
+	    let fields: Vec<&str> = hashes.split(':').collect();
+	    if fields.len() < 3 || !is_hash(fields[1]) || !is_hash(fields[2]) {
+		return None;
+	    }
+	    (Some(fields[0].parse().ok()?), Secret::Ntlm(fields[2].to_lowercase()))
+	};
+	let (realm, username) = account.split_once(['\\', '/']).unwrap_or(("", account));
+	let (username, history) = match username.rsplit_once("_history") {
+	    Some((username, n)) if rid.is_some() && !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()) => (username, n.parse().ok()),
+	    _ => (username, None),
+	};
+	Some(Self {
+	    realm: realm.to_string(),
+	    username: username.to_string(),
+	    rid,
+	    secret,
+	    history,
+	})
+    }
+}
+
+/// LM or NT hash, 32 hex digits.
+pub fn is_hash(value: &str) -> bool {
+    value.len() == 32 && value.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+
+    const SECRETSDUMP_OUT: &str = r#"Impacket v0.10.0 - Copyright 2022 SecureAuth Corporation
+
+[*] Target system bootKey: 0x1c9a5f2c1e1b5d4a8f3e2d1c0b9a8f7e
+[*] Dumping local SAM hashes (uid:rid:lmhash:nthash)
+Administrator:500:aad3b435b51404eeaad3b435b51404ee:31d6cfe0d16ae931b73c59d7e0c089c0:::
+Guest:501:aad3b435b51404eeaad3b435b51404ee:31d6cfe0d16ae931b73c59d7e0c089c0:::
+[*] Dumping cached domain logon information (domain/username:hash)
+CORP.LOCAL/carol:$DCC2$10240#carol#e4e938d12fe5974dc42a90120bd9c90f
+[*] Dumping LSA Secrets
+[*] $MACHINE.ACC
+$MACHINE.ACC:plain_password_hex:6100640061006d00
+[*] Dumping Domain Credentials (domain\uid:rid:lmhash:nthash)
+[*] Using the DRSUAPI method to get NTDS.DIT secrets
+corp.local\alice:1104:aad3b435b51404eeaad3b435b51404ee:64F12CDDAA88057E06A81B54E73B949B::: (status=Enabled)
+corp.local\alice_history0:1104:aad3b435b51404eeaad3b435b51404ee:9c2f4e8a1b3d5f7091a2b3c4d5e6f708:::
+corp.local\DC01$:1000:aad3b435b51404eeaad3b435b51404ee:5b4c6335673a75f13ed948e848f00840:::
+[*] Kerberos keys grabbed
+corp.local\alice:aes256-cts-hmac-sha1-96:4b0a4d1b2c3d4e5f60718293a4b5c6d7e8f90a1b2c3d4e5f60718293a4b5c6d7
+[*] ClearText passwords grabbed
+corp.local\bob:CLEARTEXT:Summer:2022!
+[*] Cleaning up...
+"#;
+
+
+    #[test]
+    fn parse_secretsdump_new() {
+	let secretsdump = Secretsdump::new(SECRETSDUMP_OUT.as_bytes());
+	assert!(secretsdump.is_ok());
+	let entries = secretsdump.unwrap().entries;
+	assert!(entries.len() == 8);
+	assert!(entries[0] == SecretsdumpEntry {
+	    realm: "".into(),
+	    username: "Administrator".into(),
+	    rid: Some(500),
+	    secret: Secret::Ntlm("31d6cfe0d16ae931b73c59d7e0c089c0".into()),
+	    history: None,
+	});
+	assert!(entries[2].realm == "CORP.LOCAL");
+	assert!(entries[2].secret == Secret::Dcc2("$DCC2$10240#carol#e4e938d12fe5974dc42a90120bd9c90f".into()));
+	assert!(entries[3].username == "alice");
+	assert!(entries[3].secret == Secret::Ntlm("64f12cddaa88057e06a81b54e73b949b".into()));
+	assert!(entries[3].history.is_none());
+	// a former password of alice, not an account of its own
+	assert!(entries[4].username == "alice");
+	assert!(entries[4].history == Some(0));
+	assert!(entries[4].secret == Secret::Ntlm("9c2f4e8a1b3d5f7091a2b3c4d5e6f708".into()));
+	assert!(entries[5].username == "DC01$");
+	assert!(entries[6].secret == Secret::Kerberos("aes256-cts-hmac-sha1-96".into(),
+	    "4b0a4d1b2c3d4e5f60718293a4b5c6d7e8f90a1b2c3d4e5f60718293a4b5c6d7".into()));
+	// passwords may contain colons
+	assert!(entries[7].secret == Secret::Cleartext("Summer:2022!".into()));
+    }
+}
@@ -0,0 +1,357 @@
+use std::io::Read;
+
+use std::fmt;
+
+use serde::{Deserialize, Deserializer};
+use serde::de::{EnumAccess, VariantAccess, Visitor};
+use serde_xml_rs::from_reader;
+
+use super::tls::*;
+
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+// sslscan --xml=sslscan.xml example.com
+//
+// <document title="SSLScan Results" version="2.0.10-static" web="http://github.com/rbsec/sslscan">
+//   <ssltest host="example.com" sniname="example.com" port="443">...</ssltest>
+// </document>
+#[derive(Debug, Deserialize)]
+pub struct Document {
+    pub version: String,
+    #[serde(rename = "ssltest", default)]
+    pub tests: Vec<SslTest>,
+}
+
+pub type Sslscan = Document;
+impl Sslscan {
+    pub fn new<R: Read>(reader: R) -> Result<Self> {
+	Ok(from_reader(reader)?)
+    }
+
+    /// sslscan does not write the address it connected to, only the target as
+    /// given on the command line, so ip may well be a hostname here.
+    pub fn services(&self) -> Vec<TlsService> {
+	self.tests.iter().map(|test| test.service()).collect()
+    }
+}
+
+// <ssltest host="example.com" sniname="example.com" port="443">
+//   <protocol type="tls" version="1.2" enabled="1" />
+//   <heartbleed sslversion="TLSv1.2" vulnerable="0" />
+//   <cipher status="preferred" sslversion="TLSv1.2" bits="128" cipher="ECDHE-RSA-AES128-GCM-SHA256" ... />
+//   <certificates>...</certificates>
+// </ssltest>
+#[derive(Debug, Deserialize)]
+pub struct SslTest {
+    pub host: String,
+    pub port: u32,
+    #[serde(rename = "$value", default)]
+    pub elements: Vec<TestElement>,
+}
+
+impl SslTest {
+    pub fn service(&self) -> TlsService {
+	let mut service = TlsService {
+	    ip: self.host.clone(),
+	    port: self.port,
+	    ..Default::default()
+	};
+	for element in &self.elements {
+	    match element {
+		TestElement::Protocol(protocol) => service.protocols.push(TlsProtocol {
+		    name: protocol.name(),
+		    enabled: protocol.enabled == 1,
+		}),
+		TestElement::Heartbleed(heartbleed) if heartbleed.vulnerable == 1 => service.issues.push(TlsIssue {
+		    id: "heartbleed".into(),
+		    severity: "high".into(),
+		    finding: format!("vulnerable via {}", heartbleed.sslversion),
+		    cve: Some("CVE-2014-0160".into()),
+		}),
+		TestElement::Cipher(cipher) => {
+		    if cipher.strength != "strong" && cipher.strength != "acceptable" {
+			service.issues.push(TlsIssue {
+			    id: format!("cipher {} {}", cipher.sslversion, cipher.cipher),
+			    severity: if cipher.strength == "medium" { "low".into() } else { "medium".into() },
+			    finding: format!("{} cipher {} ({} bits) offered", cipher.strength, cipher.cipher, cipher.bits),
+			    cve: None,
+			});
+		    }
+		    service.ciphers.push(TlsCipher {
+			protocol: cipher.sslversion.clone(),
+			name: cipher.cipher.clone(),
+			bits: Some(cipher.bits),
+			strength: Some(cipher.strength.clone()),
+		    });
+		},
+		TestElement::Certificates(certificates) => {
+		    for certificate in &certificates.certificates {
+			if certificate.expired.as_deref() == Some("true") {
+			    service.issues.push(TlsIssue {
+				id: "cert_expired".into(),
+				severity: "high".into(),
+				finding: format!("certificate expired {}", certificate.not_valid_after.as_deref().unwrap_or("")),
+				cve: None,
+			    });
+			}
+			if certificate.self_signed.as_deref() == Some("true") {
+			    service.issues.push(TlsIssue {
+				id: "cert_self_signed".into(),
+				severity: "medium".into(),
+				finding: "self-signed certificate".into(),
+				cve: None,
+			    });
+			}
+			service.certificates.push(certificate.certificate());
+		    }
+		},
+		_ => continue,
+	    }
+	}
+	service
+    }
+}
+
+#[derive(Debug)]
+pub enum TestElement {
+    Protocol(Protocol),
+    Fallback(Unused),
+    Renegotiation(Unused),
+    Compression(Unused),
+    Heartbleed(Heartbleed),
+    Cipher(Cipher),
+    Group(Unused),
+    Connection(Unused),
+    Certificates(Certificates),
+    ClientCas(Unused),
+    // whatever newer sslscan versions add
+    Other(Unused),
+}
+
+// By hand because #[serde(other)] only takes unit variants, which
+// serde-xml-rs cannot fill from an element with attributes.
+impl<'de> Deserialize<'de> for TestElement {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+	struct ElementVisitor;
+
+	impl<'de> Visitor<'de> for ElementVisitor {
+	    type Value = TestElement;
+
+	    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+		formatter.write_str("an ssltest element")
+	    }
+
+	    fn visit_enum<A: EnumAccess<'de>>(self, data: A) -> std::result::Result<TestElement, A::Error> {
+		let (name, element): (String, _) = data.variant()?;
+		Ok(match name.as_str() {
+		    "protocol" => TestElement::Protocol(element.newtype_variant()?),
+		    "fallback" => TestElement::Fallback(element.newtype_variant()?),
+		    "renegotiation" => TestElement::Renegotiation(element.newtype_variant()?),
+		    "compression" => TestElement::Compression(element.newtype_variant()?),
+		    "heartbleed" => TestElement::Heartbleed(element.newtype_variant()?),
+		    "cipher" => TestElement::Cipher(element.newtype_variant()?),
+		    "group" => TestElement::Group(element.newtype_variant()?),
+		    "connection" => TestElement::Connection(element.newtype_variant()?),
+		    "certificates" => TestElement::Certificates(element.newtype_variant()?),
+		    "client-cas" => TestElement::ClientCas(element.newtype_variant()?),
+		    _ => TestElement::Other(element.newtype_variant()?),
+		})
+	    }
+	}
+
+	deserializer.deserialize_enum("TestElement", &[], ElementVisitor)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Unused {}
+
+// <protocol type="tls" version="1.2" enabled="1" />
+#[derive(Debug, Deserialize)]
+pub struct Protocol {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub version: String,
+    pub enabled: u32,
+}
+
+impl Protocol {
+    /// Protocol name as written by testssl.sh and in the cipher lines.
+    pub fn name(&self) -> String {
+	match (self.kind.as_str(), self.version.as_str()) {
+	    ("ssl", version) => format!("SSLv{}", version),
+	    (_, "1") => "TLSv1.0".into(),
+	    (_, version) => format!("TLSv{}", version),
+	}
+    }
+}
+
+// <heartbleed sslversion="TLSv1.2" vulnerable="0" />
+#[derive(Debug, Deserialize)]
+pub struct Heartbleed {
+    pub sslversion: String,
+    pub vulnerable: u32,
+}
+
+// <cipher status="accepted" sslversion="TLSv1.0" bits="112" cipher="DES-CBC3-SHA" id="0x000A" strength="medium" />
+#[derive(Debug, Deserialize)]
+pub struct Cipher {
+    pub status: String,
+    pub sslversion: String,
+    pub bits: u32,
+    pub cipher: String,
+    pub strength: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Certificates {
+    #[serde(rename = "certificate", default)]
+    pub certificates: Vec<SslCertificate>,
+}
+
+// <certificate type="short">
+//   <signature-algorithm>sha256WithRSAEncryption</signature-algorithm>
+//   <pk error="false" type="RSA" bits="2048" />
+//   <subject><![CDATA[www.example.org]]></subject>
+//   <altnames><![CDATA[DNS:www.example.org, DNS:example.com]]></altnames>
+//   <issuer><![CDATA[DigiCert TLS RSA SHA256 2020 CA1]]></issuer>
+//   <self-signed>false</self-signed>
+//   <not-valid-before>Dec 10 00:00:00 2021 GMT</not-valid-before>
+//   <not-valid-after>Dec  9 23:59:59 2022 GMT</not-valid-after>
+//   <expired>false</expired>
+// </certificate>
+#[derive(Debug, Deserialize)]
+pub struct SslCertificate {
+    #[serde(rename = "signature-algorithm")]
+    pub signature_algorithm: Option<String>,
+    pub pk: Option<PublicKey>,
+    pub subject: Option<String>,
+    pub altnames: Option<String>,
+    pub issuer: Option<String>,
+    #[serde(rename = "self-signed")]
+    pub self_signed: Option<String>,
+    #[serde(rename = "not-valid-before")]
+    pub not_valid_before: Option<String>,
+    #[serde(rename = "not-valid-after")]
+    pub not_valid_after: Option<String>,
+    pub expired: Option<String>,
+}
+
+impl SslCertificate {
+    pub fn certificate(&self) -> Certificate {
+	Certificate {
+	    subject: self.subject.clone(),
+	    issuer: self.issuer.clone(),
+	    not_before: self.not_valid_before.as_deref().and_then(openssl_date),
+	    not_after: self.not_valid_after.as_deref().and_then(openssl_date),
+	    sans: self.altnames.iter()
+		.flat_map(|names| names.split(','))
+		.filter_map(|name| name.trim().strip_prefix("DNS:"))
+		.map(|name| name.to_string())
+		.collect(),
+	    key_type: self.pk.as_ref().map(|pk| pk.kind.clone()),
+	    key_size: self.pk.as_ref().map(|pk| pk.bits),
+	    signature_algorithm: self.signature_algorithm.clone(),
+	    ..Default::default()
+	}
+    }
+}
+
+// <pk error="false" type="RSA" bits="2048" />
+#[derive(Debug, Deserialize)]
+pub struct PublicKey {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub bits: u32,
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_xml_rs::from_str;
+
+
+    const SSLSCAN_XML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<document title="SSLScan Results" version="2.0.10-static" web="http://github.com/rbsec/sslscan">
+ <ssltest host="93.184.216.34" sniname="93.184.216.34" port="443">
+  <protocol type="ssl" version="2" enabled="0" />
+  <protocol type="ssl" version="3" enabled="0" />
+  <protocol type="tls" version="1.0" enabled="1" />
+  <protocol type="tls" version="1.1" enabled="0" />
+  <protocol type="tls" version="1.2" enabled="1" />
+  <protocol type="tls" version="1.3" enabled="1" />
+  <fallback supported="1" />
+  <renegotiation supported="0" secure="0" />
+  <compression supported="0" />
+  <heartbleed sslversion="TLSv1.3" vulnerable="0" />
+  <heartbleed sslversion="TLSv1.2" vulnerable="0" />
+  <heartbleed sslversion="TLSv1.0" vulnerable="1" />
+  <cipher status="preferred" sslversion="TLSv1.3" bits="128" cipher="TLS_AES_128_GCM_SHA256" id="0x1301" strength="strong" curve="25519" ecdhebits="253" />
+  <cipher status="preferred" sslversion="TLSv1.2" bits="128" cipher="ECDHE-RSA-AES128-GCM-SHA256" id="0xC02F" strength="strong" curve="25519" ecdhebits="253" />
+  <cipher status="accepted" sslversion="TLSv1.0" bits="112" cipher="DES-CBC3-SHA" id="0x000A" strength="medium" />
+  <group sslversion="TLSv1.3" bits="128" name="secp256r1 (NIST P-256)" id="0x0017" />
+  <certificates>
+   <certificate type="short">
+    <signature-algorithm>sha256WithRSAEncryption</signature-algorithm>
+    <pk error="false" type="RSA" bits="2048" />
+    <subject><![CDATA[www.example.org]]></subject>
+    <altnames><![CDATA[DNS:www.example.org, DNS:example.com, DNS:example.edu]]></altnames>
+    <issuer><![CDATA[DigiCert TLS RSA SHA256 2020 CA1]]></issuer>
+    <self-signed>false</self-signed>
+    <not-valid-before>Dec 10 00:00:00 2021 GMT</not-valid-before>
+    <not-valid-after>Dec  9 23:59:59 2022 GMT</not-valid-after>
+    <expired>true</expired>
+   </certificate>
+  </certificates>
+ </ssltest>
+</document>
+"#;
+
+
+    #[test]
+    fn parse_protocol() {
+	let protocol: Protocol = from_str(r#"<protocol type="tls" version="1.0" enabled="1" />"#).unwrap();
+	assert!(protocol.name() == "TLSv1.0");
+	let protocol: Protocol = from_str(r#"<protocol type="ssl" version="3" enabled="0" />"#).unwrap();
+	assert!(protocol.name() == "SSLv3");
+	assert!(protocol.enabled == 0);
+    }
+
+    #[test]
+    fn parse_sslscan_obj() {
+	let sslscan = Sslscan::new(SSLSCAN_XML.as_bytes());
+	assert!(sslscan.is_ok());
+	let services = sslscan.unwrap().services();
+	assert!(services.len() == 1);
+
+	let service = &services[0];
+	assert!(service.ip == "93.184.216.34");
+	assert!(service.protocols.len() == 6);
+	assert!(service.protocols.iter().filter(|p| p.enabled).count() == 3);
+	assert!(service.ciphers.len() == 3);
+	assert!(service.ciphers[2].bits == Some(112));
+
+	let certificate = &service.certificates[0];
+	assert!(certificate.sans == vec!["www.example.org", "example.com", "example.edu"]);
+	assert!(certificate.not_after.as_deref() == Some("2022-12-09 23:59:59"));
+	assert!(certificate.key_type.as_deref() == Some("RSA"));
+
+	// heartbleed, DES-CBC3-SHA and the expired certificate
+	assert!(service.issues.len() == 3);
+	assert!(service.issues[0].id == "heartbleed");
+	assert!(service.issues[2].id == "cert_expired");
+    }
+
+    #[test]
+    fn parse_unknown_elements() {
+	let xml = SSLSCAN_XML.replace(
+	    r#"<heartbleed"#,
+	    r#"<ocsp-stapling supported="0" /><signature-algorithm sslversion="TLSv1.3" name="rsa_pss_rsae_sha256" id="0x0804" /><heartbleed"#);
+	let services = Sslscan::new(xml.as_bytes()).unwrap().services();
+	assert!(services.len() == 1);
+	assert!(services[0].protocols.len() == 6);
+	assert!(services[0].issues.len() == 3);
+    }
+}
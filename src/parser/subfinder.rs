@@ -0,0 +1,93 @@
+use std::io::{Read, BufReader, BufRead};
+
+use serde::{Deserialize};
+use serde_json::from_str;
+
+use super::dns::*;
+
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+
+// subfinder -d example.com -oJ -o subfinder.json
+//
+// One JSON object per line. With -cs the single source becomes a list of
+// all sources, with -nW/-oI the resolved address is added.
+#[derive(Debug)]
+pub struct Subfinder {
+    pub entries: Vec<SubfinderEntry>,
+}
+
+impl Subfinder {
+    pub fn new<R: Read>(reader: R) -> Result<Self> {
+	let reader = BufReader::new(reader);
+	let mut entries = vec![];
+	for line in reader.lines() {
+	    let line = line?;
+	    if !line.trim().is_empty() {
+		entries.push(from_str(&line)?);
+	    }
+	}
+	Ok(Self {
+	    entries,
+	})
+    }
+}
+
+// {"host":"www.example.com","input":"example.com","source":"crtsh"}
+// {"host":"www.example.com","input":"example.com","sources":["crtsh","alienvault"]}
+// {"host":"www.example.com","ip":"93.184.216.34","input":"example.com","source":"crtsh"}
+#[derive(Debug, Deserialize)]
+pub struct SubfinderEntry {
+    pub host: String,
+    pub input: Option<String>,
+    pub ip: Option<String>,
+    pub source: Option<String>,
+    #[serde(default)]
+    pub sources: Vec<String>,
+}
+
+impl SubfinderEntry {
+    pub fn sources(&self) -> String {
+	match self.source {
+	    Some(ref source) => source.clone(),
+	    None => self.sources.join(", "),
+	}
+    }
+
+    /// Address record if subfinder was asked to resolve the name.
+    pub fn record(&self) -> Option<DnsRecord> {
+	self.ip.as_ref().map(|ip| DnsRecord::address(&self.host, ip, None))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+
+    const SUBFINDER_JSON: &str = r#"{"host":"www.example.com","input":"example.com","source":"crtsh"}
+{"host":"mail.example.com","input":"example.com","sources":["alienvault","hackertarget"]}
+{"host":"dev.example.com","ip":"93.184.216.40","input":"example.com","source":"dnsdumpster"}
+"#;
+
+
+    #[test]
+    fn parse_subfinder_entry() {
+	let entry: SubfinderEntry = from_str(r#"{"host":"mail.example.com","input":"example.com","sources":["alienvault","hackertarget"]}"#).unwrap();
+	assert!(entry.host == "mail.example.com");
+	assert!(entry.ip.is_none());
+	assert!(entry.sources() == "alienvault, hackertarget");
+    }
+
+    #[test]
+    fn parse_subfinder_new() {
+	let subfinder = Subfinder::new(SUBFINDER_JSON.as_bytes());
+	assert!(subfinder.is_ok());
+	let subfinder = subfinder.unwrap();
+	assert!(subfinder.entries.len() == 3);
+	assert!(subfinder.entries[0].sources() == "crtsh");
+	assert!(subfinder.entries[2].ip.as_deref() == Some("93.184.216.40"));
+    }
+}
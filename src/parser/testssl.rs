@@ -0,0 +1,238 @@
+use std::io::Read;
+
+use serde::{Deserialize};
+use serde_json::from_reader;
+
+use super::tls::*;
+
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+
+// testssl.sh --jsonfile testssl.json example.com
+//
+// A flat list of checks, every entry names the target it belongs to:
+// [
+//   {"id":"TLS1_2","ip":"example.com/93.184.216.34","port":"443","severity":"OK","finding":"offered"},
+//   {"id":"heartbleed","ip":"example.com/93.184.216.34","port":"443","severity":"OK",
+//    "cve":"CVE-2014-0160","cwe":"CWE-119","finding":"not vulnerable, no heartbeat extension"},
+//   ...
+// ]
+#[derive(Debug)]
+pub struct Testssl {
+    pub entries: Vec<TestsslEntry>,
+}
+
+impl Testssl {
+    pub fn new<R: Read>(reader: R) -> Result<Self> {
+	Ok(Self {
+	    entries: from_reader(reader)?,
+	})
+    }
+
+    pub fn services(&self) -> Vec<TlsService> {
+	let mut services: Vec<TlsService> = vec![];
+	for entry in &self.entries {
+	    let (ip, port) = match (entry.ip(), entry.port.parse()) {
+		(Some(ip), Ok(port)) => (ip, port),
+		// scan level messages like engine_problem are not about a target
+		_ => continue,
+	    };
+	    let service = match services.iter().position(|s| s.ip == ip && s.port == port) {
+		Some(i) => &mut services[i],
+		None => {
+		    services.push(TlsService {
+			ip: ip.to_string(),
+			port,
+			..Default::default()
+		    });
+		    services.last_mut().unwrap()
+		},
+	    };
+	    entry.apply(service);
+	}
+	services
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TestsslEntry {
+    pub id: String,
+    pub ip: String,
+    pub port: String,
+    pub severity: String,
+    pub finding: String,
+    pub cve: Option<String>,
+}
+
+impl TestsslEntry {
+    /// "example.com/93.184.216.34" -> "93.184.216.34"
+    pub fn ip(&self) -> Option<&str> {
+	let ip = self.ip.rsplit('/').next()?;
+	Some(ip).filter(|ip| !ip.is_empty())
+    }
+
+    /// Severity as a finding word, None for entries that are not a problem.
+    pub fn issue_severity(&self) -> Option<String> {
+	match self.severity.as_str() {
+	    "LOW" | "MEDIUM" | "HIGH" | "CRITICAL" => Some(self.severity.to_lowercase()),
+	    _ => None,
+	}
+    }
+
+    fn apply(&self, service: &mut TlsService) {
+	// with several server certificates the cert_ ids get a " <cert#2>" suffix
+	let (id, cert) = match self.id.split_once(" <cert#") {
+	    Some((id, n)) => (id, n.trim_end_matches('>').parse().unwrap_or(1)),
+	    None => (self.id.as_str(), 1),
+	};
+
+	match id {
+	    "service" => service.service = Some(self.finding.to_lowercase()),
+	    "SSLv2" | "SSLv3" | "TLS1" | "TLS1_1" | "TLS1_2" | "TLS1_3" => service.protocols.push(TlsProtocol {
+		name: protocol_name(id),
+		enabled: self.finding.starts_with("offered"),
+	    }),
+	    _ if id.starts_with("cipher-") => {
+		// "TLSv1.2   xc02f   ECDHE-RSA-AES128-GCM-SHA256   ECDH 256   AESGCM   128   TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256"
+		let columns: Vec<&str> = self.finding.split_whitespace().collect();
+		if columns.len() >= 5 {
+		    service.ciphers.push(TlsCipher {
+			protocol: columns[0].to_string(),
+			name: columns[2].to_string(),
+			bits: columns[columns.len() - 2].parse().ok(),
+			strength: Some(self.severity.to_lowercase()),
+		    });
+		}
+		// per cipher severity ends up in tls_cipher, not as an issue
+		return;
+	    },
+	    _ if id.starts_with("cert_") => {
+		while service.certificates.len() < cert {
+		    service.certificates.push(Certificate::default());
+		}
+		self.apply_certificate(id, &mut service.certificates[cert - 1]);
+	    },
+	    _ => (),
+	}
+
+	if let Some(severity) = self.issue_severity() {
+	    service.issues.push(TlsIssue {
+		id: self.id.clone(),
+		severity,
+		finding: self.finding.clone(),
+		cve: self.cve.clone().filter(|cve| !cve.is_empty()),
+	    });
+	}
+    }
+
+    fn apply_certificate(&self, id: &str, certificate: &mut Certificate) {
+	let value = self.finding.clone();
+	match id {
+	    "cert_commonName" => certificate.subject = Some(value),
+	    "cert_caIssuers" => certificate.issuer = Some(value),
+	    "cert_serialNumber" => certificate.serial = Some(value),
+	    "cert_notBefore" => certificate.not_before = Some(seconds(value)),
+	    "cert_notAfter" => certificate.not_after = Some(seconds(value)),
+	    "cert_subjectAltName" => certificate.sans = value.split_whitespace().map(|san| san.to_string()).collect(),
+	    "cert_fingerprintSHA256" => certificate.fingerprint = Some(value.to_lowercase()),
+	    "cert_signatureAlgorithm" => certificate.signature_algorithm = Some(value),
+	    "cert_keySize" => {
+		// "RSA 2048 bits"
+		let mut parts = value.split_whitespace();
+		certificate.key_type = parts.next().map(|key_type| key_type.to_string());
+		certificate.key_size = parts.next().and_then(|size| size.parse().ok());
+	    },
+	    _ => (),
+	}
+    }
+}
+
+fn protocol_name(id: &str) -> String {
+    match id {
+	"TLS1" => "TLSv1.0".into(),
+	_ => id.replace("TLS1_", "TLSv1."),
+    }
+}
+
+// testssl.sh leaves out the seconds: "2021-12-10 00:00"
+fn seconds(date: String) -> String {
+    if date.len() == 16 {
+	format!("{}:00", date)
+    } else {
+	date
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::from_str;
+
+
+    const TESTSSL_JSON: &str = r#"[
+  {"id":"engine_problem","ip":"/","port":"443","severity":"WARN","finding":"No engine or GOST support via engine with your /usr/bin/openssl"},
+  {"id":"service","ip":"example.com/93.184.216.34","port":"443","severity":"INFO","finding":"HTTP"},
+  {"id":"SSLv2","ip":"example.com/93.184.216.34","port":"443","severity":"OK","finding":"not offered"},
+  {"id":"SSLv3","ip":"example.com/93.184.216.34","port":"443","severity":"OK","finding":"not offered"},
+  {"id":"TLS1","ip":"example.com/93.184.216.34","port":"443","severity":"LOW","finding":"offered (deprecated)"},
+  {"id":"TLS1_2","ip":"example.com/93.184.216.34","port":"443","severity":"OK","finding":"offered"},
+  {"id":"TLS1_3","ip":"example.com/93.184.216.34","port":"443","severity":"OK","finding":"offered with final"},
+  {"id":"cipher-tls1_xc014","ip":"example.com/93.184.216.34","port":"443","severity":"LOW","finding":"TLSv1     xc014   ECDHE-RSA-AES256-SHA              ECDH 256   AES         256      TLS_ECDHE_RSA_WITH_AES_256_CBC_SHA"},
+  {"id":"cipher-tls1_2_xc02f","ip":"example.com/93.184.216.34","port":"443","severity":"OK","finding":"TLSv1.2   xc02f   ECDHE-RSA-AES128-GCM-SHA256       ECDH 256   AESGCM      128      TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256"},
+  {"id":"cert_signatureAlgorithm","ip":"example.com/93.184.216.34","port":"443","severity":"OK","finding":"SHA256 with RSA"},
+  {"id":"cert_keySize","ip":"example.com/93.184.216.34","port":"443","severity":"INFO","finding":"RSA 2048 bits"},
+  {"id":"cert_serialNumber","ip":"example.com/93.184.216.34","port":"443","severity":"INFO","finding":"0FAA63109307BC3D414892640CCD4D9A"},
+  {"id":"cert_fingerprintSHA256","ip":"example.com/93.184.216.34","port":"443","severity":"INFO","finding":"E7A2A5B2F3C0B0A4E9F4A5D8A79E0B6F7C1D8B5A1E2F3C4D5E6F708192A3B4C5"},
+  {"id":"cert_commonName","ip":"example.com/93.184.216.34","port":"443","severity":"OK","finding":"www.example.org"},
+  {"id":"cert_subjectAltName","ip":"example.com/93.184.216.34","port":"443","severity":"INFO","finding":"www.example.org example.com example.edu"},
+  {"id":"cert_caIssuers","ip":"example.com/93.184.216.34","port":"443","severity":"INFO","finding":"DigiCert TLS RSA SHA256 2020 CA1 (DigiCert Inc from US)"},
+  {"id":"cert_notBefore","ip":"example.com/93.184.216.34","port":"443","severity":"INFO","finding":"2021-12-10 00:00"},
+  {"id":"cert_notAfter","ip":"example.com/93.184.216.34","port":"443","severity":"OK","finding":"2022-12-09 23:59"},
+  {"id":"heartbleed","ip":"example.com/93.184.216.34","port":"443","severity":"OK","cve":"CVE-2014-0160","cwe":"CWE-119","finding":"not vulnerable, no heartbeat extension"},
+  {"id":"SWEET32","ip":"example.com/93.184.216.34","port":"443","severity":"LOW","cve":"CVE-2016-2183 CVE-2016-6329","cwe":"CWE-327","finding":"uses 64 bit block ciphers"}
+]"#;
+
+
+    #[test]
+    fn parse_testssl_entry() {
+	let json = r#"{"id":"heartbleed","ip":"example.com/93.184.216.34","port":"443","severity":"HIGH","cve":"CVE-2014-0160","cwe":"CWE-119","finding":"VULNERABLE"}"#;
+	let entry: TestsslEntry = from_str(json).unwrap();
+	assert!(entry.ip() == Some("93.184.216.34"));
+	assert!(entry.issue_severity().unwrap() == "high");
+	assert!(entry.cve.unwrap() == "CVE-2014-0160");
+    }
+
+    #[test]
+    fn parse_testssl_new() {
+	let testssl = Testssl::new(TESTSSL_JSON.as_bytes());
+	assert!(testssl.is_ok());
+	let services = testssl.unwrap().services();
+	assert!(services.len() == 1);
+
+	let service = &services[0];
+	assert!(service.ip == "93.184.216.34");
+	assert!(service.port == 443);
+	assert!(service.service.as_deref() == Some("http"));
+	assert!(service.protocols.len() == 5);
+	assert!(service.protocols[2].name == "TLSv1.0");
+	assert!(service.protocols[2].enabled);
+	assert!(!service.protocols[0].enabled);
+	assert!(service.ciphers.len() == 2);
+	assert!(service.ciphers[1].name == "ECDHE-RSA-AES128-GCM-SHA256");
+	assert!(service.ciphers[1].bits == Some(128));
+
+	let certificate = &service.certificates[0];
+	assert!(certificate.subject.as_deref() == Some("www.example.org"));
+	assert!(certificate.sans.len() == 3);
+	assert!(certificate.key_size == Some(2048));
+	assert!(certificate.not_after.as_deref() == Some("2022-12-09 23:59:00"));
+	assert!(certificate.fingerprint.as_ref().unwrap().starts_with("e7a2a5b2"));
+
+	// TLS1 and SWEET32, the weak cipher is tracked with the ciphers
+	assert!(service.issues.len() == 2);
+	assert!(service.issues[1].id == "SWEET32");
+	assert!(service.issues[1].cve.as_deref() == Some("CVE-2016-2183 CVE-2016-6329"));
+    }
+}
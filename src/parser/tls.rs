@@ -0,0 +1,78 @@
+// A TLS endpoint, ip and port as scanned, with the protocols it offered,
+// its ciphers, the certificates it presented and what looked wrong.
+#[derive(Debug, Default)]
+pub struct TlsService {
+    pub ip: String,
+    pub port: u32,
+    pub service: Option<String>,
+    pub protocols: Vec<TlsProtocol>,
+    pub ciphers: Vec<TlsCipher>,
+    pub certificates: Vec<Certificate>,
+    pub issues: Vec<TlsIssue>,
+}
+
+// SSLv2, SSLv3, TLSv1.0, TLSv1.1, TLSv1.2 or TLSv1.3
+#[derive(Debug)]
+pub struct TlsProtocol {
+    pub name: String,
+    pub enabled: bool,
+}
+
+#[derive(Debug)]
+pub struct TlsCipher {
+    pub protocol: String,
+    pub name: String,
+    pub bits: Option<u32>,
+    pub strength: Option<String>,
+}
+
+// Dates use the "YYYY-MM-DD HH:MM:SS" form sqlite's date functions understand.
+#[derive(Debug, Default)]
+pub struct Certificate {
+    pub subject: Option<String>,
+    pub issuer: Option<String>,
+    pub serial: Option<String>,
+    pub not_before: Option<String>,
+    pub not_after: Option<String>,
+    pub sans: Vec<String>,
+    pub fingerprint: Option<String>,
+    pub key_type: Option<String>,
+    pub key_size: Option<u32>,
+    pub signature_algorithm: Option<String>,
+}
+
+// Something the scanner flagged, e.g. heartbleed, a weak cipher or an
+// expired certificate. Severity uses the finding words (low .. critical).
+#[derive(Debug)]
+pub struct TlsIssue {
+    pub id: String,
+    pub severity: String,
+    pub finding: String,
+    pub cve: Option<String>,
+}
+
+/// "Dec  9 23:59:59 2022 GMT" (openssl's notAfter format) -> "2022-12-09 23:59:59"
+pub fn openssl_date(date: &str) -> Option<String> {
+    const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+    let mut parts = date.split_whitespace();
+    let month = parts.next()?;
+    let month = MONTHS.iter().position(|name| *name == month)? + 1;
+    let day: u32 = parts.next()?.parse().ok()?;
+    let time = parts.next()?;
+    let year: u32 = parts.next()?.parse().ok()?;
+    Some(format!("{:04}-{:02}-{:02} {}", year, month, day, time))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+
+    #[test]
+    fn parse_openssl_date() {
+	assert!(openssl_date("Dec  9 23:59:59 2022 GMT").unwrap() == "2022-12-09 23:59:59");
+	assert!(openssl_date("Jan 31 10:01:02 2022 GMT").unwrap() == "2022-01-31 10:01:02");
+	assert!(openssl_date("2022-12-09 23:59").is_none());
+    }
+}
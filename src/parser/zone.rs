@@ -0,0 +1,304 @@
+use std::io::Read;
+
+use super::dns::*;
+
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+
+// RFC 1035 master file, as written by hand or by dig:
+//
+// dig axfr @ns1.example.com example.com > example.com.zone
+//
+// ; <<>> DiG 9.16.1 <<>> axfr @ns1.example.com example.com
+// example.com.		3600	IN	SOA	ns1.example.com. hostmaster.example.com. 2022013101 7200 3600 1209600 3600
+// example.com.		3600	IN	NS	ns1.example.com.
+// www.example.com.	300	IN	CNAME	example.com.
+//
+// Hand written zones use the short forms as well: $ORIGIN and $TTL, @ for
+// the origin, names relative to the origin, an empty owner for the previous
+// owner, optional TTL and class, and parentheses to continue a record over
+// several lines.
+#[derive(Debug)]
+pub struct Zone {
+    pub records: Vec<DnsRecord>,
+}
+
+impl Zone {
+    pub fn new<R: Read>(mut reader: R) -> Result<Self> {
+	let mut content = String::new();
+	reader.read_to_string(&mut content)?;
+
+	let mut origin: Option<String> = None;
+	let mut default_ttl: Option<u32> = None;
+	let mut last_ttl: Option<u32> = None;
+	let mut owner: Option<String> = None;
+	let mut records = vec![];
+
+	for line in logical_lines(&content) {
+	    let tokens = tokens(&line);
+	    if tokens.is_empty() {
+		continue;
+	    }
+	    match tokens[0].as_str() {
+		"$ORIGIN" => {
+		    let name = tokens.get(1).ok_or("$ORIGIN without a name")?;
+		    origin = Some(qualify(name, origin.as_deref())?);
+		    continue;
+		},
+		"$TTL" => {
+		    default_ttl = tokens.get(1).and_then(|ttl| parse_ttl(ttl));
+		    continue;
+		},
+		"$INCLUDE" | "$GENERATE" => continue,
+		_ => (),
+	    }
+
+	    let mut fields = tokens.iter().map(|token| token.as_str()).peekable();
+	    if !line.starts_with(|c: char| c.is_whitespace()) {
+		let name = fields.next().unwrap_or("@");
+		owner = Some(qualify(name, origin.as_deref())?);
+	    }
+	    let name = owner.clone().ok_or("record without an owner name")?;
+
+	    // TTL and class are both optional and may come in either order
+	    let mut ttl = None;
+	    while let Some(field) = fields.peek() {
+		if let Some(value) = parse_ttl(field) {
+		    ttl = Some(value);
+		} else if ["IN", "CH", "HS", "CS"].contains(&field.to_uppercase().as_str()) {
+		    // class, pendb only cares about IN anyway
+		} else {
+		    break;
+		}
+		fields.next();
+	    }
+	    if ttl.is_some() {
+		last_ttl = ttl;
+	    }
+	    let ttl = ttl.or(default_ttl).or(last_ttl);
+
+	    let kind = fields.next().ok_or_else(|| format!("record without a type: {}", line))?.to_uppercase();
+	    let rdata: Vec<&str> = fields.collect();
+	    let value = rdata_value(&kind, &rdata, origin.as_deref())?;
+	    records.push(DnsRecord::new(&name, &kind, &value, ttl));
+	}
+
+	Ok(Self {
+	    records,
+	})
+    }
+}
+
+// Lines without comments, records in parentheses joined into one line.
+fn logical_lines(content: &str) -> Vec<String> {
+    let mut lines = vec![];
+    let mut current = String::new();
+    let mut depth = 0;
+    for line in content.lines() {
+	let mut quoted = false;
+	for c in line.chars() {
+	    match c {
+		'"' => quoted = !quoted,
+		';' if !quoted => break,
+		'(' if !quoted => {
+		    depth += 1;
+		    current.push(' ');
+		    continue;
+		},
+		')' if !quoted => {
+		    depth -= 1;
+		    current.push(' ');
+		    continue;
+		},
+		_ => (),
+	    }
+	    current.push(c);
+	}
+	if depth > 0 {
+	    current.push(' ');
+	    continue;
+	}
+	lines.push(std::mem::take(&mut current));
+    }
+    if !current.trim().is_empty() {
+	lines.push(current);
+    }
+    lines
+}
+
+// Whitespace separated fields, a quoted string is one field and keeps its quotes.
+fn tokens(line: &str) -> Vec<String> {
+    let mut tokens = vec![];
+    let mut current = String::new();
+    let mut quoted = false;
+    for c in line.chars() {
+	match c {
+	    '"' => {
+		quoted = !quoted;
+		current.push(c);
+	    },
+	    c if c.is_whitespace() && !quoted => {
+		if !current.is_empty() {
+		    tokens.push(std::mem::take(&mut current));
+		}
+	    },
+	    c => current.push(c),
+	}
+    }
+    if !current.is_empty() {
+	tokens.push(current);
+    }
+    tokens
+}
+
+/// "3600" or "1h30m" -> seconds, None if the field is not a TTL or does
+/// not fit in a u32.
+fn parse_ttl(field: &str) -> Option<u32> {
+    if !field.starts_with(|c: char| c.is_ascii_digit()) {
+	return None;
+    }
+    let mut ttl: u32 = 0;
+    let mut number: u32 = 0;
+    for c in field.to_lowercase().chars() {
+	let unit = match c {
+	    '0'..='9' => {
+		number = number.checked_mul(10)?.checked_add(c.to_digit(10)?)?;
+		continue;
+	    },
+	    's' => 1,
+	    'm' => 60,
+	    'h' => 3600,
+	    'd' => 86400,
+	    'w' => 604800,
+	    _ => return None,
+	};
+	ttl = ttl.checked_add(number.checked_mul(unit)?)?;
+	number = 0;
+    }
+    ttl.checked_add(number)
+}
+
+/// Absolute name for "@", "www" or "www.example.com.".
+fn qualify(name: &str, origin: Option<&str>) -> Result<String> {
+    if name.ends_with('.') {
+	return Ok(name.to_string());
+    }
+    let origin = origin.ok_or_else(|| format!("relative name {} without $ORIGIN", name))?;
+    if name == "@" {
+	Ok(origin.to_string())
+    } else {
+	Ok(format!("{}.{}", name, origin))
+    }
+}
+
+fn rdata_value(kind: &str, rdata: &[&str], origin: Option<&str>) -> Result<String> {
+    // index of the host name fields in the rdata of each type
+    let names: &[usize] = match kind {
+	"CNAME" | "NS" | "PTR" | "DNAME" => &[0],
+	"MX" => &[1],
+	"SRV" => &[3],
+	"SOA" => &[0, 1],
+	"TXT" | "SPF" => {
+	    // character strings of one record are one value
+	    return Ok(rdata.iter().map(|part| part.trim_matches('"')).collect());
+	},
+	_ => &[],
+    };
+    let mut fields = vec![];
+    for (i, field) in rdata.iter().enumerate() {
+	if names.contains(&i) {
+	    fields.push(qualify(field, origin)?.trim_end_matches('.').to_string());
+	} else {
+	    fields.push(field.to_string());
+	}
+    }
+    Ok(fields.join(" "))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+
+    const ZONE: &str = r#"$ORIGIN example.com.
+$TTL 1h
+@	IN	SOA	ns1 hostmaster (
+		2022013101 ; serial
+		7200       ; refresh
+		3600       ; retry
+		1209600    ; expire
+		3600 )     ; minimum
+	IN	NS	ns1
+	IN	NS	ns2.example.net.
+	IN	MX	10 mail
+	IN	TXT	"v=spf1 mx " "-all"
+ns1	300	IN	A	192.0.2.1
+www	IN	CNAME	@
+mail	A	192.0.2.25
+	AAAA	2001:db8::25
+_sip._tcp	IN	SRV	0 5 5060 sip
+"#;
+
+    const DIG_AXFR: &str = r#"
+; <<>> DiG 9.16.1-Ubuntu <<>> axfr @ns1.example.com example.com
+; (1 server found)
+;; global options: +cmd
+example.com.		3600	IN	SOA	ns1.example.com. hostmaster.example.com. 2022013101 7200 3600 1209600 3600
+example.com.		3600	IN	NS	ns1.example.com.
+dev.example.com.	300	IN	A	192.0.2.40
+www.example.com.	300	IN	CNAME	example.com.
+example.com.		3600	IN	SOA	ns1.example.com. hostmaster.example.com. 2022013101 7200 3600 1209600 3600
+;; Query time: 12 msec
+;; SERVER: 192.0.2.1#53(192.0.2.1)
+;; XFR size: 5 records (messages 1, bytes 245)
+"#;
+
+
+    #[test]
+    fn parse_zone_ttl() {
+	assert!(parse_ttl("3600") == Some(3600));
+	assert!(parse_ttl("1h30m") == Some(5400));
+	assert!(parse_ttl("1W") == Some(604800));
+	assert!(parse_ttl("IN").is_none());
+	assert!(parse_ttl("3x").is_none());
+	// too large for a u32
+	assert!(parse_ttl("99999w").is_none());
+	assert!(parse_ttl("4294967296").is_none());
+	assert!(parse_ttl("4294967295") == Some(u32::MAX));
+	assert!(parse_ttl("7101w5d").is_none());
+    }
+
+    #[test]
+    fn parse_zone_file() {
+	let zone = Zone::new(ZONE.as_bytes());
+	assert!(zone.is_ok());
+	let records = zone.unwrap().records;
+	assert!(records.len() == 10);
+	assert!(records[0].kind == "SOA");
+	assert!(records[0].value == "ns1.example.com hostmaster.example.com 2022013101 7200 3600 1209600 3600");
+	assert!(records[0].ttl == Some(3600));
+	assert!(records[2] == DnsRecord::new("example.com", "NS", "ns2.example.net", Some(3600)));
+	assert!(records[3].value == "10 mail.example.com");
+	assert!(records[4].value == "v=spf1 mx -all");
+	assert!(records[5] == DnsRecord::new("ns1.example.com", "A", "192.0.2.1", Some(300)));
+	assert!(records[6] == DnsRecord::new("www.example.com", "CNAME", "example.com", Some(3600)));
+	assert!(records[8].name == "mail.example.com");
+	assert!(records[8].kind == "AAAA");
+	assert!(records[9].value == "0 5 5060 sip.example.com");
+    }
+
+    #[test]
+    fn parse_zone_dig_axfr() {
+	let zone = Zone::new(DIG_AXFR.as_bytes()).unwrap();
+	// dig repeats the SOA at the end of a transfer
+	assert!(zone.records.len() == 5);
+	assert!(zone.records[2] == DnsRecord::new("dev.example.com", "A", "192.0.2.40", Some(300)));
+    }
+
+    #[test]
+    fn parse_zone_without_origin() {
+	assert!(Zone::new("www IN A 192.0.2.1\n".as_bytes()).is_err());
+    }
+}
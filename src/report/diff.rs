@@ -0,0 +1,288 @@
+use std::collections::BTreeSet;
+
+use crate::db::sqlite::{Db, ScanService, Snapshot};
+use crate::db::filter::parse_time;
+
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+// One side of a diff, a single scan or everything scanned up to a time.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Point {
+    Scan(i64),
+    // as returned by parse_time, a date means up to the end of that day
+    Time(String),
+    // every scan so far
+    Latest,
+}
+
+impl Point {
+    /// A scan id, a time or "latest".
+    pub fn parse(value: &str) -> Result<Self> {
+	if value == "latest" {
+	    return Ok(Point::Latest);
+	}
+	match value.parse() {
+	    Ok(id) => Ok(Point::Scan(id)),
+	    Err(_) => Ok(Point::Time(parse_time(value)?)),
+	}
+    }
+
+    fn snapshot(&self, db: &Db) -> Result<Snapshot> {
+	let scans = db.scans()?;
+	let scan_ids: Vec<i64> = match self {
+	    Point::Scan(id) => match scans.iter().find(|scan| scan.id == *id) {
+		Some(scan) => vec![scan.id],
+		None => return Err(format!("no scan {}, see scans", id).into()),
+	    },
+	    Point::Time(until) => scans.iter()
+		.filter(|scan| scan.time().get(..until.len()).unwrap_or(scan.time()) <= until.as_str())
+		.map(|scan| scan.id)
+		.collect(),
+	    Point::Latest => scans.iter().map(|scan| scan.id).collect(),
+	};
+	db.snapshot(&scan_ids)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Change {
+    Opened,
+    Closed,
+    // same port, other service, product or version
+    Changed,
+    NewDomain,
+    // same name, other addresses
+    ChangedIps,
+}
+
+impl Change {
+    pub fn as_str(&self) -> &'static str {
+	match self {
+	    Change::Opened => "opened",
+	    Change::Closed => "closed",
+	    Change::Changed => "changed",
+	    Change::NewDomain => "new_domain",
+	    Change::ChangedIps => "changed_ips",
+	}
+    }
+}
+
+// Port changes have ip, port and protocol, name changes the domain. old and
+// new are the service or the addresses before and after.
+#[derive(Debug, PartialEq)]
+pub struct DiffEntry {
+    pub change: Change,
+    pub ip: Option<String>,
+    pub port: Option<u16>,
+    pub protocol: Option<String>,
+    pub domain: Option<String>,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+/// What changed between two points. A port that is no longer reported open
+/// is only closed if the later side probed that port of the address.
+pub fn diff(db: &Db, from: &Point, to: &Point) -> Result<Vec<DiffEntry>> {
+    let from = from.snapshot(db)?;
+    let to = to.snapshot(db)?;
+    let mut entries = vec![];
+
+    for ((ip, port, protocol), service) in &to.services {
+	let before = from.services.get(&(ip.clone(), *port, protocol.clone())).filter(|before| is_open(before));
+	let change = match before {
+	    _ if !is_open(service) => continue,
+	    None => Change::Opened,
+	    Some(before) if describe(before) != describe(service) => Change::Changed,
+	    Some(_) => continue,
+	};
+	entries.push(DiffEntry {
+	    change,
+	    ip: Some(ip.clone()),
+	    port: Some(*port),
+	    protocol: Some(protocol.clone()),
+	    domain: None,
+	    old: before.map(describe),
+	    new: Some(describe(service)),
+	});
+    }
+    for ((ip, port, protocol), service) in &from.services {
+	if !is_open(service) || !to.covers(ip, *port, protocol) {
+	    continue;
+	}
+	let after = to.services.get(&(ip.clone(), *port, protocol.clone()));
+	if after.is_some_and(is_open) {
+	    continue;
+	}
+	entries.push(DiffEntry {
+	    change: Change::Closed,
+	    ip: Some(ip.clone()),
+	    port: Some(*port),
+	    protocol: Some(protocol.clone()),
+	    domain: None,
+	    old: Some(describe(service)),
+	    new: after.and_then(|after| after.state.clone()),
+	});
+    }
+
+    for (domain, ips) in &to.domains {
+	let change = match from.domains.get(domain) {
+	    None => Change::NewDomain,
+	    Some(before) if before != ips => Change::ChangedIps,
+	    Some(_) => continue,
+	};
+	entries.push(DiffEntry {
+	    change,
+	    ip: None,
+	    port: None,
+	    protocol: None,
+	    domain: Some(domain.clone()),
+	    old: from.domains.get(domain).map(join),
+	    new: Some(join(ips)),
+	});
+    }
+
+    entries.sort_by_key(|entry| entry.change);
+    Ok(entries)
+}
+
+fn is_open(service: &ScanService) -> bool {
+    service.state.as_deref() == Some("open")
+}
+
+// "http nginx 1.25.3", whatever the scan knew
+fn describe(service: &ScanService) -> String {
+    [&service.service_name, &service.product, &service.version]
+	.iter()
+	.filter_map(|part| part.as_deref())
+	.collect::<Vec<_>>()
+	.join(" ")
+}
+
+fn join(ips: &BTreeSet<String>) -> String {
+    ips.iter().map(|ip| ip.as_str()).collect::<Vec<_>>().join(", ")
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::nmap::Nmap;
+    use crate::parser::amass::Amass;
+
+    const NMAP_WEEK1: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<nmaprun scanner="nmap" args="nmap -sV 10.0.0.1" start="1760000000" startstr="Thu Oct  9 08:53:20 2025" version="7.94" xmloutputversion="1.05">
+<scaninfo type="syn" protocol="tcp" numservices="1000" services="1-1000"/>
+<host starttime="1760000000" endtime="1760000100"><status state="up" reason="syn-ack" reason_ttl="0"/>
+<address addr="10.0.0.1" addrtype="ipv4"/>
+<ports>
+<port protocol="tcp" portid="22"><state state="open" reason="syn-ack" reason_ttl="0"/><service name="ssh" product="OpenSSH" version="9.2p1" method="probed" conf="10"/></port>
+<port protocol="tcp" portid="80"><state state="open" reason="syn-ack" reason_ttl="0"/><service name="http" product="nginx" version="1.24.0" method="probed" conf="10"/></port>
+</ports>
+</host>
+</nmaprun>
+"#;
+
+    const NMAP_WEEK2: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<nmaprun scanner="nmap" args="nmap -sV 10.0.0.1" start="1760604800" startstr="Thu Oct 16 08:53:20 2025" version="7.94" xmloutputversion="1.05">
+<scaninfo type="syn" protocol="tcp" numservices="1000" services="1-1000"/>
+<host starttime="1760604800" endtime="1760604900"><status state="up" reason="syn-ack" reason_ttl="0"/>
+<address addr="10.0.0.1" addrtype="ipv4"/>
+<ports>
+<port protocol="tcp" portid="80"><state state="open" reason="syn-ack" reason_ttl="0"/><service name="http" product="nginx" version="1.25.3" method="probed" conf="10"/></port>
+<port protocol="tcp" portid="443"><state state="open" reason="syn-ack" reason_ttl="0"/><service name="http" product="nginx" version="1.25.3" tunnel="ssl" method="probed" conf="10"/></port>
+</ports>
+</host>
+</nmaprun>
+"#;
+
+    // only the web ports, 443 is filtered now
+    const NMAP_WEEK3: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<nmaprun scanner="nmap" args="nmap -sV -p 80,443 10.0.0.1" start="1761209600" startstr="Thu Oct 23 08:53:20 2025" version="7.94" xmloutputversion="1.05">
+<scaninfo type="syn" protocol="tcp" numservices="2" services="80,443"/>
+<host starttime="1761209600" endtime="1761209700"><status state="up" reason="syn-ack" reason_ttl="0"/>
+<address addr="10.0.0.1" addrtype="ipv4"/>
+<ports>
+<port protocol="tcp" portid="80"><state state="open" reason="syn-ack" reason_ttl="0"/><service name="http" product="nginx" version="1.25.3" method="probed" conf="10"/></port>
+<port protocol="tcp" portid="443"><state state="filtered" reason="no-response" reason_ttl="0"/><service name="https" method="table" conf="3"/></port>
+</ports>
+</host>
+</nmaprun>
+"#;
+
+    const AMASS_WEEK1: &str = r#"{"name":"www.example.com","domain":"example.com","addresses":[{"ip":"10.0.0.1","cidr":"10.0.0.0/24","asn":64500,"desc":"EXAMPLE"}],"tag":"dns","sources":["DNS"]}
+"#;
+
+    const AMASS_WEEK2: &str = r#"{"name":"www.example.com","domain":"example.com","addresses":[{"ip":"10.0.0.2","cidr":"10.0.0.0/24","asn":64500,"desc":"EXAMPLE"}],"tag":"dns","sources":["DNS"]}
+{"name":"api.example.com","domain":"example.com","addresses":[{"ip":"10.0.0.1","cidr":"10.0.0.0/24","asn":64500,"desc":"EXAMPLE"}],"tag":"dns","sources":["DNS"]}
+"#;
+
+    #[test]
+    fn diff_scans() {
+	let db = Db::new(":memory:").unwrap();
+	db.create_table().unwrap();
+	db.insert_nmap_scan(Nmap::new(NMAP_WEEK1.as_bytes()).unwrap()).unwrap();
+	db.insert_amass_scan(Amass::new(AMASS_WEEK1.as_bytes()).unwrap()).unwrap();
+	db.insert_nmap_scan(Nmap::new(NMAP_WEEK2.as_bytes()).unwrap()).unwrap();
+	db.insert_amass_scan(Amass::new(AMASS_WEEK2.as_bytes()).unwrap()).unwrap();
+
+	let scans = db.scans().unwrap();
+	assert!(scans.len() == 4);
+	assert!(scans[0].started.as_deref() == Some("2025-10-09 08:53:20"));
+	assert!(scans[0].services == 2 && scans[0].hosts == 1);
+	assert!(scans.iter().filter(|scan| scan.tool == "amass").map(|scan| scan.domains).sum::<i64>() == 3);
+
+	let entries = diff(&db, &Point::Scan(1), &Point::Scan(3)).unwrap();
+	let summary: Vec<_> = entries.iter().map(|entry| (entry.change, entry.port)).collect();
+	assert!(summary == vec![
+	    (Change::Opened, Some(443)),
+	    (Change::Closed, Some(22)),
+	    (Change::Changed, Some(80)),
+	]);
+	assert!(entries[2].old.as_deref() == Some("http nginx 1.24.0"));
+	assert!(entries[2].new.as_deref() == Some("http nginx 1.25.3"));
+	assert!(entries[1].new.is_none());
+
+	let entries = diff(&db, &Point::Scan(2), &Point::Scan(4)).unwrap();
+	assert!(entries == vec![
+	    DiffEntry {
+		change: Change::NewDomain,
+		ip: None, port: None, protocol: None,
+		domain: Some("api.example.com".into()),
+		old: None,
+		new: Some("10.0.0.1".into()),
+	    },
+	    DiffEntry {
+		change: Change::ChangedIps,
+		ip: None, port: None, protocol: None,
+		domain: Some("www.example.com".into()),
+		old: Some("10.0.0.1".into()),
+		new: Some("10.0.0.2".into()),
+	    },
+	]);
+
+	// the amass scans have no start time of their own, they were imported now
+	let entries = diff(&db, &Point::Time("2025-10-09".into()), &Point::Latest).unwrap();
+	assert!(entries.iter().filter(|entry| entry.port.is_some()).count() == 3);
+	assert!(entries.iter().filter(|entry| entry.change == Change::NewDomain).count() == 2);
+	assert!(diff(&db, &Point::Latest, &Point::Latest).unwrap().is_empty());
+
+	// a rescan of the web ports says nothing about ssh
+	db.insert_nmap_scan(Nmap::new(NMAP_WEEK3.as_bytes()).unwrap()).unwrap();
+	let entries = diff(&db, &Point::Scan(1), &Point::Scan(5)).unwrap();
+	let summary: Vec<_> = entries.iter().map(|entry| (entry.change, entry.port)).collect();
+	assert!(summary == vec![(Change::Changed, Some(80))]);
+	let entries = diff(&db, &Point::Scan(3), &Point::Scan(5)).unwrap();
+	assert!(entries.len() == 1 && entries[0].change == Change::Closed && entries[0].port == Some(443));
+	assert!(entries[0].new.as_deref() == Some("filtered"));
+	// the full scan of the second week still closed it
+	let entries = diff(&db, &Point::Scan(1), &Point::Latest).unwrap();
+	let summary: Vec<_> = entries.iter().filter(|entry| entry.port.is_some()).map(|entry| (entry.change, entry.port)).collect();
+	assert!(summary == vec![(Change::Closed, Some(22)), (Change::Changed, Some(80))]);
+
+	assert!(Point::parse("3").unwrap() == Point::Scan(3));
+	assert!(Point::parse("2025-10-09").unwrap() == Point::Time("2025-10-09".into()));
+	assert!(Point::parse("last week").is_err());
+	assert!(diff(&db, &Point::Scan(9), &Point::Latest).is_err());
+    }
+}
@@ -0,0 +1,4 @@
+pub mod takeover;
+pub mod shared;
+pub mod targets;
+pub mod diff;
@@ -0,0 +1,167 @@
+use serde::{Deserialize};
+use serde_json::from_str;
+
+use crate::db::sqlite::{Db, NetworkEntry};
+use crate::db::scope::{Rule, ScopeFilter};
+use crate::db::tag::Entity;
+
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+// CDNs, cloud and hosting providers whose addresses serve many unrelated
+// customers. A name of the engagement resolving there does not make the
+// address a target, scanning it hits the provider and everybody else on it.
+const PROVIDERS: &str = include_str!("shared.json");
+
+// {"provider": "Cloudflare", "asn": [13335], "description": ["CLOUDFLARENET"], "cidr": ["104.16.0.0/13"]}
+#[derive(Debug, Deserialize)]
+pub struct Provider {
+    pub provider: String,
+    pub asn: Vec<i64>,
+    // start of the AS handle amass puts in the description, "CLOUDFLARENET - Cloudflare, Inc."
+    pub description: Vec<String>,
+    // for addresses amass did not look up, e.g. from dnsx or zone files
+    pub cidr: Vec<String>,
+}
+
+impl Provider {
+    pub fn all() -> Result<Vec<Self>> {
+	Ok(from_str(PROVIDERS)?)
+    }
+
+    pub fn matches(&self, entry: &NetworkEntry) -> bool {
+	if entry.asn.is_some_and(|asn| self.asn.contains(&asn)) {
+	    return true;
+	}
+	if let Some(description) = &entry.description {
+	    let handle = description.split(" - ").next().unwrap_or("").to_uppercase();
+	    if self.description.iter().any(|pattern| handle.starts_with(pattern.as_str())) {
+		return true;
+	    }
+	}
+	self.matches_ip(&entry.ip)
+    }
+
+    pub fn matches_ip(&self, ip: &str) -> bool {
+	let ip = match ip.parse() {
+	    Ok(ip) => ip,
+	    Err(_) => return false,
+	};
+	self.cidr.iter()
+	    .filter_map(|cidr| Rule::parse(true, cidr).ok())
+	    .any(|rule| rule.matches_ip(&ip))
+    }
+}
+
+#[derive(Debug)]
+pub struct Alert {
+    pub domain: String,
+    pub ip: String,
+    pub provider: String,
+    pub asn: Option<i64>,
+    pub cidr: Option<String>,
+    pub approved: bool,
+    // id of the domain row
+    pub id: i64,
+}
+
+/// Names shown by the scope and tag filters that resolve to shared
+/// infrastructure.
+pub fn shared_infrastructure(db: &Db, filter: ScopeFilter, tags: &[String]) -> Result<Vec<Alert>> {
+    let providers = Provider::all()?;
+    let rules = db.scope_rules()?;
+    let tagged = db.tagged(Entity::Domain, tags)?;
+    let mut alerts = vec![];
+    for entry in db.domain_networks()? {
+	if !filter.allows(&rules, entry.scope) {
+	    continue;
+	}
+	if tagged.as_ref().is_some_and(|tagged| !tagged.contains(&entry.id)) {
+	    continue;
+	}
+	if let Some(provider) = providers.iter().find(|provider| provider.matches(&entry)) {
+	    alerts.push(Alert {
+		domain: entry.domain,
+		ip: entry.ip,
+		provider: provider.provider.clone(),
+		asn: entry.asn,
+		cidr: entry.cidr,
+		approved: entry.approved,
+		id: entry.id,
+	    });
+	}
+    }
+    Ok(alerts)
+}
+
+/// Addresses on shared infrastructure that were not approved, to be left
+/// out of target lists.
+pub fn shared_ips(db: &Db) -> Result<Vec<String>> {
+    let mut ips: Vec<String> = shared_infrastructure(db, ScopeFilter::Any, &[])?
+	.into_iter()
+	.filter(|alert| !alert.approved)
+	.map(|alert| alert.ip)
+	.collect();
+    ips.sort();
+    ips.dedup();
+    Ok(ips)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::amass::Amass;
+    use crate::parser::dnsx::Dnsx;
+
+
+    const AMASS_JSON: &str = r#"{"name":"4chan.org","domain":"4chan.org","addresses":[{"ip":"104.19.128.108","cidr":"104.16.0.0/14","asn":13335,"desc":"CLOUDFLARENET - Cloudflare, Inc."}],"tag":"dns","sources":["DNS"]}
+{"name":"blog.4chan.org","domain":"4chan.org","addresses":[{"ip":"74.114.154.18","cidr":"74.114.152.0/22","asn":2635,"desc":"AUTOMATTIC - Automattic, Inc"}],"tag":"api","sources":["AlienVault"]}
+{"name":"vpn.4chan.org","domain":"4chan.org","addresses":[{"ip":"198.51.100.7","cidr":"198.51.100.0/24","asn":64500,"desc":"EXAMPLE-AS - Example hosting"}],"tag":"dns","sources":["DNS"]}"#;
+
+
+    #[test]
+    fn parse_providers() {
+	let providers = Provider::all().unwrap();
+	let cloudflare = providers.iter().find(|provider| provider.provider == "Cloudflare").unwrap();
+	assert!(cloudflare.matches_ip("104.19.128.108"));
+	assert!(cloudflare.matches_ip("2606:4700::6813:806c"));
+	assert!(!cloudflare.matches_ip("198.51.100.7"));
+	// every range has to parse, a typo would silently match nothing
+	for provider in &providers {
+	    for cidr in &provider.cidr {
+		assert!(Rule::parse(true, cidr).is_ok());
+	    }
+	}
+    }
+
+    #[test]
+    fn shared_alerts() {
+	let db = Db::new(":memory:").unwrap();
+	db.create_table().unwrap();
+	db.insert_amass_scan(Amass::new(AMASS_JSON.as_bytes()).unwrap()).unwrap();
+	// no asn for dnsx results, the address range gives it away
+	db.insert_dnsx_scan(Dnsx::new(r#"{"host":"cdn.4chan.org","a":["172.67.1.1"]}"#.as_bytes()).unwrap()).unwrap();
+	db.add_scope_rule(&Rule::parse(false, "blog.4chan.org").unwrap()).unwrap();
+	db.apply_scope("test").unwrap();
+
+	let alerts = shared_infrastructure(&db, ScopeFilter::Default, &[]).unwrap();
+	assert!(alerts.len() == 2);
+	assert!(alerts[0].domain == "4chan.org");
+	assert!(alerts[0].provider == "Cloudflare");
+	assert!(alerts[0].asn == Some(13335));
+	assert!(alerts[1].domain == "cdn.4chan.org");
+	assert!(alerts[1].cidr.is_none());
+	assert!(shared_infrastructure(&db, ScopeFilter::Any, &[]).unwrap().len() == 3);
+
+	assert!(shared_ips(&db).unwrap() == vec!["104.19.128.108", "172.67.1.1", "74.114.154.18"]);
+	assert!(db.approve_shared("104.19.128.108", true).unwrap());
+	assert!(!db.approve_shared("104.19.128.108", true).unwrap());
+	assert!(shared_infrastructure(&db, ScopeFilter::Default, &[]).unwrap()[0].approved);
+	assert!(shared_ips(&db).unwrap() == vec!["172.67.1.1", "74.114.154.18"]);
+	assert!(db.approve_shared("www.4chan.org", true).is_err());
+	// a typo must not create a host
+	assert!(db.approve_shared("104.19.128.109", true).is_err());
+	assert!(db.conn.prepare("SELECT id FROM host WHERE ip = '104.19.128.109'").unwrap().into_cursor().next().unwrap().is_none());
+    }
+}
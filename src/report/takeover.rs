@@ -0,0 +1,216 @@
+use std::collections::HashSet;
+
+use serde::{Deserialize};
+use serde_json::from_str;
+
+use crate::db::sqlite::Db;
+use crate::db::scope::ScopeFilter;
+
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+// Services a dangling CNAME can be claimed on, shipped with the binary so the
+// report works offline. Based on the can-i-take-over-xyz list.
+const FINGERPRINTS: &str = include_str!("takeover.json");
+
+// {"service": "GitHub Pages", "cname": ["github.io"], "nxdomain": false, "fingerprint": ["There isn't a GitHub Pages site here."]}
+#[derive(Debug, Deserialize)]
+pub struct Fingerprint {
+    pub service: String,
+    pub cname: Vec<String>,
+    // a CNAME to a name that no longer exists can be claimed
+    pub nxdomain: bool,
+    pub fingerprint: Vec<String>,
+}
+
+impl Fingerprint {
+    pub fn all() -> Result<Vec<Self>> {
+	Ok(from_str(FINGERPRINTS)?)
+    }
+
+    /// The target is one of the patterns or a name below one.
+    pub fn matches_cname(&self, target: &str) -> bool {
+	self.cname.iter().any(|pattern| target == pattern || target.ends_with(&format!(".{}", pattern)))
+    }
+
+    pub fn matches_output(&self, output: &str) -> Option<&str> {
+	self.fingerprint.iter()
+	    .find(|fingerprint| output.contains(fingerprint.as_str()))
+	    .map(|fingerprint| fingerprint.as_str())
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Reason {
+    // neither the name nor anything on its CNAME chain has an address, and
+    // the service hands out such names to whoever asks
+    Unresolved,
+    // one of the addresses serves the service's "not found" page for the name
+    Fingerprint(String),
+}
+
+#[derive(Debug)]
+pub struct Candidate {
+    pub domain: String,
+    pub cname: String,
+    pub service: String,
+    pub reason: Reason,
+}
+
+/// Names with a CNAME into a takeover prone service that either do not
+/// resolve any more, for the services where that is enough, or whose page
+/// shows the service's fingerprint. Only script output (http-title and
+/// friends) of scans that reached the address by the name counts, the bare
+/// address of a shared provider always serves its "not found" page. With
+/// tags only names of domain rows carrying all of them are checked.
+pub fn takeover(db: &Db, filter: ScopeFilter, tags: &[String]) -> Result<Vec<Candidate>> {
+    let fingerprints = Fingerprint::all()?;
+    let rules = db.scope_rules()?;
+    let tagged: Option<HashSet<String>> = if tags.is_empty() {
+	None
+    } else {
+	Some(db.domains(true, ScopeFilter::Any, tags)?.into_iter().map(|domain| domain.domain).collect())
+    };
+    let mut candidates: Vec<Candidate> = vec![];
+
+    for (name, target) in db.cname_records()? {
+	let fingerprint = match fingerprints.iter().find(|fingerprint| fingerprint.matches_cname(&target)) {
+	    Some(fingerprint) => fingerprint,
+	    None => continue,
+	};
+	// hops inside the provider's own zone are not ours to take over
+	if fingerprint.matches_cname(&name) || candidates.iter().any(|candidate| candidate.domain == name) {
+	    continue;
+	}
+	if tagged.as_ref().is_some_and(|tagged| !tagged.contains(&name)) {
+	    continue;
+	}
+
+	let ips = db.resolve(&name)?;
+	let scope = ips.iter().fold(rules.name(&name), |scope, ip| scope.combine(rules.ip(ip)));
+	if !filter.allows(&rules, scope) {
+	    continue;
+	}
+	let reason = if ips.is_empty() {
+	    Some(Reason::Unresolved).filter(|_| fingerprint.nxdomain)
+	} else {
+	    let mut found = None;
+	    for ip in &ips {
+		found = db.vhost_outputs(ip, &name)?
+		    .iter()
+		    .find_map(|output| fingerprint.matches_output(output))
+		    .map(|matched| Reason::Fingerprint(matched.to_string()));
+		if found.is_some() {
+		    break;
+		}
+	    }
+	    found
+	};
+
+	if let Some(reason) = reason {
+	    candidates.push(Candidate {
+		domain: name,
+		cname: target,
+		service: fingerprint.service.clone(),
+		reason,
+	    });
+	}
+    }
+    Ok(candidates)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::dnsx::Dnsx;
+    use crate::parser::nmap::Nmap;
+    use crate::db::scope::Rule;
+    use crate::db::tag::{Condition, Entity};
+
+
+    const DNSX_JSON: &str = r#"{"host":"old.example.com","cname":["example-old.us-east-1.elasticbeanstalk.com"],"status_code":"NOERROR"}
+{"host":"app.example.com","cname":["example-app.herokuapp.com"],"status_code":"NOERROR"}
+{"host":"blog.example.com","cname":["blog.notgithub.io.example.net"],"status_code":"NOERROR"}
+{"host":"docs.example.com","cname":["example.github.io"],"a":["185.199.108.153"],"status_code":"NOERROR"}
+{"host":"pages.example.com","cname":["example-live.github.io"],"a":["185.199.108.153"],"status_code":"NOERROR"}
+{"host":"shop.example.com","cname":["shops.myshopify.com"],"status_code":"NOERROR"}
+{"host":"shops.myshopify.com","a":["23.227.38.65"],"status_code":"NOERROR"}
+{"host":"www.example.com","cname":["example.com"],"a":["93.184.216.34"],"status_code":"NOERROR"}
+"#;
+
+    // one target scanned for its web pages, by name or by address
+    const NMAP_TARGET: &str = r#"
+<nmaprun scanner="nmap" args="nmap -p 443 --script http-title,http-enum TARGET" start="1760000000" startstr="Thu Oct  9 08:53:20 2025" version="7.94" xmloutputversion="1.05">
+<host starttime="1760000000" endtime="1760000100"><status state="up" reason="syn-ack" reason_ttl="0"/>
+<address addr="ADDRESS" addrtype="ipv4"/>
+<hostnames>
+HOSTNAME
+</hostnames>
+<ports>
+<port protocol="tcp" portid="443"><state state="open" reason="syn-ack" reason_ttl="0"/><service name="http" tunnel="ssl" method="probed" conf="10"/><script id="SCRIPT" output="OUTPUT"></script></port>
+</ports>
+</host>
+</nmaprun>
+"#;
+
+    fn scan(db: &Db, address: &str, name: Option<&str>, script: &str, output: &str) {
+	let xml = NMAP_TARGET
+	    .replace("TARGET", name.unwrap_or(address))
+	    .replace("ADDRESS", address)
+	    .replace("HOSTNAME", &name.map_or("".into(), |name| format!(r#"<hostname name="{}" type="user"/>"#, name)))
+	    .replace("SCRIPT", script)
+	    .replace("OUTPUT", output);
+	db.insert_nmap_scan(Nmap::new(xml.as_bytes()).unwrap()).unwrap();
+    }
+
+
+    #[test]
+    fn parse_fingerprints() {
+	let fingerprints = Fingerprint::all().unwrap();
+	let github = fingerprints.iter().find(|f| f.service == "GitHub Pages").unwrap();
+	assert!(github.matches_cname("example.github.io"));
+	assert!(github.matches_cname("github.io"));
+	assert!(!github.matches_cname("example.com"));
+	assert!(!github.matches_cname("notgithub.io"));
+	assert!(!github.matches_cname("example.github.io.example.net"));
+	assert!(github.matches_output("404\nThere isn't a GitHub Pages site here.").is_some());
+    }
+
+    #[test]
+    fn takeover_candidates() {
+	let db = Db::new(":memory:").unwrap();
+	db.create_table().unwrap();
+	db.insert_dnsx_scan(Dnsx::new(DNSX_JSON.as_bytes()).unwrap()).unwrap();
+	// the bare address of GitHub Pages serves the "not found" page, the live
+	// pages.example.com its own
+	scan(&db, "185.199.108.153", None, "http-enum", "There isn&apos;t a GitHub Pages site here.");
+	scan(&db, "185.199.108.153", Some("docs.example.com"), "http-enum", "There isn&apos;t a GitHub Pages site here.");
+	scan(&db, "185.199.108.153", Some("pages.example.com"), "http-enum", "/: Example Docs");
+	scan(&db, "23.227.38.65", Some("shops.myshopify.com"), "http-title", "Example Shop");
+	// the bare address and each name keep their own output
+	let vhosts: Vec<Option<String>> = db.scripts().unwrap().into_iter().map(|script| script.vhost).collect();
+	assert!(vhosts == vec![Some("shops.myshopify.com".into()), None, Some("docs.example.com".into()), Some("pages.example.com".into())]);
+
+	let candidates = takeover(&db, ScopeFilter::Default, &[]).unwrap();
+	assert!(candidates.len() == 2);
+	assert!(candidates[0].domain == "docs.example.com");
+	assert!(candidates[0].service == "GitHub Pages");
+	assert!(candidates[0].reason == Reason::Fingerprint("There isn't a GitHub Pages site here.".into()));
+	assert!(candidates[1].domain == "old.example.com");
+	// the dangling herokuapp.com name is left out, Heroku does not hand
+	// out app names that way
+	assert!(candidates[1].cname == "example-old.us-east-1.elasticbeanstalk.com");
+	assert!(candidates[1].reason == Reason::Unresolved);
+
+	db.add_scope_rule(&Rule::parse(false, "old.example.com").unwrap()).unwrap();
+	assert!(takeover(&db, ScopeFilter::Default, &[]).unwrap().len() == 1);
+	assert!(takeover(&db, ScopeFilter::Any, &[]).unwrap().len() == 2);
+
+	let condition = Condition::parse("domain=docs.example.com").unwrap();
+	assert!(db.tag("prod", Entity::Domain, &[condition], true).unwrap() == 1);
+	let candidates = takeover(&db, ScopeFilter::Any, &["prod".to_string()]).unwrap();
+	assert!(candidates.len() == 1);
+	assert!(candidates[0].domain == "docs.example.com");
+    }
+}
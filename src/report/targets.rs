@@ -0,0 +1,357 @@
+use std::collections::HashSet;
+use std::net::IpAddr;
+
+use crate::db::sqlite::{Db, ServiceEntry};
+use crate::db::scope::ScopeFilter;
+use crate::db::filter::{Filter, Table};
+use crate::db::tag::Entity;
+use super::shared::shared_ips;
+
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+// nmap names of web services, nessus and the TLS scanners are mapped to them
+const WEB_SERVICES: [&str; 5] = ["http", "https", "http-alt", "https-alt", "http-proxy"];
+
+// One line per target in what the follow-up tools read:
+//   ip        10.0.0.1              nmap -iL
+//   ipport    10.0.0.1:443          masscan, naabu
+//   url       https://10.0.0.1:443  httpx, nuclei, ffuf
+//   hostname  www.example.com       vhost-aware tools
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Ip,
+    IpPort,
+    Url,
+    Hostname,
+}
+
+impl Format {
+    pub fn parse(value: &str) -> Result<Self> {
+	match value {
+	    "ip" => Ok(Format::Ip),
+	    "ipport" => Ok(Format::IpPort),
+	    "url" => Ok(Format::Url),
+	    "hostname" => Ok(Format::Hostname),
+	    _ => Err(format!("unknown target format {}, use ip, ipport, url or hostname", value).into()),
+	}
+    }
+}
+
+// Which targets end up in the list. Services and ports select open services,
+// the other filters apply to what is listed: hosts for ip, services for
+// ipport and url, names for hostname. Times are compared with first_seen.
+// A filter expression selects services, or names for hostname.
+#[derive(Debug, Clone)]
+pub struct TargetFilter {
+    pub services: Vec<String>,
+    pub ports: Vec<u16>,
+    pub tags: Vec<String>,
+    pub scope: ScopeFilter,
+    pub since: Option<String>,
+    pub until: Option<String>,
+    pub filter: Option<Filter>,
+}
+
+impl Default for TargetFilter {
+    fn default() -> Self {
+	Self {
+	    services: vec![],
+	    ports: vec![],
+	    tags: vec![],
+	    scope: ScopeFilter::Default,
+	    since: None,
+	    until: None,
+	    filter: None,
+	}
+    }
+}
+
+impl TargetFilter {
+    fn selects_services(&self) -> bool {
+	!self.services.is_empty() || !self.ports.is_empty() || self.filter.is_some()
+    }
+
+    fn matches_service(&self, service: &ServiceEntry) -> bool {
+	service.state.as_deref() == Some("open")
+	    && (self.ports.is_empty() || self.ports.contains(&service.port))
+	    && (self.services.is_empty() || service.service_name.as_ref()
+		.is_some_and(|name| self.services.iter().any(|wanted| wanted.eq_ignore_ascii_case(name))))
+    }
+
+    /// since is inclusive, until includes everything it is a prefix of, so
+    /// --until 2026-10-05 covers that whole day.
+    fn matches_time(&self, first_seen: &Option<String>) -> bool {
+	if self.since.is_none() && self.until.is_none() {
+	    return true;
+	}
+	let first_seen = match first_seen {
+	    Some(first_seen) => first_seen.as_str(),
+	    None => return false,
+	};
+	self.since.as_ref().is_none_or(|since| first_seen >= since.as_str())
+	    && self.until.as_ref().is_none_or(|until| first_seen.get(..until.len()).unwrap_or(first_seen) <= until.as_str())
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Targets {
+    pub targets: Vec<String>,
+    // unapproved addresses on shared infrastructure that were left out
+    pub excluded: Vec<String>,
+}
+
+/// The target list in the given format, sorted and without duplicates.
+/// Addresses on shared infrastructure are only listed once approved.
+pub fn targets(db: &Db, format: Format, filter: &TargetFilter) -> Result<Targets> {
+    let rules = db.scope_rules()?;
+    let shared: HashSet<String> = shared_ips(db)?.into_iter().collect();
+    let mut excluded = HashSet::new();
+    let mut keep = |ip: &str| {
+	if shared.contains(ip) {
+	    excluded.insert(ip.to_string());
+	    false
+	} else {
+	    true
+	}
+    };
+
+    // open services the service, port and expression filters select
+    let (service_filter, domain_filter) = match format {
+	Format::Hostname => (None, filter.filter.as_ref()),
+	_ => (filter.filter.as_ref(), None),
+    };
+    let selected = db.filtered(Table::Service, service_filter)?;
+    let services: Vec<ServiceEntry> = db.services()?
+	.into_iter()
+	.filter(|service| filter.matches_service(service))
+	.filter(|service| selected.as_ref().is_none_or(|selected| selected.contains(&service.id)))
+	.collect();
+    let service_ips: HashSet<&str> = services.iter().map(|service| service.ip.as_str()).collect();
+
+    let mut targets = vec![];
+    match format {
+	Format::Ip => {
+	    let tagged = db.tagged(Entity::Host, &filter.tags)?;
+	    for host in db.hosts()? {
+		if filter.selects_services() && !service_ips.contains(host.ip.as_str()) {
+		    continue;
+		}
+		if !filter.scope.allows(&rules, host.scope)
+		    || tagged.as_ref().is_some_and(|tagged| !tagged.contains(&host.id))
+		    || !filter.matches_time(&host.first_seen)
+		    || !keep(&host.ip) {
+		    continue;
+		}
+		targets.push(host.ip);
+	    }
+	},
+	Format::IpPort | Format::Url => {
+	    let tagged = db.tagged(Entity::Service, &filter.tags)?;
+	    // names pointing at a web server, for its virtual hosts
+	    let names = if format == Format::Url {
+		db.domains(false, filter.scope, &[])?
+	    } else {
+		vec![]
+	    };
+	    for service in &services {
+		if !filter.scope.allows(&rules, service.scope)
+		    || tagged.as_ref().is_some_and(|tagged| !tagged.contains(&service.id))
+		    || !filter.matches_time(&service.first_seen)
+		    || !keep(&service.ip) {
+		    continue;
+		}
+		if format == Format::IpPort {
+		    targets.push(format!("{}:{}", host(&service.ip), service.port));
+		    continue;
+		}
+		let scheme = match scheme(service) {
+		    Some(scheme) => scheme,
+		    None => continue,
+		};
+		targets.push(format!("{}://{}:{}", scheme, host(&service.ip), service.port));
+		for name in names.iter().filter(|name| name.ip.as_ref() == Some(&service.ip)) {
+		    targets.push(format!("{}://{}:{}", scheme, name.domain, service.port));
+		}
+	    }
+	},
+	Format::Hostname => {
+	    let selected = db.filtered(Table::Domain, domain_filter)?;
+	    for name in db.domains(false, filter.scope, &filter.tags)? {
+		if selected.as_ref().is_some_and(|selected| !selected.contains(&name.id)) {
+		    continue;
+		}
+		if (!filter.services.is_empty() || !filter.ports.is_empty()) && !name.ip.as_deref().is_some_and(|ip| service_ips.contains(ip)) {
+		    continue;
+		}
+		if !filter.matches_time(&name.first_seen) || !name.ip.as_deref().is_none_or(&mut keep) {
+		    continue;
+		}
+		targets.push(name.domain);
+	    }
+	},
+    }
+    // ip and ipport come sorted by address from the db
+    if format == Format::Hostname || format == Format::Url {
+	targets.sort();
+    }
+    targets.dedup();
+    let mut excluded: Vec<String> = excluded.into_iter().collect();
+    excluded.sort();
+    Ok(Targets {
+	targets,
+	excluded,
+    })
+}
+
+// http or https for web services, whatever the TLS scanners found is https
+fn scheme(service: &ServiceEntry) -> Option<&'static str> {
+    let name = service.service_name.as_deref()?;
+    if !WEB_SERVICES.contains(&name) {
+	return None;
+    }
+    if service.ssl || name.starts_with("https") {
+	Some("https")
+    } else {
+	Some("http")
+    }
+}
+
+// IPv6 addresses in brackets, so the port can follow
+fn host(ip: &str) -> String {
+    match ip.parse::<IpAddr>() {
+	Ok(IpAddr::V6(ip)) => format!("[{}]", ip),
+	_ => ip.to_string(),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::scope::Rule;
+    use crate::db::tag::Condition;
+    use crate::db::filter::parse_time;
+    use crate::parser::amass::Amass;
+    use crate::parser::nmap::Nmap;
+
+
+    // one run per host, serde-xml-rs trips over the second host of a run
+    const NMAP_HOSTS: [&str; 3] = [
+	r#"<host starttime="1643060432" endtime="1643060451">
+<status state="up" reason="syn-ack" reason_ttl="0"/>
+<address addr="198.51.100.7" addrtype="ipv4"/>
+<ports>
+    <port protocol="tcp" portid="22"><state state="open" reason="syn-ack" reason_ttl="0"/><service name="ssh" product="OpenSSH" method="probed" conf="10"/></port>
+    <port protocol="tcp" portid="80"><state state="open" reason="syn-ack" reason_ttl="0"/><service name="http" product="nginx" method="probed" conf="10"/></port>
+    <port protocol="tcp" portid="8443"><state state="open" reason="syn-ack" reason_ttl="0"/><service name="http" product="nginx" tunnel="ssl" method="probed" conf="10"/></port>
+    <port protocol="tcp" portid="3306"><state state="filtered" reason="no-response" reason_ttl="0"/><service name="mysql" method="table" conf="3"/></port>
+</ports>
+</host>"#,
+	r#"<host starttime="1643060432" endtime="1643060451">
+<status state="up" reason="syn-ack" reason_ttl="0"/>
+<address addr="2001:db8::10" addrtype="ipv6"/>
+<ports>
+    <port protocol="tcp" portid="443"><state state="open" reason="syn-ack" reason_ttl="0"/><service name="https" method="table" conf="3"/></port>
+</ports>
+</host>"#,
+	r#"<host starttime="1643060432" endtime="1643060451">
+<status state="up" reason="syn-ack" reason_ttl="0"/>
+<address addr="104.19.128.108" addrtype="ipv4"/>
+<ports>
+    <port protocol="tcp" portid="443"><state state="open" reason="syn-ack" reason_ttl="0"/><service name="http" product="Cloudflare http proxy" tunnel="ssl" method="probed" conf="10"/></port>
+</ports>
+</host>"#,
+    ];
+
+    const AMASS_JSON: &str = r#"{"name":"vpn.example.com","domain":"example.com","addresses":[{"ip":"198.51.100.7","cidr":"198.51.100.0/24","asn":64500,"desc":"EXAMPLE-AS - Example hosting"}],"tag":"dns","sources":["DNS"]}
+{"name":"www.example.com","domain":"example.com","addresses":[{"ip":"104.19.128.108","cidr":"104.16.0.0/14","asn":13335,"desc":"CLOUDFLARENET - Cloudflare, Inc."}],"tag":"dns","sources":["DNS"]}
+{"name":"dev.example.com","domain":"example.com","addresses":[{"ip":"2001:db8::10","cidr":"2001:db8::/32","asn":64500,"desc":"EXAMPLE-AS - Example hosting"}],"tag":"dns","sources":["DNS"]}"#;
+
+
+    fn test_db() -> Db {
+	let db = Db::new(":memory:").unwrap();
+	db.create_table().unwrap();
+	for host in NMAP_HOSTS {
+	    let xml = format!(r#"<nmaprun scanner="nmap" args="nmap -sV" start="1643060432" startstr="Mon Jan 24 21:40:32 2022" version="7.92" xmloutputversion="1.05">{}</nmaprun>"#, host);
+	    db.insert_nmap_scan(Nmap::new(xml.as_bytes()).unwrap()).unwrap();
+	}
+	db.insert_amass_scan(Amass::new(AMASS_JSON.as_bytes()).unwrap()).unwrap();
+	db.add_scope_rule(&Rule::parse(true, "*.example.com").unwrap()).unwrap();
+	db.apply_scope("test").unwrap();
+	db
+    }
+
+    #[test]
+    fn target_lists() {
+	let db = test_db();
+	let filter = TargetFilter::default();
+
+	// the Cloudflare address is left out until approved
+	let ips = targets(&db, Format::Ip, &filter).unwrap();
+	assert!(ips.targets == vec!["198.51.100.7", "2001:db8::10"]);
+	assert!(ips.excluded == vec!["104.19.128.108"]);
+	let ipports = targets(&db, Format::IpPort, &filter).unwrap().targets;
+	assert!(ipports == vec!["198.51.100.7:22", "198.51.100.7:80", "198.51.100.7:8443", "[2001:db8::10]:443"]);
+	let urls = targets(&db, Format::Url, &filter).unwrap().targets;
+	assert!(urls == vec![
+	    "http://198.51.100.7:80",
+	    "http://vpn.example.com:80",
+	    "https://198.51.100.7:8443",
+	    "https://[2001:db8::10]:443",
+	    "https://dev.example.com:443",
+	    "https://vpn.example.com:8443",
+	]);
+	let names = targets(&db, Format::Hostname, &filter).unwrap().targets;
+	assert!(names == vec!["dev.example.com", "vpn.example.com"]);
+
+	db.approve_shared("104.19.128.108", true).unwrap();
+	let urls = targets(&db, Format::Url, &filter).unwrap();
+	assert!(urls.excluded.is_empty());
+	assert!(urls.targets.contains(&"https://www.example.com:443".to_string()));
+    }
+
+    #[test]
+    fn filter_targets() {
+	let db = test_db();
+	let filter = TargetFilter {
+	    ports: vec![22, 443],
+	    ..Default::default()
+	};
+	let ipports = targets(&db, Format::IpPort, &filter).unwrap().targets;
+	assert!(ipports == vec!["198.51.100.7:22", "[2001:db8::10]:443"]);
+	let filter = TargetFilter {
+	    services: vec!["SSH".into()],
+	    ..Default::default()
+	};
+	assert!(targets(&db, Format::Ip, &filter).unwrap().targets == vec!["198.51.100.7"]);
+	assert!(targets(&db, Format::Hostname, &filter).unwrap().targets == vec!["vpn.example.com"]);
+	let filter = TargetFilter {
+	    filter: Some(Filter::parse("ssl = true").unwrap()),
+	    ..Default::default()
+	};
+	assert!(targets(&db, Format::Ip, &filter).unwrap().targets == vec!["198.51.100.7"]);
+	assert!(targets(&db, Format::IpPort, &filter).unwrap().targets == vec!["198.51.100.7:8443"]);
+	let filter = TargetFilter {
+	    filter: Some(Filter::parse("name ~ dev").unwrap()),
+	    ..Default::default()
+	};
+	assert!(targets(&db, Format::Hostname, &filter).unwrap().targets == vec!["dev.example.com"]);
+
+	db.tag("retest", Entity::Service, &[Condition::parse("port=8443").unwrap()], true).unwrap();
+	let filter = TargetFilter {
+	    tags: vec!["retest".into()],
+	    ..Default::default()
+	};
+	assert!(targets(&db, Format::Url, &filter).unwrap().targets == vec!["https://198.51.100.7:8443", "https://vpn.example.com:8443"]);
+
+	db.conn.execute("UPDATE service SET first_seen = '2026-10-05 09:30:00' WHERE port = 80").unwrap();
+	let filter = TargetFilter {
+	    since: Some(parse_time("2026-10-05").unwrap()),
+	    until: Some(parse_time("2026-10-05").unwrap()),
+	    ..Default::default()
+	};
+	assert!(targets(&db, Format::IpPort, &filter).unwrap().targets == vec!["198.51.100.7:80"]);
+	assert!(Format::parse("nmap").is_err());
+    }
+}